@@ -39,11 +39,14 @@
 //! - Public API already written for this task must not be changed (any change to
 //!   the public API items must be considered as breaking change).
 
-#![allow(unused_variables, dead_code)]
-
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::slice;
+use std::time::{Duration, SystemTime};
+use rand::{Rng, RngCore};
+use crate::clock::{Clock, SystemClock};
 use crate::commands::CommandHandler;
+use crate::events::{Event, EventSink, SequencedEvent};
 use crate::queries::QueryHandler;
 
 /// All possible errors of the [`UrlShortenerService`].
@@ -59,17 +62,315 @@ pub enum ShortenerError {
     /// This error occurs when the provided [`Slug`] does not map to any existing
     /// short link.
     SlugNotFound,
+
+    /// This error occurs when [`SequencedEvent`]s being applied don't
+    /// continue the current event log without gaps.
+    ///
+    /// [`SequencedEvent`]: events::SequencedEvent
+    SequenceGap,
+
+    /// This error occurs when an attempt is made to shorten a [`Url`] already
+    /// shortened, while URLs are required to be unique.
+    UrlAlreadyInUse,
+
+    /// This error occurs when the provided [`Slug`] maps to a disabled short
+    /// link.
+    LinkDisabled,
+
+    /// This error occurs when the provided [`Slug`] maps to an expired short
+    /// link.
+    LinkExpired,
+
+    /// This error occurs when split variants are provided without any of them
+    /// having a positive weight.
+    InvalidSplitWeights,
+
+    /// This error occurs when seeding redirects would overflow the redirect
+    /// counter of a short link.
+    CounterOverflow,
+
+    /// This error occurs when the provided [`Slug`] maps to a short link not
+    /// allowed in the visitor's country.
+    CountryBlocked,
+
+    /// This error occurs when a conditional command is rejected, as its
+    /// condition doesn't hold for the short link.
+    PreconditionFailed,
+
+    /// This error occurs when an HTTP status not being a redirect one is
+    /// provided for a short link.
+    InvalidRedirectStatus,
+
+    /// This error occurs when a zero-length, inverted or out-of-range interval
+    /// is provided.
+    InvalidInterval,
+
+    /// This error occurs when the provided [`Slug`] maps to a short link not
+    /// allowed to be redirected through from the visitor's referer.
+    RefererNotAllowed,
+
+    /// This error occurs when the event log cannot be split at the provided
+    /// moment, as the events made before it cannot be replaced with a
+    /// snapshot of the state they lead to without changing the current one.
+    LogNotSplittable,
+
+    /// This error occurs when following short links pointing to other short
+    /// links of the service comes back to an already visited one.
+    RedirectLoop,
+
+    /// This error occurs when the provided [`Slug`] maps to a short link whose
+    /// [`Url`] has been changed the maximum allowed number of times already.
+    ChangeLimitExceeded,
+
+    /// This error occurs when the provided [`Slug`] maps to a short link not
+    /// gone live yet.
+    NotYetActive,
+
+    /// This error occurs when the provided redirect sample rate is not in
+    /// `0.0..=1.0` range, or is zero.
+    InvalidSampleRate,
+
+    /// This error occurs when the provided custom [`Slug`] has the exact format
+    /// of the generated ones, while they're required to be distinct.
+    SlugFormatReserved,
+
+    /// This error occurs when the provided link template contains an unknown
+    /// or unclosed placeholder.
+    InvalidTemplate,
+
+    /// This error occurs when the provided time-to-live of a short link is
+    /// outside the allowed range.
+    TtlOutOfBounds,
+
+    /// This error occurs when the provided [`Slug`] maps to a short link
+    /// having the maximum allowed number of aliases already.
+    AliasLimitExceeded,
+}
+
+impl ShortenerError {
+    /// Returns a stable, machine-readable `snake_case` identifier of this
+    /// error, suitable for API responses.
+    ///
+    /// Unlike human-readable messages, these codes are part of the public
+    /// contract and must never change for an existing variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidUrl => "invalid_url",
+            Self::SlugAlreadyInUse => "slug_already_in_use",
+            Self::SlugNotFound => "slug_not_found",
+            Self::SequenceGap => "sequence_gap",
+            Self::UrlAlreadyInUse => "url_already_in_use",
+            Self::LinkDisabled => "link_disabled",
+            Self::LinkExpired => "link_expired",
+            Self::InvalidSplitWeights => "invalid_split_weights",
+            Self::CounterOverflow => "counter_overflow",
+            Self::CountryBlocked => "country_blocked",
+            Self::PreconditionFailed => "precondition_failed",
+            Self::InvalidRedirectStatus => "invalid_redirect_status",
+            Self::InvalidInterval => "invalid_interval",
+            Self::RefererNotAllowed => "referer_not_allowed",
+            Self::LogNotSplittable => "log_not_splittable",
+            Self::RedirectLoop => "redirect_loop",
+            Self::ChangeLimitExceeded => "change_limit_exceeded",
+            Self::NotYetActive => "not_yet_active",
+            Self::InvalidSampleRate => "invalid_sample_rate",
+            Self::SlugFormatReserved => "slug_format_reserved",
+            Self::InvalidTemplate => "invalid_template",
+            Self::TtlOutOfBounds => "ttl_out_of_bounds",
+            Self::AliasLimitExceeded => "alias_limit_exceeded",
+        }
+    }
+}
+
+/// All possible errors of the [`UrlShortenerService`] configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigError {
+    /// This error occurs when no generated [`Slug`] could ever pass the
+    /// denylist, given the slug alphabet and length.
+    ImpossibleSlugConstraints,
+
+    /// This error occurs when the default redirect sample rate is not in
+    /// `0.0..=1.0` range, or is zero.
+    InvalidSampleRate,
+}
+
+/// Violations of the [`Slug`] uniqueness invariant of the
+/// [`UrlShortenerService`], found by
+/// [`UrlShortenerService::verify_slug_integrity()`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SlugIntegrityError {
+    /// This error occurs when the [`Slug`] is an alias of itself.
+    SelfAlias(Slug),
+
+    /// This error occurs when the [`Slug`] is both a live short link and an
+    /// alias of another one.
+    LiveAlias(Slug),
+
+    /// This error occurs when the [`Slug`] is an alias of a short link which
+    /// doesn't exist.
+    DanglingAlias(Slug),
+
+    /// This error occurs when several live short links share the same
+    /// canonical form of the [`Slug`] within its namespace.
+    DuplicateSlug(Slug),
 }
 
 /// A unique string (or alias) that represents the shortened version of the
 /// URL.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Slug(pub String);
 
+impl Slug {
+    /// Indicates whether the provided `slug` is a well-formed hardcoded one
+    /// (see [`slug!`]): non-empty and consisting of ASCII alphanumeric
+    /// characters, `-`, `_` and `/` only.
+    pub const fn is_valid_literal(slug: &str) -> bool {
+        let bytes = slug.as_bytes();
+        if bytes.is_empty() {
+            return false;
+        }
+        let mut i = 0;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'/')) {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+}
+
+/// Creates a [`Slug`] out of the provided string literal, failing to compile
+/// if it's not well-formed (see [`Slug::is_valid_literal()`]).
+///
+/// Useful for hardcoded [`Slug`]s (like the reserved ones), so a typo in them
+/// is caught immediately:
+///
+/// ```compile_fail
+/// let reserved = slug!("not a slug");
+/// ```
+#[macro_export]
+macro_rules! slug {
+    ($slug:literal) => {{
+        const _: () = assert!(
+            $crate::Slug::is_valid_literal($slug),
+            "invalid slug literal",
+        );
+        $crate::Slug(String::from($slug))
+    }};
+}
+
 /// The original URL that the short link points to.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Url(pub String);
 
+impl Url {
+    /// Returns the host part of this [`Url`], if it has any.
+    ///
+    /// Userinfo and port are stripped, while IPv6 literals keep their
+    /// brackets.
+    fn host(&self) -> Option<&str> {
+        let (_, rest) = self.0.split_once("://")?;
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host_port =
+            authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+        let host = if host_port.starts_with('[') {
+            host_port.find(']').map(|end| &host_port[..=end])?
+        } else {
+            host_port.split(':').next()?
+        };
+        (!host.is_empty()).then_some(host)
+    }
+
+    /// Indicates whether this [`Url`] is an absolute one, having a scheme and
+    /// a host.
+    fn is_absolute(&self) -> bool {
+        let Some((scheme, _)) = self.0.split_once("://") else {
+            return false;
+        };
+        let mut chars = scheme.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+            && self.host().is_some()
+    }
+
+    /// Returns the scheme of this [`Url`], or an empty string if it has none.
+    fn scheme(&self) -> &str {
+        self.0.split_once("://").map_or("", |(s, _)| s)
+    }
+
+    /// Returns the heuristic [`UrlWarning`]s about this [`Url`].
+    fn warnings(&self) -> Vec<UrlWarning> {
+        let mut warnings = Vec::new();
+        let scheme = self.scheme();
+        if !["http", "https"]
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(scheme))
+        {
+            warnings.push(UrlWarning::SuspiciousScheme);
+        }
+        if let Some(host) = self.host() {
+            let is_ip = host.starts_with('[')
+                || host.parse::<std::net::Ipv4Addr>().is_ok();
+            let tld = host.trim_end_matches('.').rsplit_once('.');
+            if !is_ip && tld.is_none_or(|(_, tld)| tld.is_empty()) {
+                warnings.push(UrlWarning::NoTld);
+            }
+        }
+        warnings
+    }
+
+    /// Returns this [`Url`] with its host lowercased, if it has any.
+    fn with_lowercased_host(&self) -> Url {
+        let Some(host) = self.host() else {
+            return self.clone();
+        };
+        let start = host.as_ptr() as usize - self.0.as_ptr() as usize;
+        let end = start + host.len();
+        Url(format!(
+            "{}{}{}",
+            &self.0[..start],
+            host.to_lowercase(),
+            &self.0[end..],
+        ))
+    }
+
+    /// Returns the normalized form of this [`Url`]: with lowercased scheme and
+    /// host, without a default port (`80` for `http` and `443` for `https`),
+    /// and with `/` as an empty path.
+    ///
+    /// [`Url`]s without a scheme are returned as is.
+    fn normalized(&self) -> Url {
+        let Some((scheme, rest)) = self.0.split_once("://") else {
+            return self.clone();
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, tail) = rest.split_at(authority_end);
+        let (userinfo, host_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_port)) => (Some(userinfo), host_port),
+            None => (None, authority),
+        };
+
+        let mut host_port = host_port.to_ascii_lowercase();
+        let default_port = match scheme.as_str() {
+            "http" => ":80",
+            "https" => ":443",
+            _ => "",
+        };
+        if !default_port.is_empty() && host_port.ends_with(default_port) {
+            host_port.truncate(host_port.len() - default_port.len());
+        }
+
+        let userinfo = userinfo.map(|u| format!("{u}@")).unwrap_or_default();
+        let slash = if tail.starts_with('/') { "" } else { "/" };
+        Url(format!("{scheme}://{userinfo}{host_port}{slash}{tail}"))
+    }
+}
+
 /// Shortened URL representation.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ShortLink {
@@ -81,6 +382,19 @@ pub struct ShortLink {
     pub url: Url,
 }
 
+impl ShortLink {
+    /// Creates a new [`ShortLink`] out of the provided [`Slug`] and [`Url`].
+    pub fn from_parts(slug: Slug, url: Url) -> Self {
+        Self { slug, url }
+    }
+}
+
+impl From<ShortLink> for (Slug, Url) {
+    fn from(link: ShortLink) -> Self {
+        (link.slug, link.url)
+    }
+}
+
 /// Statistics of the [`ShortLink`].
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stats {
@@ -91,6 +405,481 @@ pub struct Stats {
     pub redirects: u64,
 }
 
+/// Breakdown of the count of redirects of a [`ShortLink`] by their origin,
+/// returned by [`UrlShortenerService::get_stats_detailed_counts()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountBreakdown {
+    /// Count of redirects made through the [`ShortLink`].
+    pub organic: u64,
+
+    /// Count of redirects seeded from elsewhere (see
+    /// [`UrlShortenerService::handle_seed_redirects()`]).
+    pub seeded: u64,
+
+    /// Count of all the redirects, as reported by the [`Stats`].
+    pub total: u64,
+}
+
+/// Outcome of importing an access log with the
+/// [`UrlShortenerService::handle_import_access_log()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Count of the imported redirects.
+    pub imported: u64,
+
+    /// Count of the lines referring to unknown [`Slug`]s.
+    pub unknown_slugs: u64,
+
+    /// Count of the lines that couldn't be parsed.
+    pub parse_errors: u64,
+}
+
+/// Summary of the [`UrlShortenerService`] health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Health {
+    /// Count of all the [`ShortLink`]s.
+    pub total_links: usize,
+
+    /// Count of the [`ShortLink`]s which can be redirected through right now.
+    pub active_links: usize,
+
+    /// Length of the event log.
+    pub event_log_len: usize,
+
+    /// Indicator whether replaying the event log reproduces the current
+    /// state.
+    pub consistency_ok: bool,
+
+    /// Count of the expired [`ShortLink`]s still kept.
+    pub expired_pending_cleanup: usize,
+}
+
+/// Result of a redirect along with everything needed to respond to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnrichedRedirect {
+    /// [`Url`] to redirect to.
+    pub url: Url,
+
+    /// HTTP status to redirect with.
+    pub status: u16,
+
+    /// Content type to respond with, if any.
+    pub content_type: Option<String>,
+
+    /// Key-value metadata attached to the [`ShortLink`].
+    pub metadata: HashMap<String, String>,
+}
+
+/// All the attributes of a [`ShortLink`] to create at once with
+/// [`UrlShortenerService::handle_create_full()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateRequest {
+    /// [`Url`] to shorten.
+    pub url: Url,
+
+    /// [`Slug`] to use, or [`None`] to generate one.
+    pub slug: Option<Slug>,
+
+    /// Time to live of the [`ShortLink`], if it expires at all.
+    pub ttl: Option<Duration>,
+
+    /// Free-form note to attach, if any.
+    pub note: Option<String>,
+
+    /// Key-value metadata to attach.
+    pub metadata: HashMap<String, String>,
+
+    /// HTTP status to redirect with, or [`None`] for the default `302`.
+    pub redirect_status: Option<u16>,
+}
+
+/// Single operation of a [`UrlShortenerService::handle_transaction()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Creates a new [`ShortLink`] the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does.
+    Create {
+        /// [`Url`] to shorten.
+        url: Url,
+
+        /// [`Slug`] to use, or [`None`] to generate one.
+        slug: Option<Slug>,
+    },
+
+    /// Changes the [`Url`] of a [`ShortLink`] the same way as
+    /// [`CommandHandler::handle_change_short_link()`] does.
+    Change {
+        /// [`Slug`] of the [`ShortLink`] to change.
+        slug: Slug,
+
+        /// New [`Url`] to point to.
+        new_url: Url,
+    },
+
+    /// Deletes a [`ShortLink`] the same way as
+    /// [`UrlShortenerService::handle_delete()`] does.
+    Delete {
+        /// [`Slug`] of the [`ShortLink`] to delete.
+        slug: Slug,
+    },
+
+    /// Renames a [`ShortLink`], keeping all its attributes and statistics.
+    Rename {
+        /// [`Slug`] of the [`ShortLink`] to rename.
+        slug: Slug,
+
+        /// New [`Slug`] of the [`ShortLink`].
+        new_slug: Slug,
+    },
+
+    /// Sets a metadata entry of a [`ShortLink`] the same way as
+    /// [`UrlShortenerService::handle_set_metadata()`] does.
+    SetMetadata {
+        /// [`Slug`] of the [`ShortLink`] to set the entry of.
+        slug: Slug,
+
+        /// Key of the entry.
+        key: String,
+
+        /// Value of the entry, or [`None`] to remove it.
+        value: Option<String>,
+    },
+
+    /// Sets the note of a [`ShortLink`] the same way as
+    /// [`UrlShortenerService::handle_set_note()`] does.
+    SetNote {
+        /// [`Slug`] of the [`ShortLink`] to set the note of.
+        slug: Slug,
+
+        /// Note to set, or [`None`] to remove it.
+        note: Option<String>,
+    },
+
+    /// Disables a [`ShortLink`] the same way as
+    /// [`UrlShortenerService::handle_disable()`] does.
+    Disable {
+        /// [`Slug`] of the [`ShortLink`] to disable.
+        slug: Slug,
+    },
+
+    /// Enables back a [`ShortLink`] the same way as
+    /// [`UrlShortenerService::handle_enable()`] does.
+    Enable {
+        /// [`Slug`] of the [`ShortLink`] to enable.
+        slug: Slug,
+    },
+}
+
+/// Behavior of redirecting through a disabled [`ShortLink`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisabledBehavior {
+    /// Redirect fails with a [`ShortenerError::LinkDisabled`].
+    #[default]
+    Reject,
+
+    /// Redirect succeeds, being counted separately as well (see
+    /// [`UrlShortenerService::redirects_while_disabled()`]).
+    ServeAnyway,
+}
+
+/// Policy of resolving a [`Slug`] provided more than once within a single
+/// [`UrlShortenerService::handle_create_many()`] batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatchDuplicatePolicy {
+    /// The first item with the [`Slug`] is created, while the following ones
+    /// fail with a [`ShortenerError::SlugAlreadyInUse`].
+    #[default]
+    FirstWins,
+
+    /// The last item with the [`Slug`] is created, while the preceding ones
+    /// fail with a [`ShortenerError::SlugAlreadyInUse`].
+    LastWins,
+
+    /// All the items with the [`Slug`] fail with a
+    /// [`ShortenerError::SlugAlreadyInUse`].
+    Error,
+}
+
+/// Non-fatal heuristic warning about a [`Url`] being shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UrlWarning {
+    /// The scheme is neither `http` nor `https`, and is possibly a typo (like
+    /// `htp`).
+    SuspiciousScheme,
+
+    /// The host has no top-level domain (like `localhost`), so it's unlikely
+    /// to resolve publicly.
+    NoTld,
+}
+
+/// Role of a [`Slug`] in the aliasing of [`ShortLink`]s (see
+/// [`UrlShortenerService::consolidate_duplicates()`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlugRole {
+    /// The [`Slug`] is neither an alias nor aliased.
+    Standalone,
+
+    /// The [`Slug`] is an alias of the [`ShortLink`] with the contained
+    /// [`Slug`].
+    Alias(Slug),
+
+    /// The [`Slug`] is aliased by the contained [`Slug`]s, ordered.
+    CanonicalTargetOf(Vec<Slug>),
+}
+
+/// Origin of the [`Slug`] of a [`ShortLink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlugOrigin {
+    /// The [`Slug`] was provided on creation.
+    Custom,
+
+    /// The [`Slug`] was generated randomly.
+    Generated,
+}
+
+/// Aggregate statistics of all the [`ShortLink`]s of a campaign.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CampaignStats {
+    /// Count of the [`ShortLink`]s assigned to the campaign.
+    pub links: usize,
+
+    /// Total count of redirects of the [`ShortLink`]s of the campaign.
+    pub total_redirects: u64,
+}
+
+/// Point-in-time copy of the [`Url`]s, redirect counts and metadata of all the
+/// [`ShortLink`]s along with a fingerprint of the event log, taken with
+/// [`UrlShortenerService::snapshot()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// [`Url`] and count of redirects of every [`ShortLink`] by its [`Slug`].
+    links: HashMap<Slug, (Url, u64)>,
+
+    /// [`LinkAnalytics`] of every [`ShortLink`] by its [`Slug`], if taken
+    /// (see [`UrlShortenerService::with_full_analytics_snapshot()`]).
+    analytics: Option<HashMap<Slug, LinkAnalytics>>,
+
+    /// Numeric id of every [`ShortLink`] by its [`Slug`] (see
+    /// [`UrlShortenerService::link_id()`]).
+    ids: HashMap<Slug, u64>,
+
+    /// [`SlugOrigin`] of every [`ShortLink`] by its [`Slug`].
+    origins: HashMap<Slug, SlugOrigin>,
+
+    /// Metadata of every [`ShortLink`] by its [`Slug`] (see
+    /// [`UrlShortenerService::handle_set_metadata()`]).
+    metadata: HashMap<Slug, BTreeMap<String, String>>,
+
+    /// Count of the [`Event`]s in the event log.
+    seq: u64,
+
+    /// 64-bit FNV-1a hash of the [`Event`]s in the event log.
+    log_fingerprint: u64,
+}
+
+/// Point-in-time copy of the analytics of all the [`ShortLink`]s, without
+/// their [`Url`]s and other attributes, taken with
+/// [`UrlShortenerService::analytics_snapshot()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyticsSnapshot {
+    /// [`LinkTraffic`] of every [`ShortLink`] by its [`Slug`].
+    links: HashMap<Slug, LinkTraffic>,
+}
+
+/// Redirects of a [`ShortLink`] kept in an [`AnalyticsSnapshot`].
+#[derive(Debug, Clone, PartialEq)]
+struct LinkTraffic {
+    /// Count of redirects of the [`ShortLink`].
+    redirects: u64,
+
+    /// Count of redirects seeded from elsewhere, out of the `redirects`.
+    seeded: u64,
+
+    /// Every redirect recorded individually into the event log along with
+    /// the count of redirects it represents, in chronological order.
+    clicks: Vec<(ClickRecord, u64)>,
+}
+
+/// Detailed analytics of a [`ShortLink`] kept in a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+struct LinkAnalytics {
+    /// Moment the [`ShortLink`] was created at.
+    created_at: SystemTime,
+
+    /// Every redirect recorded individually into the event log along with
+    /// the count of redirects it represents, in chronological order.
+    redirects: Vec<(ClickRecord, u64)>,
+}
+
+/// Record of a read of the [`UrlShortenerService`] (see
+/// [`UrlShortenerService::with_read_auditing()`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadEvent {
+    /// Name of the query method the read has been made with.
+    pub kind: &'static str,
+
+    /// [`Slug`] the read has been requested for, if any.
+    pub slug: Option<Slug>,
+
+    /// Moment the read has been made at.
+    pub at: SystemTime,
+}
+
+/// Single redirect (click) through a [`ShortLink`], as recorded in the event
+/// log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickRecord {
+    /// Moment the redirect was made at.
+    pub at: SystemTime,
+
+    /// Referer the redirect was made from, if known (see
+    /// [`UrlShortenerService::handle_redirect_with_referrer()`]).
+    pub referrer: Option<String>,
+
+    /// Source tag the redirect was made with, if any (see
+    /// [`UrlShortenerService::handle_redirect_with_source()`]).
+    pub source: Option<String>,
+}
+
+/// Key to sort the [`ShortLink`]s by in a [`LinkQuery`], with ties broken by
+/// [`Slug`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    /// [`Slug`] of the [`ShortLink`].
+    #[default]
+    Slug,
+
+    /// Count of redirects of the [`ShortLink`].
+    Redirects,
+
+    /// Moment the [`ShortLink`] was created at.
+    CreatedAt,
+}
+
+/// Predicate over the [`Stats`] of a [`ShortLink`] in a [`LinkQuery`].
+type StatsFilter<'a> = Box<dyn Fn(&Stats) -> bool + 'a>;
+
+/// Composable query of the [`Stats`] of the [`ShortLink`]s, built with
+/// [`UrlShortenerService::query()`].
+pub struct LinkQuery<'a> {
+    /// Service being queried.
+    service: &'a UrlShortenerService,
+
+    /// Predicates every returned [`Stats`] must satisfy.
+    filters: Vec<StatsFilter<'a>>,
+
+    /// Key the [`Stats`] are sorted by.
+    sort_key: SortKey,
+
+    /// Indicator whether the [`Stats`] are sorted in descending order.
+    descending: bool,
+
+    /// Number of the leading [`Stats`] to skip.
+    offset: usize,
+
+    /// Maximum number of the [`Stats`] to return, if limited.
+    limit: Option<usize>,
+}
+
+impl<'a> LinkQuery<'a> {
+    /// Keeps only the [`ShortLink`]s whose [`Stats`] satisfy the provided
+    /// predicate, in addition to the previously provided ones.
+    pub fn filter(mut self, predicate: impl Fn(&Stats) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Sorts the [`ShortLink`]s by the provided [`SortKey`] ([`SortKey::Slug`]
+    /// by default), in ascending order unless
+    /// [`LinkQuery::descending()`] is used.
+    pub fn sort_by(mut self, key: SortKey) -> Self {
+        self.sort_key = key;
+        self
+    }
+
+    /// Sorts the [`ShortLink`]s in descending order of the [`SortKey`], with
+    /// ties still broken by ascending [`Slug`].
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    /// Skips the provided number of the leading [`ShortLink`]s.
+    pub fn offset(mut self, n: usize) -> Self {
+        self.offset = n;
+        self
+    }
+
+    /// Returns at most the provided number of [`ShortLink`]s.
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Executes this [`LinkQuery`], returning the [`Stats`] of the matching
+    /// [`ShortLink`]s.
+    pub fn collect(self) -> Vec<Stats> {
+        self.service.audit_read("query", None);
+        let mut rows: Vec<_> = self
+            .service
+            .links
+            .values()
+            .map(|state| {
+                let stats = Stats {
+                    link: self.service.link_of(state),
+                    redirects: state.redirects,
+                };
+                (stats, state.created_at)
+            })
+            .filter(|(stats, _)| self.filters.iter().all(|f| f(stats)))
+            .collect();
+        rows.sort_by(|(a, a_at), (b, b_at)| {
+            let order = match self.sort_key {
+                SortKey::Slug => a.link.slug.0.cmp(&b.link.slug.0),
+                SortKey::Redirects => a.redirects.cmp(&b.redirects),
+                SortKey::CreatedAt => a_at.cmp(b_at),
+            };
+            let order = if self.descending {
+                order.reverse()
+            } else {
+                order
+            };
+            order.then_with(|| a.link.slug.0.cmp(&b.link.slug.0))
+        });
+        rows.into_iter()
+            .map(|(stats, _)| stats)
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// All the attributes of a [`ShortLink`] besides its [`Url`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkMetadata {
+    /// Moment the [`ShortLink`] was created at.
+    pub created_at: SystemTime,
+
+    /// Count of redirects of the [`ShortLink`].
+    pub redirects: u64,
+
+    /// Moment of the last redirect of the [`ShortLink`], if any.
+    pub last_redirect: Option<SystemTime>,
+
+    /// Indicator whether the [`ShortLink`] is enabled.
+    pub enabled: bool,
+
+    /// Time left until the [`ShortLink`] expires, if it expires at all.
+    ///
+    /// [`Duration::ZERO`] means the [`ShortLink`] has already expired.
+    pub ttl_remaining: Option<Duration>,
+
+    /// Free-form note attached to the [`ShortLink`], if any.
+    pub note: Option<String>,
+}
+
 /// Commands for CQRS.
 pub mod commands {
     use super::{ShortLink, ShortenerError, Slug, Url};
@@ -140,134 +929,11139 @@ pub mod queries {
     }
 }
 
-/// CQRS and Event Sourcing-based service implementation
-pub struct UrlShortenerService {
-    // TODO: add needed fields
-    links: HashMap<Slug, (ShortLink, u64)>, // Stores the ShortLink and the count of redirects
-}
+/// Events for Event Sourcing.
+pub mod events {
+    use std::{cell::RefCell, rc::Rc, time::SystemTime};
 
-impl UrlShortenerService {
-    /// Creates a new instance of the service
-    pub fn new() -> Self {
-        Self {
-            links: HashMap::new(),
-        }
-    }
-    
-    /// Generates a random slug (basic version)
-    fn generate_random_slug() -> Slug {
-        use rand::{distributions::Alphanumeric, Rng}; // Ensure import is in the function scope
-        let slug: String = rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(6) // Adjust the length as needed
-            .map(char::from)
-            .collect();
-        Slug(slug)
-    }
-}
+    use super::{Slug, Url};
 
-impl commands::CommandHandler for UrlShortenerService {
-    fn handle_create_short_link(
-        &mut self,
-        url: Url,
-        slug: Option<Slug>,
-    ) -> Result<ShortLink, ShortenerError> {
-        // todo!("Implement the logic for creating a short link")
+    /// All state changes of the [`UrlShortenerService`], as recorded in its
+    /// event log.
+    ///
+    /// Replaying the events in order reconstructs the state of the service.
+    ///
+    /// [`UrlShortenerService`]: super::UrlShortenerService
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Event {
+        /// A new [`ShortLink`] has been created.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkCreated {
+            /// [`Slug`] of the created link.
+            slug: Slug,
 
-        let slug = match slug {
-            Some(s) => s,
-            None => Self::generate_random_slug(),
-        };
+            /// [`Url`] the created link points to, exactly as stored (already
+            /// normalized, if the normalization is enabled).
+            url: Url,
 
-        // Check if the slug already exists
-        match self.links.entry(slug.clone()) {
-            Entry::Occupied(_) => Err(ShortenerError::SlugAlreadyInUse),
-            Entry::Vacant(entry) => {
-                let short_link = ShortLink {
-                    slug: slug.clone(),
-                    url: url.clone(),
-                };
-                entry.insert((short_link.clone(), 0)); // Insert the link with initial redirect count of 0
-                Ok(short_link)
-            }
-        }
-    }
+            /// Indicator whether the [`Slug`] has been provided rather than
+            /// generated.
+            custom_slug: bool,
 
-    fn handle_redirect(
-        &mut self,
-        slug: Slug,
-    ) -> Result<ShortLink, ShortenerError> {
-        // todo!("Implement the logic for redirection and incrementing the click counter")
-        
-        match self.links.get_mut(&slug) {
-            Some((link, redirects)) => {
-                *redirects += 1;
-                Ok(link.clone())
-            }
-            None => Err(ShortenerError::SlugNotFound),
-        }
-    }
+            /// Numeric id of the created link, assigned monotonically.
+            id: u64,
 
-    fn handle_change_short_link(
-        &mut self,
-        slug: Slug,
-        new_url: Url,
-    ) -> Result<ShortLink, ShortenerError> {
-        match self.links.get_mut(&slug) {
-            Some((link, _)) => {
-                link.url = new_url.clone();
-                Ok(link.clone())
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been redirected through.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkRedirected {
+            /// [`Slug`] of the redirected link.
+            slug: Slug,
+
+            /// Source tag the redirect was made with, if any.
+            source: Option<String>,
+
+            /// Referer the redirect was made from, if known.
+            referrer: Option<String>,
+
+            /// Index of the split variant served by the redirect, if any.
+            variant: Option<usize>,
+
+            /// Count of redirects this one represents.
+            count: u64,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been changed to point to a new [`Url`].
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkChanged {
+            /// [`Slug`] of the changed link.
+            slug: Slug,
+
+            /// New [`Url`] the link points to, exactly as stored (already
+            /// normalized, if the normalization is enabled).
+            new_url: Url,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been disabled, so it cannot be redirected
+        /// through anymore.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkDisabled {
+            /// [`Slug`] of the disabled link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A previously disabled [`ShortLink`] has been enabled back.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkEnabled {
+            /// [`Slug`] of the enabled link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// Expiration moment of a [`ShortLink`] has been set or removed.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkExpirySet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the link expires at, if any.
+            expires_at: Option<SystemTime>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// Note of a [`ShortLink`] has been set or removed.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkNoteSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// New note of the link, if any.
+            note: Option<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// Count of redirects of a [`ShortLink`] has been increased by the
+        /// provided amount, without recording the redirects individually.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        RedirectsSeeded {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Count of redirects added.
+            count: u64,
+
+            /// Indicator whether the redirects have been made through the link
+            /// (and are recorded in bulk), rather than imported from
+            /// elsewhere.
+            organic: bool,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been made a one-time one, consumed by its first
+        /// redirect.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkMadeOneTime {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A one-time [`ShortLink`] has been consumed by its first redirect,
+        /// so it cannot be redirected through anymore.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkConsumed {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been renamed to a new [`Slug`], keeping all its
+        /// attributes and statistics.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkRenamed {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// New [`Slug`] of the link.
+            new_slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been split between several weighted [`Url`]s.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkSplitSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// [`Url`]s to serve along with their weights.
+            variants: Vec<(Url, u32)>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been folded into another one, becoming its
+        /// alias.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkMerged {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// [`Slug`] of the link it has been folded into.
+            into: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// The countries a [`ShortLink`] is allowed in have been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkCountriesSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Country codes the link is allowed in, or [`Vec::is_empty()`]
+            /// if it's allowed everywhere.
+            countries: Vec<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A metadata entry of a [`ShortLink`] has been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkMetadataSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Key of the metadata entry.
+            key: String,
+
+            /// New value of the metadata entry, or [`None`] if it has been
+            /// removed.
+            value: Option<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// The response to redirects through a [`ShortLink`] has been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkResponseSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// HTTP status to redirect with.
+            status: u16,
+
+            /// Content type to respond with, if any.
+            content_type: Option<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been assigned to a campaign.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkCampaignAssigned {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Name of the campaign.
+            campaign: String,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// The referers a [`ShortLink`] is allowed from have been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkReferersSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Hosts the link is allowed from, or [`Vec::is_empty()`] if it's
+            /// allowed from anywhere.
+            referers: Vec<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// Display title of a [`ShortLink`] has been set or removed.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkTitleSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// New display title of the link, if any.
+            title: Option<String>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// Moment a [`ShortLink`] goes live at has been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkActivationScheduled {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the link goes live at.
+            active_from: SystemTime,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// The redirect sample rate of a [`ShortLink`] has been set.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkSamplingSet {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Fraction of the redirects to record individually, or [`None`]
+            /// for the default one of the service.
+            rate: Option<f64>,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been deleted along with its aliases, freeing its
+        /// [`Slug`] for reuse.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkDeleted {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// An alias has been added to a [`ShortLink`], so redirecting through
+        /// it behaves as redirecting through the link.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkAliasAdded {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// New alias of the link.
+            alias: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// An alias of a [`ShortLink`] has been removed.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkAliasRemoved {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Removed alias of the link.
+            alias: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+
+        /// A [`ShortLink`] has been made a template one, having the
+        /// placeholders in its [`Url`] expanded on every redirect.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        ShortLinkMadeTemplate {
+            /// [`Slug`] of the link.
+            slug: Slug,
+
+            /// Moment the event occurred at.
+            at: SystemTime,
+        },
+    }
+
+    impl Event {
+        /// Returns the [`Slug`] of the link this [`Event`] relates to.
+        pub fn slug(&self) -> &Slug {
+            match self {
+                Self::ShortLinkCreated { slug, .. }
+                | Self::ShortLinkRedirected { slug, .. }
+                | Self::ShortLinkChanged { slug, .. }
+                | Self::ShortLinkDisabled { slug, .. }
+                | Self::ShortLinkEnabled { slug, .. }
+                | Self::ShortLinkExpirySet { slug, .. }
+                | Self::ShortLinkNoteSet { slug, .. }
+                | Self::RedirectsSeeded { slug, .. }
+                | Self::ShortLinkMadeOneTime { slug, .. }
+                | Self::ShortLinkConsumed { slug, .. }
+                | Self::ShortLinkRenamed { slug, .. }
+                | Self::ShortLinkSplitSet { slug, .. }
+                | Self::ShortLinkMerged { slug, .. }
+                | Self::ShortLinkCountriesSet { slug, .. }
+                | Self::ShortLinkMetadataSet { slug, .. }
+                | Self::ShortLinkResponseSet { slug, .. }
+                | Self::ShortLinkCampaignAssigned { slug, .. }
+                | Self::ShortLinkReferersSet { slug, .. }
+                | Self::ShortLinkTitleSet { slug, .. }
+                | Self::ShortLinkActivationScheduled { slug, .. }
+                | Self::ShortLinkAliasAdded { slug, .. }
+                | Self::ShortLinkAliasRemoved { slug, .. }
+                | Self::ShortLinkMadeTemplate { slug, .. }
+                | Self::ShortLinkDeleted { slug, .. }
+                | Self::ShortLinkSamplingSet { slug, .. } => slug,
             }
-            None => Err(ShortenerError::SlugNotFound),
+        }
+
+        /// Returns this [`Event`] with every [`Slug`] in it replaced with the
+        /// one returned by the provided function.
+        pub fn map_slugs(mut self, mut f: impl FnMut(&Slug) -> Slug) -> Self {
+            match &mut self {
+                Self::ShortLinkRenamed { slug, new_slug, .. } => {
+                    *slug = f(slug);
+                    *new_slug = f(new_slug);
+                }
+                Self::ShortLinkMerged { slug, into, .. } => {
+                    *slug = f(slug);
+                    *into = f(into);
+                }
+                Self::ShortLinkAliasAdded { slug, alias, .. }
+                | Self::ShortLinkAliasRemoved { slug, alias, .. } => {
+                    *slug = f(slug);
+                    *alias = f(alias);
+                }
+                Self::ShortLinkCreated { slug, .. }
+                | Self::ShortLinkRedirected { slug, .. }
+                | Self::ShortLinkChanged { slug, .. }
+                | Self::ShortLinkDisabled { slug, .. }
+                | Self::ShortLinkEnabled { slug, .. }
+                | Self::ShortLinkExpirySet { slug, .. }
+                | Self::ShortLinkNoteSet { slug, .. }
+                | Self::RedirectsSeeded { slug, .. }
+                | Self::ShortLinkMadeOneTime { slug, .. }
+                | Self::ShortLinkConsumed { slug, .. }
+                | Self::ShortLinkSplitSet { slug, .. }
+                | Self::ShortLinkCountriesSet { slug, .. }
+                | Self::ShortLinkMetadataSet { slug, .. }
+                | Self::ShortLinkResponseSet { slug, .. }
+                | Self::ShortLinkCampaignAssigned { slug, .. }
+                | Self::ShortLinkReferersSet { slug, .. }
+                | Self::ShortLinkTitleSet { slug, .. }
+                | Self::ShortLinkActivationScheduled { slug, .. }
+                | Self::ShortLinkMadeTemplate { slug, .. }
+                | Self::ShortLinkDeleted { slug, .. }
+                | Self::ShortLinkSamplingSet { slug, .. } => *slug = f(slug),
+            }
+            self
+        }
+
+        /// Returns the moment this [`Event`] occurred at.
+        pub fn at(&self) -> SystemTime {
+            match self {
+                Self::ShortLinkCreated { at, .. }
+                | Self::ShortLinkRedirected { at, .. }
+                | Self::ShortLinkChanged { at, .. }
+                | Self::ShortLinkDisabled { at, .. }
+                | Self::ShortLinkEnabled { at, .. }
+                | Self::ShortLinkExpirySet { at, .. }
+                | Self::ShortLinkNoteSet { at, .. }
+                | Self::RedirectsSeeded { at, .. }
+                | Self::ShortLinkMadeOneTime { at, .. }
+                | Self::ShortLinkConsumed { at, .. }
+                | Self::ShortLinkRenamed { at, .. }
+                | Self::ShortLinkSplitSet { at, .. }
+                | Self::ShortLinkMerged { at, .. }
+                | Self::ShortLinkCountriesSet { at, .. }
+                | Self::ShortLinkMetadataSet { at, .. }
+                | Self::ShortLinkResponseSet { at, .. }
+                | Self::ShortLinkCampaignAssigned { at, .. }
+                | Self::ShortLinkReferersSet { at, .. }
+                | Self::ShortLinkTitleSet { at, .. }
+                | Self::ShortLinkActivationScheduled { at, .. }
+                | Self::ShortLinkAliasAdded { at, .. }
+                | Self::ShortLinkAliasRemoved { at, .. }
+                | Self::ShortLinkMadeTemplate { at, .. }
+                | Self::ShortLinkDeleted { at, .. }
+                | Self::ShortLinkSamplingSet { at, .. } => *at,
+            }
+        }
+    }
+
+    /// [`Event`] along with its sequence number in the event log.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct SequencedEvent {
+        /// Sequence number of the [`Event`] in the event log, starting from
+        /// `1`.
+        pub seq: u64,
+
+        /// The [`Event`] itself.
+        pub event: Event,
+    }
+
+    /// Receiver of every [`Event`] appended to the event log of the
+    /// [`UrlShortenerService`].
+    ///
+    /// Events are forwarded only after a command has succeeded, so a failed
+    /// command never reaches a sink.
+    ///
+    /// A file-backed sink persisting the log across process restarts may be
+    /// implemented the following way (not provided, as the Rust Playground has
+    /// no persistent filesystem):
+    ///
+    /// ```ignore
+    /// struct FileEventSink(std::fs::File);
+    ///
+    /// impl EventSink for FileEventSink {
+    ///     fn append(&mut self, event: &Event) {
+    ///         use std::io::Write as _;
+    ///         writeln!(self.0, "{event:?}").expect("failed to persist event");
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`UrlShortenerService`]: super::UrlShortenerService
+    pub trait EventSink {
+        /// Accepts the provided [`Event`] which has just been appended to the
+        /// event log.
+        fn append(&mut self, event: &Event);
+    }
+
+    /// In-memory [`EventSink`] collecting all the received [`Event`]s.
+    ///
+    /// Clones share the same storage, so a clone may be kept to inspect the
+    /// [`Event`]s received by the one passed into the service.
+    #[derive(Clone, Debug, Default)]
+    pub struct VecEventSink {
+        /// Received [`Event`]s, in order.
+        events: Rc<RefCell<Vec<Event>>>,
+    }
+
+    impl VecEventSink {
+        /// Creates a new empty [`VecEventSink`].
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns all the [`Event`]s received so far, in order.
+        pub fn events(&self) -> Vec<Event> {
+            self.events.borrow().clone()
+        }
+    }
+
+    impl EventSink for VecEventSink {
+        fn append(&mut self, event: &Event) {
+            self.events.borrow_mut().push(event.clone());
         }
     }
 }
 
-impl queries::QueryHandler for UrlShortenerService {
-    fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
-        // todo!("Implement the logic for retrieving link statistics")
+/// Time source of the [`UrlShortenerService`].
+pub mod clock {
+    use std::{
+        cell::Cell,
+        rc::Rc,
+        time::{Duration, SystemTime},
+    };
 
-        match self.links.get(&slug) {
-            Some((link, redirects)) => Ok(Stats {
-                link: link.clone(),
-                redirects: *redirects,
-            }),
-            None => Err(ShortenerError::SlugNotFound),
+    /// Source of the current time.
+    pub trait Clock {
+        /// Returns the current moment.
+        fn now(&self) -> SystemTime;
+    }
+
+    /// [`Clock`] backed by the system time.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct SystemClock;
+
+    impl Clock for SystemClock {
+        fn now(&self) -> SystemTime {
+            SystemTime::now()
+        }
+    }
+
+    /// Manually controlled [`Clock`], useful for tests.
+    ///
+    /// Clones share the same time, so a clone may be kept to control the time
+    /// of the one passed into the service.
+    #[derive(Clone, Debug)]
+    pub struct MockClock {
+        /// Current moment of this [`MockClock`].
+        now: Rc<Cell<SystemTime>>,
+    }
+
+    impl MockClock {
+        /// Creates a new [`MockClock`] starting at the provided moment.
+        pub fn new(now: SystemTime) -> Self {
+            Self {
+                now: Rc::new(Cell::new(now)),
+            }
+        }
+
+        /// Sets the current moment of this [`MockClock`].
+        pub fn set(&self, now: SystemTime) {
+            self.now.set(now);
+        }
+
+        /// Moves the current moment of this [`MockClock`] forward by the
+        /// provided [`Duration`].
+        pub fn advance(&self, by: Duration) {
+            self.now.set(self.now.get() + by);
+        }
+    }
+
+    impl Default for MockClock {
+        fn default() -> Self {
+            Self::new(SystemTime::UNIX_EPOCH)
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> SystemTime {
+            self.now.get()
         }
     }
 }
 
+/// CQRS and Event Sourcing-based service implementation
+pub struct UrlShortenerService {
+    /// Append-only log of all the [`Event`]s, being the source of truth.
+    events: Vec<Event>,
 
+    /// [`EventSink`]s every appended [`Event`] is forwarded to.
+    sinks: Vec<Box<dyn EventSink>>,
 
+    /// [`Event`]s not forwarded to the [`EventSink`]s yet, while the
+    /// notifications are suspended.
+    ///
+    /// [`None`] if the notifications are not suspended.
+    suspended: Option<Vec<Event>>,
 
-fn main() {
-    let mut service = UrlShortenerService::new();
+    /// [`Clock`] the [`Event`]s are timestamped with.
+    clock: Box<dyn Clock>,
 
-    // Create a short link with a random slug
-    let url = Url("https://original-url.com".to_string());
-    let short_link = service
-        .handle_create_short_link(url.clone(), None)
-        .expect("Failed to create short link");
+    /// Moment this service has been started at, according to its [`Clock`].
+    started_at: SystemTime,
 
-    // Create a short link with a predefined slug
-    let slug = Slug("custom.xyz".to_string());
-    let custom_link = service
-        .handle_create_short_link(url.clone(), Some(slug.clone()))
-        .expect("Failed to create short link with custom slug");
+    /// Read model built from the [`Event`]s.
+    links: HashMap<Slug, LinkState>,
 
-    // Change the URL for an existing short link
-    let new_url = Url("https://new-original-url.com".to_string());
-    let updated_link = service
-        .handle_change_short_link(slug.clone(), new_url.clone())
-        .expect("Failed to change the URL for the short link");
+    /// [`Slug`]s of the folded [`ShortLink`]s, mapped to the [`Slug`]s of the
+    /// ones they have been folded into.
+    aliases: HashMap<Slug, Slug>,
 
-    // Redirect using a slug
-    let redirected_link = service
-        .handle_redirect(slug.clone())
-        .expect("Failed to redirect using the slug");
+    /// Count of the [`ShortLink`]s created with generated [`Slug`]s so far.
+    generated_slugs: u64,
 
-    // Get stats for the link
-    let stats = service.get_stats(slug.clone()).expect("Failed to get stats");
+    /// Greatest numeric id assigned to a [`ShortLink`] so far.
+    last_link_id: u64,
 
-    println!("Stats: {:?}", stats);
+    /// Configuration of this service.
+    config: Config,
+
+    /// Random number generator the [`Slug`]s are generated and the split
+    /// variants are picked with.
+    rng: Box<dyn RngCore>,
+
+    /// Cache of the recent [`UrlShortenerService::resolve()`] lookups, if
+    /// enabled.
+    resolve_cache: Option<RefCell<ResolveCache>>,
+
+    /// Indicator whether the reads are audited.
+    read_auditing: bool,
+
+    /// Log of the audited reads.
+    read_audit: RefCell<Vec<ReadEvent>>,
+
+    /// Redirects not merged into the counters yet, if buffering is enabled.
+    pending_redirects: Option<Vec<PendingRedirect>>,
+
+    /// [`Url`] every redirect goes to while in maintenance, if any.
+    maintenance_url: Option<Url>,
+
+    /// [`Slug`]s reserved from being used by new [`ShortLink`]s.
+    reserved_slugs: HashSet<Slug>,
+
+    /// Count of the [`Event`]s appended since the last compaction.
+    appended_since_compaction: usize,
+
+    /// Indicator whether the detailed history has been discarded from the
+    /// event log (see [`UrlShortenerService::compact()`]).
+    compacted: bool,
+}
+
+/// Redirect buffered until the next
+/// [`UrlShortenerService::flush_pending_redirects()`].
+#[derive(Clone, Debug)]
+struct PendingRedirect {
+    /// [`Slug`] of the redirected [`ShortLink`].
+    slug: Slug,
+
+    /// Source tag the redirect was made with, if any.
+    source: Option<String>,
+
+    /// Referer the redirect was made from, if known.
+    referrer: Option<String>,
+
+    /// Index of the split variant the redirect served, if any.
+    variant: Option<usize>,
+
+    /// Moment the redirect was made at.
+    at: SystemTime,
+}
+
+/// Least-recently-used cache of [`Slug`] to [`Url`] lookups.
+#[derive(Clone, Debug)]
+struct ResolveCache {
+    /// Maximum number of the cached lookups.
+    capacity: usize,
+
+    /// Cached lookups, the most recently used first.
+    entries: VecDeque<(Slug, Url)>,
+}
+
+impl ResolveCache {
+    /// Creates a new empty [`ResolveCache`] of the provided capacity.
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the cached [`Url`] of the provided [`Slug`], marking it as the
+    /// most recently used.
+    fn get(&mut self, slug: &Slug) -> Option<Url> {
+        let pos = self.entries.iter().position(|(s, _)| s == slug)?;
+        let entry = self.entries.remove(pos)?;
+        let url = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(url)
+    }
+
+    /// Caches the provided lookup, evicting the least recently used one if
+    /// the capacity is exceeded.
+    fn insert(&mut self, slug: Slug, url: Url) {
+        self.invalidate(&slug);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            _ = self.entries.pop_back();
+        }
+        self.entries.push_front((slug, url));
+    }
+
+    /// Removes the cached lookup of the provided [`Slug`], if any.
+    fn invalidate(&mut self, slug: &Slug) {
+        self.entries.retain(|(s, _)| s != slug);
+    }
+}
+
+/// Whole configuration of a [`UrlShortenerService`], extracted with
+/// [`UrlShortenerService::config()`] to reconstruct an equally configured
+/// one with [`UrlShortenerService::from_config()`].
+///
+/// The [`Clock`], the random number generator and the [`EventSink`]s are
+/// not part of it.
+///
+/// With the `serde` feature enabled, it can be serialized and deserialized
+/// with [serde](https://serde.rs).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServiceConfig {
+    /// Settings of the [`UrlShortenerService`] builder methods.
+    config: Config,
+
+    /// Capacity of the cache of the [`UrlShortenerService::resolve()`]
+    /// lookups, if enabled.
+    resolve_cache_capacity: Option<usize>,
+
+    /// Indicator whether the reads are audited.
+    read_auditing: bool,
+
+    /// Indicator whether the redirects are buffered.
+    buffered_redirects: bool,
+
+    /// Reserved [`Slug`]s.
+    reserved_slugs: HashSet<Slug>,
+}
+
+/// Configuration of the [`UrlShortenerService`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Config {
+    /// Indicator whether [`Url`]s must have a public-looking hostname.
+    require_public_hostname: bool,
+
+    /// Indicator whether every redirect is recorded into the event log.
+    log_redirects: bool,
+
+    /// Indicator whether the [`Snapshot`]s include the [`LinkAnalytics`].
+    full_analytics_snapshot: bool,
+
+    /// Count of redirects every redirect represents.
+    redirect_increment: u64,
+
+    /// Fraction of redirects recorded individually into the event log.
+    redirect_sample_rate: f64,
+
+    /// Indicator whether [`Url`]s are normalized before being stored.
+    normalize_urls: bool,
+
+    /// Indicator whether a trailing slash of [`Slug`]s is ignored.
+    trim_trailing_slash_slugs: bool,
+
+    /// Key the [`Url`]s are obfuscated with in the read model, if any.
+    url_obfuscation_key: Option<Vec<u8>>,
+
+    /// Indicator whether a [`Url`] may be pointed to by a single [`ShortLink`]
+    /// only.
+    unique_urls: bool,
+
+    /// Indicator whether the host case is ignored when checking [`Url`]s for
+    /// uniqueness.
+    unique_url_host_case_insensitive: bool,
+
+    /// Maximum number of changes of the [`Url`] of a single [`ShortLink`], if
+    /// limited.
+    max_url_changes: Option<u32>,
+
+    /// Maximum number of aliases of a single [`ShortLink`], if limited.
+    max_aliases: Option<usize>,
+
+    /// Minimum time-to-live of a [`ShortLink`], if limited.
+    min_ttl: Option<Duration>,
+
+    /// Maximum time-to-live of a [`ShortLink`], if limited.
+    max_ttl: Option<Duration>,
+
+    /// Window of inactivity a [`ShortLink`] expires after, if any.
+    idle_expiry: Option<Duration>,
+
+    /// Behavior of redirecting through a disabled [`ShortLink`].
+    disabled_behavior: DisabledBehavior,
+
+    /// Policy of resolving a [`Slug`] provided more than once within a single
+    /// batch.
+    batch_duplicate_policy: BatchDuplicatePolicy,
+
+    /// Scheme prepended to the [`Url`]s provided without any, if any.
+    default_scheme: Option<String>,
+
+    /// [`Url`] the [`ShortLink`]s are served under, if known.
+    base_url: Option<Url>,
+
+    /// Length of the generated [`Slug`]s.
+    slug_length: usize,
+
+    /// Characters the generated [`Slug`]s consist of, if other than the
+    /// alphanumeric ones.
+    slug_alphabet: Option<Vec<char>>,
+
+    /// Lowercased words the generated [`Slug`]s must not contain.
+    slug_denylist: Vec<String>,
+
+    /// Number of appended [`Event`]s the event log is compacted after, if
+    /// any.
+    auto_compact_every: Option<usize>,
+
+    /// Maximum number of attempts to generate a [`Slug`] not in use yet.
+    slug_generation_attempts: u32,
+
+    /// Indicator whether the [`Slug`]s are generated sequentially rather than
+    /// randomly.
+    sequential_slugs: bool,
+
+    /// Indicator whether custom [`Slug`]s must not have the format of the
+    /// generated ones.
+    distinct_custom_slug_format: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            require_public_hostname: false,
+            log_redirects: true,
+            full_analytics_snapshot: false,
+            redirect_increment: 1,
+            redirect_sample_rate: 1.0,
+            normalize_urls: false,
+            trim_trailing_slash_slugs: false,
+            url_obfuscation_key: None,
+            unique_urls: false,
+            unique_url_host_case_insensitive: false,
+            max_url_changes: None,
+            max_aliases: None,
+            min_ttl: None,
+            max_ttl: None,
+            idle_expiry: None,
+            disabled_behavior: DisabledBehavior::default(),
+            batch_duplicate_policy: BatchDuplicatePolicy::default(),
+            default_scheme: None,
+            base_url: None,
+            slug_length: 6,
+            slug_alphabet: None,
+            slug_denylist: Vec::new(),
+            slug_generation_attempts: 10,
+            sequential_slugs: false,
+            distinct_custom_slug_format: false,
+            auto_compact_every: None,
+        }
+    }
+}
+
+/// State of a single [`ShortLink`] in the read model of the
+/// [`UrlShortenerService`].
+#[derive(Clone, Debug)]
+struct LinkState {
+    /// The [`ShortLink`] itself.
+    link: ShortLink,
+
+    /// Count of redirects of the [`ShortLink`].
+    redirects: u64,
+
+    /// Count of individual redirects of the [`ShortLink`], regardless of how
+    /// many redirects each of them represents.
+    hits: u64,
+
+    /// Counts of redirects per source tag they were made with.
+    sources: HashMap<String, u64>,
+
+    /// Moment the [`ShortLink`] was created at.
+    created_at: SystemTime,
+
+    /// Indicator whether the [`Slug`] of the [`ShortLink`] has been provided
+    /// rather than generated.
+    custom_slug: bool,
+
+    /// Numeric id of the [`ShortLink`].
+    id: u64,
+
+    /// Moment of the first redirect of the [`ShortLink`], if any.
+    first_redirect: Option<SystemTime>,
+
+    /// Moment of the last redirect of the [`ShortLink`], if any.
+    last_redirect: Option<SystemTime>,
+
+    /// Indicator whether the [`ShortLink`] can be redirected through.
+    enabled: bool,
+
+    /// Moment the [`ShortLink`] expires at, if any.
+    expires_at: Option<SystemTime>,
+
+    /// Free-form note attached to the [`ShortLink`], if any.
+    note: Option<String>,
+
+    /// Indicator whether the [`ShortLink`] is consumed by its first redirect.
+    one_time: bool,
+
+    /// Indicator whether the placeholders in the [`Url`] of the [`ShortLink`]
+    /// are expanded on every redirect.
+    template: bool,
+
+    /// Indicator whether the one-time [`ShortLink`] has been consumed already.
+    consumed: bool,
+
+    /// Count of redirects not recorded into the event log yet.
+    unlogged_redirects: u64,
+
+    /// Count of redirects seeded from elsewhere rather than made through the
+    /// [`ShortLink`].
+    seeded: u64,
+
+    /// Count of redirects made while the [`ShortLink`] was disabled.
+    redirects_while_disabled: u64,
+
+    /// Weighted [`Url`]s the [`ShortLink`] is split between, if any.
+    variants: Vec<Variant>,
+
+    /// Country codes the [`ShortLink`] is allowed in, or empty if it's
+    /// allowed everywhere.
+    allowed_countries: Vec<String>,
+
+    /// Referer hosts the [`ShortLink`] is allowed from, or empty if it's
+    /// allowed from anywhere.
+    allowed_referers: Vec<String>,
+
+    /// Key-value metadata attached to the [`ShortLink`].
+    metadata: BTreeMap<String, String>,
+
+    /// HTTP status to redirect through the [`ShortLink`] with.
+    redirect_status: u16,
+
+    /// Content type to respond to redirects with, if any.
+    content_type: Option<String>,
+
+    /// Name of the campaign the [`ShortLink`] is assigned to, if any.
+    campaign: Option<String>,
+
+    /// Count of changes of the [`Url`] of the [`ShortLink`].
+    url_changes: u32,
+
+    /// Display title of the [`ShortLink`] (e.g. for link previews), if any.
+    title: Option<String>,
+
+    /// Moment the [`ShortLink`] goes live at, if scheduled.
+    active_from: Option<SystemTime>,
+
+    /// Fraction of the redirects recorded individually, if overriding the
+    /// default one of the service.
+    sample_rate: Option<f64>,
+}
+
+/// Single weighted [`Url`] of a split [`ShortLink`].
+#[derive(Clone, Debug)]
+struct Variant {
+    /// [`Url`] of this [`Variant`].
+    url: Url,
+
+    /// Relative weight this [`Variant`] is picked with.
+    weight: u32,
+
+    /// Count of redirects served by this [`Variant`].
+    redirects: u64,
+}
+
+impl LinkState {
+    /// Creates a new [`LinkState`] of the provided [`ShortLink`] created at the
+    /// provided moment and having no redirects yet.
+    fn new(link: ShortLink, created_at: SystemTime) -> Self {
+        Self {
+            link,
+            redirects: 0,
+            hits: 0,
+            sources: HashMap::new(),
+            created_at,
+            custom_slug: false,
+            id: 0,
+            first_redirect: None,
+            last_redirect: None,
+            enabled: true,
+            expires_at: None,
+            note: None,
+            one_time: false,
+            template: false,
+            consumed: false,
+            unlogged_redirects: 0,
+            seeded: 0,
+            redirects_while_disabled: 0,
+            variants: Vec::new(),
+            allowed_countries: Vec::new(),
+            allowed_referers: Vec::new(),
+            metadata: BTreeMap::new(),
+            redirect_status: 302,
+            content_type: None,
+            campaign: None,
+            url_changes: 0,
+            title: None,
+            active_from: None,
+            sample_rate: None,
+        }
+    }
+
+    /// Records a single redirect of the [`ShortLink`] made at the provided
+    /// moment, representing the provided `count` of redirects and served by
+    /// the provided split variant, if any.
+    fn record_redirect(
+        &mut self,
+        source: Option<&String>,
+        variant: Option<usize>,
+        count: u64,
+        at: SystemTime,
+    ) {
+        self.redirects = self.redirects.saturating_add(count);
+        self.hits = self.hits.saturating_add(1);
+        if !self.enabled {
+            self.redirects_while_disabled =
+                self.redirects_while_disabled.saturating_add(count);
+        }
+        if let Some(v) = variant.and_then(|i| self.variants.get_mut(i)) {
+            v.redirects = v.redirects.saturating_add(count);
+        }
+        self.first_redirect = self.first_redirect.or(Some(at));
+        self.last_redirect = Some(at);
+        if let Some(source) = source {
+            let total = self.sources.entry(source.clone()).or_default();
+            *total = total.saturating_add(count);
+        }
+    }
+
+    /// Returns the moment of the last activity of the [`ShortLink`]: its last
+    /// redirect or, if there were none, its creation.
+    fn last_activity(&self) -> SystemTime {
+        self.last_redirect.unwrap_or(self.created_at)
+    }
+
+    /// Returns the [`SlugOrigin`] of the [`ShortLink`].
+    fn origin(&self) -> SlugOrigin {
+        if self.custom_slug {
+            SlugOrigin::Custom
+        } else {
+            SlugOrigin::Generated
+        }
+    }
+
+    /// Indicates whether the [`ShortLink`] can be redirected through at the
+    /// provided moment, given the provided idle expiry window, if any.
+    fn is_active(&self, now: SystemTime, idle: Option<Duration>) -> bool {
+        self.enabled
+            && !self.consumed
+            && self.is_live(now)
+            && !self.is_expired(now, idle)
+    }
+
+    /// Indicates whether the [`ShortLink`] has gone live by the provided
+    /// moment.
+    fn is_live(&self, now: SystemTime) -> bool {
+        self.active_from.is_none_or(|at| at <= now)
+    }
+
+    /// Indicates whether the [`ShortLink`] is allowed in the country with the
+    /// provided code, compared case-insensitively.
+    fn is_allowed_in(&self, country: &str) -> bool {
+        self.allowed_countries.is_empty()
+            || self
+                .allowed_countries
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(country))
+    }
+
+    /// Indicates whether the [`ShortLink`] is allowed from the provided
+    /// referer [`Url`], its host compared case-insensitively.
+    fn is_allowed_from(&self, referer: &Url) -> bool {
+        self.allowed_referers.is_empty()
+            || referer.host().is_some_and(|host| {
+                self.allowed_referers
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(host))
+            })
+    }
+
+    /// Indicates whether the [`ShortLink`] is expired at the provided moment,
+    /// either by its expiration moment or by being idle for at least the
+    /// provided window, if any.
+    fn is_expired(&self, now: SystemTime, idle: Option<Duration>) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+            || idle.is_some_and(|idle| self.last_activity() + idle <= now)
+    }
+}
+
+impl UrlShortenerService {
+    /// Creates a new instance of the service
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            sinks: Vec::new(),
+            suspended: None,
+            clock: Box::new(SystemClock),
+            started_at: SystemClock.now(),
+            links: HashMap::new(),
+            aliases: HashMap::new(),
+            generated_slugs: 0,
+            last_link_id: 0,
+            config: Config::default(),
+            rng: Box::new(rand::thread_rng()),
+            resolve_cache: None,
+            read_auditing: false,
+            read_audit: RefCell::new(Vec::new()),
+            pending_redirects: None,
+            maintenance_url: None,
+            reserved_slugs: HashSet::new(),
+            appended_since_compaction: 0,
+            compacted: false,
+        }
+    }
+
+    /// Makes the service reject [`Url`]s whose host doesn't look like a public
+    /// hostname, with a [`ShortenerError::InvalidUrl`].
+    ///
+    /// The heuristic is deliberately simple: IP-literal hosts (like
+    /// `127.0.0.1` or `[::1]`) and single-label hosts without any dot (like
+    /// `localhost`) are rejected, while no DNS or public suffix lookups are
+    /// performed.
+    pub fn with_require_public_hostname(mut self, require: bool) -> Self {
+        self.config.require_public_hostname = require;
+        self
+    }
+
+    /// Makes the service prepend the provided scheme (like `https`) to the
+    /// [`Url`]s provided without any (like `example.com/page`), instead of
+    /// rejecting them with a [`ShortenerError::InvalidUrl`].
+    pub fn with_default_scheme(mut self, scheme: Option<&str>) -> Self {
+        self.config.default_scheme =
+            scheme.map(|s| s.trim_end_matches("://").to_owned());
+        self
+    }
+
+    /// Sets the [`Url`] the [`ShortLink`]s are served under (like
+    /// `https://sho.rt`), so a [`Url`] like `https://sho.rt/abc` is recognized
+    /// as the [`ShortLink`] with the [`Slug`] `abc` of this service.
+    pub fn with_base_url(mut self, url: Url) -> Self {
+        self.config.base_url = Some(url);
+        self
+    }
+
+    /// Makes the service normalize [`Url`]s before storing them: lowercase
+    /// their scheme and host, drop a default port, and use `/` as an empty
+    /// path.
+    ///
+    /// [`Url`]s are compared in their normalized form too. The [`Event`]s
+    /// record the normalized form, so replaying them reproduces it regardless
+    /// of this setting.
+    pub fn with_normalize_urls(mut self, normalize: bool) -> Self {
+        self.config.normalize_urls = normalize;
+        self
+    }
+
+    /// Makes the service ignore a trailing slash of [`Slug`]s, treating `abc/`
+    /// and `abc` as the same [`Slug`].
+    ///
+    /// [`ShortLink`]s are stored and returned under the form without the
+    /// slash.
+    pub fn with_trim_trailing_slash_slugs(mut self, trim: bool) -> Self {
+        self.config.trim_trailing_slash_slugs = trim;
+        self
+    }
+
+    /// Sets the length of the generated [`Slug`]s (`6` by default).
+    pub fn with_slug_length(mut self, length: usize) -> Self {
+        self.config.slug_length = length;
+        self
+    }
+
+    /// Sets the maximum number of attempts to generate a [`Slug`] not in use
+    /// yet (`10` by default), before failing with a
+    /// [`ShortenerError::SlugAlreadyInUse`].
+    ///
+    /// At least one attempt is always made.
+    pub fn with_slug_generation_attempts(mut self, attempts: u32) -> Self {
+        self.config.slug_generation_attempts = attempts;
+        self
+    }
+
+    /// Sets the characters the generated [`Slug`]s consist of (the
+    /// alphanumeric ones by default).
+    pub fn with_slug_alphabet(mut self, alphabet: &str) -> Self {
+        self.config.slug_alphabet = Some(alphabet.chars().collect());
+        self
+    }
+
+    /// Makes the service generate [`Slug`]s sequentially rather than randomly:
+    /// the `n`-th generated [`Slug`] (counting from `0`) is `n` written in the
+    /// [`UrlShortenerService::with_slug_alphabet()`] digits, left-padded to
+    /// the [`UrlShortenerService::with_slug_length()`] with the first one.
+    ///
+    /// A [`Slug`] in use (or denied) already is skipped by the next attempt,
+    /// while the sequence advances by one per generated [`ShortLink`] only.
+    pub fn with_sequential_slugs(mut self, sequential: bool) -> Self {
+        self.config.sequential_slugs = sequential;
+        self
+    }
+
+    /// Makes creating a [`ShortLink`] with a custom [`Slug`] having the exact
+    /// format of the generated ones (the
+    /// [`UrlShortenerService::with_slug_length()`] and only characters of the
+    /// [`UrlShortenerService::with_slug_alphabet()`]) fail with a
+    /// [`ShortenerError::SlugFormatReserved`], so the [`SlugOrigin`] stays
+    /// unambiguous by the [`Slug`] alone.
+    pub fn with_distinct_custom_slug_format(mut self, distinct: bool) -> Self {
+        self.config.distinct_custom_slug_format = distinct;
+        self
+    }
+
+    /// Makes the service never generate [`Slug`]s containing any of the
+    /// provided words, compared case-insensitively.
+    ///
+    /// Provided [`Slug`]s are not checked against these words.
+    pub fn with_slug_denylist(mut self, words: &[&str]) -> Self {
+        self.config.slug_denylist =
+            words.iter().map(|w| w.to_lowercase()).collect();
+        self
+    }
+
+    /// Reconstructs a service with no [`ShortLink`]s configured the same way
+    /// as the one the provided [`ServiceConfig`] has been extracted from (see
+    /// [`UrlShortenerService::config()`]), including its reserved [`Slug`]s.
+    ///
+    /// ## Errors
+    ///
+    /// The same way as [`UrlShortenerService::build()`] does.
+    pub fn from_config(config: ServiceConfig) -> Result<Self, ConfigError> {
+        let mut service = Self::new()
+            .with_read_auditing(config.read_auditing)
+            .with_buffered_redirects(config.buffered_redirects);
+        if let Some(capacity) = config.resolve_cache_capacity {
+            service = service.with_resolve_cache(capacity);
+        }
+        service.config = config.config;
+        service.reserved_slugs = config.reserved_slugs;
+        service.build()
+    }
+
+    /// Checks the configuration of this service, returning the service if
+    /// it's satisfiable.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ConfigError::ImpossibleSlugConstraints`] if no generated [`Slug`]
+    ///   could ever pass the [`UrlShortenerService::with_slug_denylist()`].
+    /// - [`ConfigError::InvalidSampleRate`] if the
+    ///   [`UrlShortenerService::with_redirect_sampling()`] is not in
+    ///   `0.0..=1.0` range, or is zero.
+    pub fn build(self) -> Result<Self, ConfigError> {
+        // Any non-empty slug contains at least one character of the alphabet,
+        // so it's impossible to generate one if each of them is denied.
+        let impossible = if self.config.slug_length == 0 {
+            self.is_denied_slug("")
+        } else {
+            self.slug_alphabet()
+                .iter()
+                .all(|c| self.is_denied_slug(&c.to_string()))
+        };
+        if impossible {
+            return Err(ConfigError::ImpossibleSlugConstraints);
+        }
+        if !is_sample_rate(self.config.redirect_sample_rate) {
+            return Err(ConfigError::InvalidSampleRate);
+        }
+        Ok(self)
+    }
+
+    /// Makes the service keep the [`Url`]s in its read model XOR-ed with the
+    /// provided `key`, so they cannot be trivially read from a memory dump.
+    ///
+    /// This is obfuscation, __not__ security: the key is kept in memory along
+    /// with the obfuscated [`Url`]s, and the event log still keeps them in
+    /// plaintext for being replayable. All the queries and commands
+    /// transparently operate on plaintext [`Url`]s. An empty `key` disables
+    /// the obfuscation.
+    ///
+    /// Must be set before any [`ShortLink`] is created.
+    pub fn with_url_obfuscation(mut self, key: &[u8]) -> Self {
+        self.config.url_obfuscation_key =
+            (!key.is_empty()).then(|| key.to_vec());
+        self
+    }
+
+    /// Makes every [`ShortLink`] expire once it's not redirected through for
+    /// the provided window since its last redirect (or its creation, if there
+    /// were none), in addition to its own expiration moment, if any.
+    ///
+    /// Every redirect starts the window over.
+    pub fn with_idle_expiry(mut self, window: Duration) -> Self {
+        self.config.idle_expiry = Some(window);
+        self
+    }
+
+    /// Limits changing the [`Url`] of a single [`ShortLink`] (with the
+    /// [`CommandHandler::handle_change_short_link()`], the
+    /// [`UrlShortenerService::handle_rebrand()`] or the
+    /// [`UrlShortenerService::handle_retarget_url()`]) to the provided number
+    /// of times, rejecting further changes with a
+    /// [`ShortenerError::ChangeLimitExceeded`].
+    ///
+    /// Changes keep being counted after a
+    /// [`UrlShortenerService::compact()`], but as the compacted event log
+    /// doesn't record them, a service replayed from it counts them over.
+    pub fn with_max_url_changes(mut self, n: u32) -> Self {
+        self.config.max_url_changes = Some(n);
+        self
+    }
+
+    /// Limits the aliases of a single [`ShortLink`] to the provided number,
+    /// rejecting further ones added with the
+    /// [`UrlShortenerService::handle_add_alias()`] with a
+    /// [`ShortenerError::AliasLimitExceeded`].
+    ///
+    /// The aliases of the [`ShortLink`]s folded by
+    /// [`UrlShortenerService::consolidate_duplicates()`] are counted as well.
+    pub fn with_max_aliases(mut self, n: usize) -> Self {
+        self.config.max_aliases = Some(n);
+        self
+    }
+
+    /// Limits the time-to-live set with the
+    /// [`UrlShortenerService::handle_set_ttl()`] (or the
+    /// [`UrlShortenerService::handle_create_full()`]) to the provided `min`
+    /// and `max` (both inclusive), rejecting others with a
+    /// [`ShortenerError::TtlOutOfBounds`].
+    ///
+    /// Making a [`ShortLink`] never expire is allowed regardless of the bounds.
+    pub fn with_ttl_bounds(
+        mut self,
+        min: Option<Duration>,
+        max: Option<Duration>,
+    ) -> Self {
+        self.config.min_ttl = min;
+        self.config.max_ttl = max;
+        self
+    }
+
+    /// Makes the service reject creating a [`ShortLink`] to (or changing it to
+    /// point to) a [`Url`] already pointed to by another [`ShortLink`], with a
+    /// [`ShortenerError::UrlAlreadyInUse`].
+    ///
+    /// [`Url`]s are compared in the normalized form if
+    /// [`UrlShortenerService::with_normalize_urls()`] is enabled.
+    pub fn with_unique_urls(mut self, unique: bool) -> Self {
+        self.config.unique_urls = unique;
+        self
+    }
+
+    /// Makes the [`UrlShortenerService::with_unique_urls()`] check ignore the
+    /// case of hosts, so `http://Example.com` and `http://example.com` are
+    /// duplicates.
+    ///
+    /// Affects the comparison only: [`Url`]s are stored verbatim.
+    pub fn with_unique_url_host_case_insensitive(
+        mut self,
+        insensitive: bool,
+    ) -> Self {
+        self.config.unique_url_host_case_insensitive = insensitive;
+        self
+    }
+
+    /// Enables caching of up to `capacity` recent
+    /// [`UrlShortenerService::resolve()`] lookups, evicting the least recently
+    /// used ones.
+    ///
+    /// A cached lookup is invalidated whenever the [`Url`] of its
+    /// [`ShortLink`] changes, so stale [`Url`]s are never served.
+    pub fn with_resolve_cache(mut self, capacity: usize) -> Self {
+        self.resolve_cache = Some(RefCell::new(ResolveCache::new(capacity)));
+        self
+    }
+
+    /// Makes the service record a [`ReadEvent`] for every read of the data of
+    /// the [`ShortLink`]s into a read-audit log (see
+    /// [`UrlShortenerService::read_audit()`]), separate from the event log.
+    ///
+    /// The audited reads are all the ones of particular [`ShortLink`]s (every
+    /// query taking their [`Slug`]s, like [`QueryHandler::get_stats()`] or
+    /// [`UrlShortenerService::resolve()`], recording one [`ReadEvent`] per
+    /// [`Slug`]), and the ones exporting all of them
+    /// ([`UrlShortenerService::snapshot()`],
+    /// [`UrlShortenerService::analytics_snapshot()`],
+    /// [`UrlShortenerService::export_prometheus()`],
+    /// [`UrlShortenerService::render_table()`] and [`LinkQuery::collect()`]).
+    /// Aggregate queries not revealing any particular [`ShortLink`] are not
+    /// audited. Reads are recorded even if they fail.
+    pub fn with_read_auditing(mut self, audit: bool) -> Self {
+        self.read_auditing = audit;
+        self.read_audit.get_mut().clear();
+        self
+    }
+
+    /// Makes the redirects be buffered instead of bumping the counters right
+    /// away, until they're merged in bulk by
+    /// [`UrlShortenerService::flush_pending_redirects()`], so the redirects
+    /// don't contend on the counters in a sharded deployment.
+    ///
+    /// The counters are eventually consistent then: the [`Stats`] (and every
+    /// other query) don't reflect the redirects made since the last flush.
+    pub fn with_buffered_redirects(mut self, buffer: bool) -> Self {
+        self.pending_redirects = buffer.then(Vec::new);
+        self
+    }
+
+    /// Makes every redirect count as the provided `step` of redirects (`1` by
+    /// default), e.g. `10` when only every tenth redirect is recorded.
+    ///
+    /// The counts of redirects then diverge from the counts of the individual
+    /// redirects recorded along with their moments: the former are reported
+    /// by the [`Stats`], while the latter by
+    /// [`UrlShortenerService::redirect_hits()`].
+    ///
+    /// Every other query over the redirects recorded into the event log (e.g.
+    /// [`UrlShortenerService::redirect_buckets()`] or
+    /// [`UrlShortenerService::referrer_total()`]) counts each of them as its
+    /// `step`, the same way the [`Stats`] do.
+    pub fn with_redirect_increment(mut self, step: u64) -> Self {
+        self.config.redirect_increment = step;
+        self
+    }
+
+    /// Makes only a randomly sampled fraction of redirects, the provided
+    /// `rate` of them (`1.0` by default), be recorded individually into the
+    /// event log along with their moments and referers, picked with the
+    /// random number generator (see [`UrlShortenerService::with_rng()`]).
+    ///
+    /// It's the default for all the [`ShortLink`]s, which can be overridden
+    /// for the high-traffic ones with
+    /// [`UrlShortenerService::handle_set_redirect_sampling()`].
+    ///
+    /// The counts of redirects still include every redirect, while the counts
+    /// over time windows (see [`UrlShortenerService::redirect_buckets()`] and
+    /// [`UrlShortenerService::in_window_redirects()`]) are estimated by
+    /// scaling the recorded ones by the inverse of the current rate of their
+    /// [`ShortLink`]. The redirects not sampled bump the in-memory counters
+    /// only, the same way as [`UrlShortenerService::with_log_redirects()`]
+    /// makes all of them do, so they're lost on a replay until recorded by
+    /// [`UrlShortenerService::checkpoint_redirects()`].
+    ///
+    /// A `rate` not in `0.0..=1.0` range, or zero, makes the
+    /// [`UrlShortenerService::build()`] fail.
+    pub fn with_redirect_sampling(mut self, rate: f64) -> Self {
+        self.config.redirect_sample_rate = rate;
+        self
+    }
+
+    /// Sets the behavior of redirecting through a disabled [`ShortLink`]
+    /// ([`DisabledBehavior::Reject`] by default).
+    pub fn with_disabled_behavior(
+        mut self,
+        behavior: DisabledBehavior,
+    ) -> Self {
+        self.config.disabled_behavior = behavior;
+        self
+    }
+
+    /// Sets the policy of resolving a [`Slug`] provided more than once within
+    /// a single [`UrlShortenerService::handle_create_many()`] batch
+    /// ([`BatchDuplicatePolicy::FirstWins`] by default).
+    pub fn with_batch_duplicate_policy(
+        mut self,
+        policy: BatchDuplicatePolicy,
+    ) -> Self {
+        self.config.batch_duplicate_policy = policy;
+        self
+    }
+
+    /// Makes the service record every redirect into the event log (the
+    /// default), or only bump the in-memory counters otherwise.
+    ///
+    /// Not logging redirects keeps the event log small, as redirects dominate
+    /// it. The bumped counters are recorded into the event log in bulk as
+    /// [`Event::RedirectsSeeded`] by
+    /// [`UrlShortenerService::checkpoint_redirects()`] (and
+    /// [`UrlShortenerService::compact()`]), so the redirects made after the
+    /// last checkpoint are lost on a replay.
+    ///
+    /// Nothing else checkpoints them, so callers must call the
+    /// [`UrlShortenerService::checkpoint_redirects()`] before persisting the
+    /// event log or taking a [`UrlShortenerService::snapshot()`], whose
+    /// fingerprint of the event log doesn't cover the unrecorded redirects.
+    pub fn with_log_redirects(mut self, log: bool) -> Self {
+        self.config.log_redirects = log;
+        self
+    }
+
+    /// Makes the [`UrlShortenerService::snapshot()`]s include every redirect
+    /// recorded individually into the event log (along with its moment,
+    /// referer and source tag), so the time series survive the
+    /// [`UrlShortenerService::from_snapshot()`].
+    ///
+    /// Such [`Snapshot`]s grow with every redirect instead of staying of a
+    /// constant size per [`ShortLink`], becoming about as large as the event
+    /// log itself.
+    pub fn with_full_analytics_snapshot(mut self, full: bool) -> Self {
+        self.config.full_analytics_snapshot = full;
+        self
+    }
+
+    /// Makes the service [`UrlShortenerService::compact()`] its event log
+    /// automatically after every `n` appended [`Event`]s, keeping the state
+    /// identical.
+    ///
+    /// As any compaction, it discards the detailed history and starts the
+    /// sequence numbers over. `0` disables the auto-compaction (the default).
+    pub fn with_auto_compact_every(mut self, n: usize) -> Self {
+        self.config.auto_compact_every = (n > 0).then_some(n);
+        self
+    }
+
+    /// Uses the provided [`Clock`] for timestamping [`Event`]s and checking
+    /// expiration.
+    ///
+    /// The service is considered started at the current moment of the
+    /// provided [`Clock`] (see [`UrlShortenerService::started_at()`]).
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.started_at = clock.now();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Uses the provided random number generator for generating [`Slug`]s and
+    /// picking the split variants (see
+    /// [`UrlShortenerService::handle_create_split()`]).
+    ///
+    /// A seeded one makes both deterministic.
+    pub fn with_rng(mut self, rng: impl RngCore + 'static) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Adds the provided [`EventSink`] to forward every appended [`Event`] to.
+    pub fn with_event_sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Suspends forwarding [`Event`]s to the [`EventSink`]s (e.g. for the
+    /// duration of a bulk import), while still appending them to the event
+    /// log.
+    ///
+    /// Does nothing if the notifications are suspended already.
+    pub fn suspend_notifications(&mut self) {
+        self.suspended.get_or_insert_with(Vec::new);
+    }
+
+    /// Resumes forwarding [`Event`]s to the [`EventSink`]s, flushing to them
+    /// all the [`Event`]s appended while suspended, in order, before
+    /// returning.
+    ///
+    /// Does nothing if the notifications are not suspended.
+    pub fn resume_notifications(&mut self) {
+        for event in self.suspended.take().unwrap_or_default() {
+            for sink in &mut self.sinks {
+                sink.append(&event);
+            }
+        }
+    }
+
+    /// Replaces the random number generator of this service (see
+    /// [`UrlShortenerService::with_rng()`]) with a new one seeded with the
+    /// provided `seed`, e.g. when the generated [`Slug`]s cluster.
+    ///
+    /// Only the [`Slug`]s generated (and the split variants picked) from now
+    /// on are affected, while the existing [`ShortLink`]s are kept as is.
+    pub fn reseed_rng(&mut self, seed: u64) {
+        use rand::{rngs::StdRng, SeedableRng as _};
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
+    }
+
+    /// Puts the service into maintenance, so every successful redirect goes
+    /// to the provided [`Url`] instead of the [`ShortLink`]'s one, without
+    /// changing any [`ShortLink`].
+    ///
+    /// Redirects are still validated and counted as usual, so the statistics
+    /// keep reflecting the actual demand during maintenance.
+    ///
+    /// The maintenance is a runtime setting, not recorded into the event log.
+    pub fn enable_maintenance(&mut self, url: Url) {
+        self.maintenance_url = Some(url);
+    }
+
+    /// Takes the service out of maintenance (see
+    /// [`UrlShortenerService::enable_maintenance()`]).
+    ///
+    /// Does nothing if the service is not in maintenance.
+    pub fn disable_maintenance(&mut self) {
+        self.maintenance_url = None;
+    }
+
+    /// Reserves the provided [`Slug`], so creating a [`ShortLink`] with it
+    /// fails with a [`ShortenerError::SlugAlreadyInUse`], and it's never
+    /// generated.
+    ///
+    /// Reservations are a runtime setting, not recorded into the event log.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugAlreadyInUse`] if the [`Slug`] is already in use
+    /// by a [`ShortLink`].
+    pub fn reserve_slug(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        if self.state(&slug).is_ok() {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        let slug = self.canonical_slug(slug);
+        _ = self.reserved_slugs.insert(slug);
+        Ok(())
+    }
+
+    /// Releases the reservation of the provided [`Slug`] (see
+    /// [`UrlShortenerService::reserve_slug()`]).
+    ///
+    /// Does nothing if the [`Slug`] is not reserved.
+    pub fn unreserve_slug(&mut self, slug: Slug) {
+        let slug = self.canonical_slug(slug);
+        _ = self.reserved_slugs.remove(&slug);
+    }
+
+    /// Returns the whole configuration of this service, to reconstruct an
+    /// equally configured one with [`UrlShortenerService::from_config()`].
+    pub fn config(&self) -> ServiceConfig {
+        ServiceConfig {
+            config: self.config.clone(),
+            resolve_cache_capacity: self
+                .resolve_cache
+                .as_ref()
+                .map(|cache| cache.borrow().capacity),
+            read_auditing: self.read_auditing,
+            buffered_redirects: self.pending_redirects.is_some(),
+            reserved_slugs: self.reserved_slugs.clone(),
+        }
+    }
+
+    /// Returns a copy of the read-audit log of this service, empty if
+    /// [`UrlShortenerService::with_read_auditing()`] is not enabled.
+    pub fn read_audit(&self) -> Vec<ReadEvent> {
+        self.read_audit.borrow().clone()
+    }
+
+    /// Records a [`ReadEvent`] of the provided `kind` into the read-audit log,
+    /// if [`UrlShortenerService::with_read_auditing()`] is enabled.
+    fn audit_read(&self, kind: &'static str, slug: Option<&Slug>) {
+        if self.read_auditing {
+            self.read_audit.borrow_mut().push(ReadEvent {
+                kind,
+                slug: slug.cloned(),
+                at: self.clock.now(),
+            });
+        }
+    }
+
+    /// Returns the event log of this service.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Indicates whether replaying the event log (see
+    /// [`UrlShortenerService::from_events()`]) reproduces the current state
+    /// exactly, so a [`Snapshot`] is not needed to restore it.
+    ///
+    /// The redirects not logged individually (see
+    /// [`UrlShortenerService::with_log_redirects()`]) are never reproduced
+    /// exactly, as [`UrlShortenerService::checkpoint_redirects()`] records
+    /// their counts only, without their moments and sources. Once the
+    /// detailed history is discarded by
+    /// [`UrlShortenerService::compact()`] (or
+    /// [`UrlShortenerService::rotate_log()`], or
+    /// [`UrlShortenerService::split_log_at()`]), the event log is never
+    /// replayable again.
+    pub fn is_replayable(&self) -> bool {
+        if self.compacted || !self.is_consistent() {
+            return false;
+        }
+        let Ok(replayed) = Self::from_events(self.events.iter().cloned())
+        else {
+            return false;
+        };
+        self.links.iter().all(|(slug, state)| {
+            let variants = state
+                .variants
+                .iter()
+                .map(|v| (self.deobfuscate(&v.url), v.weight, v.redirects));
+            replayed.links.get(slug).is_some_and(|r| {
+                r.redirects == state.redirects
+                    && r.hits == state.hits
+                    && r.sources == state.sources
+                    && r.first_redirect == state.first_redirect
+                    && r.last_redirect == state.last_redirect
+                    && r.url_changes == state.url_changes
+                    && r.redirects_while_disabled
+                        == state.redirects_while_disabled
+                    && r.variants
+                        .iter()
+                        .map(|v| (v.url.clone(), v.weight, v.redirects))
+                        .eq(variants)
+            })
+        })
+    }
+
+    /// Returns a copy of the event log of this service with every [`Slug`]
+    /// replaced with its pseudonym (e.g. for sharing it with analysts), while
+    /// the [`Url`]s are preserved.
+    ///
+    /// Pseudonyms are derived from a 64-bit hash of the [`Slug`]s, so the
+    /// same [`Slug`] always gets the same pseudonym, while different ones are
+    /// unlikely to collide. As the hash is unkeyed, a known [`Slug`] can be
+    /// checked against the pseudonyms.
+    pub fn export_events_anonymized(&self) -> Vec<Event> {
+        self.events
+            .iter()
+            .map(|event| event.clone().map_slugs(pseudonym_of))
+            .collect()
+    }
+
+    /// Reconstructs a service by replaying the provided [`Event`]s in order.
+    ///
+    /// ## Errors
+    ///
+    /// If any of the [`Event`]s cannot be applied to the state reconstructed
+    /// from the preceding ones.
+    pub fn from_events(
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<Self, ShortenerError> {
+        let mut service = Self::new();
+        for event in events {
+            service.validate(&event)?;
+            service.append(event);
+        }
+        Ok(service)
+    }
+
+    /// Reconstructs a service out of the provided [`Snapshot`].
+    ///
+    /// If the [`Snapshot`] includes the full analytics (see
+    /// [`UrlShortenerService::with_full_analytics_snapshot()`]), the
+    /// [`ShortLink`]s are recreated at their original moments along with
+    /// their individually recorded redirects. Otherwise, they're created now,
+    /// with their counts of redirects seeded only. Either way, they keep the
+    /// [`SlugOrigin`]s of their [`Slug`]s and their metadata.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        let mut service = Self::new();
+        let mut slugs: Vec<_> = snapshot.links.keys().collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let now = service.clock.now();
+        for slug in slugs {
+            let (url, count) = &snapshot.links[slug];
+            let analytics =
+                snapshot.analytics.as_ref().and_then(|a| a.get(slug));
+            let id = snapshot.ids.get(slug).copied();
+            let origin = snapshot.origins.get(slug);
+            service.append(Event::ShortLinkCreated {
+                slug: slug.clone(),
+                url: url.clone(),
+                custom_slug: origin != Some(&SlugOrigin::Generated),
+                id: id.unwrap_or(service.last_link_id + 1),
+                at: analytics.map_or(now, |a| a.created_at),
+            });
+            for (key, value) in
+                snapshot.metadata.get(slug).into_iter().flatten()
+            {
+                service.append(Event::ShortLinkMetadataSet {
+                    slug: slug.clone(),
+                    key: key.clone(),
+                    value: Some(value.clone()),
+                    at: now,
+                });
+            }
+            let mut seeded = *count;
+            for (click, count) in analytics.iter().flat_map(|a| &a.redirects) {
+                seeded = seeded.saturating_sub(*count);
+                service.append(Event::ShortLinkRedirected {
+                    slug: slug.clone(),
+                    source: click.source.clone(),
+                    referrer: click.referrer.clone(),
+                    variant: None,
+                    count: *count,
+                    at: click.at,
+                });
+            }
+            if seeded > 0 {
+                service.append(Event::RedirectsSeeded {
+                    slug: slug.clone(),
+                    count: seeded,
+                    organic: true,
+                    at: now,
+                });
+            }
+        }
+        service
+    }
+
+    /// Returns the sequence number of the last [`Event`] in the event log, or
+    /// `0` if it's empty.
+    pub fn seq(&self) -> u64 {
+        self.events.len() as u64
+    }
+
+    /// Returns the canonical form of the provided [`Slug`] the
+    /// [`ShortLink`]s are stored under.
+    fn canonical_slug(&self, slug: Slug) -> Slug {
+        if self.config.trim_trailing_slash_slugs {
+            if let Some(trimmed) = slug.0.strip_suffix('/') {
+                if !trimmed.is_empty() {
+                    return Slug(trimmed.into());
+                }
+            }
+        }
+        slug
+    }
+
+    /// Returns the [`LinkState`] of the [`ShortLink`] with the provided
+    /// [`Slug`] (in any of its forms, see
+    /// [`UrlShortenerService::canonical_slug()`], or any of its aliases, see
+    /// [`UrlShortenerService::consolidate_duplicates()`]).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    fn state(&self, slug: &Slug) -> Result<&LinkState, ShortenerError> {
+        let canonical;
+        let slug = if self.config.trim_trailing_slash_slugs {
+            canonical = self.canonical_slug(slug.clone());
+            &canonical
+        } else {
+            slug
+        };
+        let slug = self.aliases.get(slug).unwrap_or(slug);
+        self.links.get(slug).ok_or(ShortenerError::SlugNotFound)
+    }
+
+    /// Returns the [`LinkState`]s of the active [`ShortLink`]s, the ones that
+    /// can be redirected through right now: enabled, not consumed, gone live
+    /// and not expired.
+    ///
+    /// All the queries over the active [`ShortLink`]s go through it.
+    fn active_links(&self) -> impl Iterator<Item = &LinkState> {
+        let now = self.clock.now();
+        self.links
+            .values()
+            .filter(move |state| state.is_active(now, self.config.idle_expiry))
+    }
+
+    /// Returns the [`Url`] of the provided [`LinkState`] (see
+    /// [`UrlShortenerService::with_url_obfuscation()`]).
+    fn url_of(&self, state: &LinkState) -> Url {
+        self.deobfuscate(&state.link.url)
+    }
+
+    /// Returns the [`ShortLink`] of the provided [`LinkState`] (see
+    /// [`UrlShortenerService::with_url_obfuscation()`]).
+    fn link_of(&self, state: &LinkState) -> ShortLink {
+        ShortLink {
+            slug: state.link.slug.clone(),
+            url: self.url_of(state),
+        }
+    }
+
+    /// Obfuscates the provided [`Url`] for storing it in the read model, if
+    /// [`UrlShortenerService::with_url_obfuscation()`] is enabled.
+    fn obfuscate(&self, url: &Url) -> Url {
+        let Some(key) = &self.config.url_obfuscation_key else {
+            return url.clone();
+        };
+        let hex = url
+            .0
+            .bytes()
+            .zip(key.iter().cycle())
+            .map(|(b, k)| format!("{:02x}", b ^ k))
+            .collect();
+        Url(hex)
+    }
+
+    /// Reverts the [`UrlShortenerService::obfuscate()`] of the provided
+    /// stored [`Url`].
+    fn deobfuscate(&self, stored: &Url) -> Url {
+        let Some(key) = &self.config.url_obfuscation_key else {
+            return stored.clone();
+        };
+        let bytes: Vec<u8> = (0..stored.0.len())
+            .step_by(2)
+            .filter_map(|i| u8::from_str_radix(&stored.0[i..i + 2], 16).ok())
+            .zip(key.iter().cycle())
+            .map(|(b, k)| b ^ k)
+            .collect();
+        Url(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Checks whether the provided [`Event`] may be applied to the current
+    /// state.
+    fn validate(&self, event: &Event) -> Result<(), ShortenerError> {
+        let in_use = |slug| {
+            self.links.contains_key(slug) || self.aliases.contains_key(slug)
+        };
+        let exists = self.links.contains_key(event.slug());
+        match event {
+            Event::ShortLinkRenamed { new_slug, .. } if in_use(new_slug) => {
+                Err(ShortenerError::SlugAlreadyInUse)
+            }
+            Event::ShortLinkCreated { slug, .. } if in_use(slug) => {
+                Err(ShortenerError::SlugAlreadyInUse)
+            }
+            Event::ShortLinkMerged { slug, into, .. }
+                if slug == into || !self.links.contains_key(into) =>
+            {
+                Err(ShortenerError::SlugNotFound)
+            }
+            Event::ShortLinkAliasAdded { alias, .. } if in_use(alias) => {
+                Err(ShortenerError::SlugAlreadyInUse)
+            }
+            Event::ShortLinkAliasRemoved { slug, alias, .. }
+                if self.aliases.get(alias) != Some(slug) =>
+            {
+                Err(ShortenerError::SlugNotFound)
+            }
+            Event::ShortLinkCreated { .. } => Ok(()),
+            _ if !exists => Err(ShortenerError::SlugNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Prepares the provided [`Url`] for being stored, checking whether it's
+    /// an absolute one allowed by the configuration of this service and
+    /// completing and normalizing it, if required.
+    fn prepare_url(&self, url: Url) -> Result<Url, ShortenerError> {
+        let url = match &self.config.default_scheme {
+            Some(scheme) if !url.0.contains("://") => {
+                Url(format!("{scheme}://{}", url.0))
+            }
+            _ => url,
+        };
+        if !url.is_absolute() {
+            return Err(ShortenerError::InvalidUrl);
+        }
+
+        let url = if self.config.normalize_urls {
+            url.normalized()
+        } else {
+            url
+        };
+
+        if self.config.require_public_hostname {
+            let host = url.host().ok_or(ShortenerError::InvalidUrl)?;
+            if host.starts_with('[')
+                || host.parse::<std::net::Ipv4Addr>().is_ok()
+                || !host.contains('.')
+            {
+                return Err(ShortenerError::InvalidUrl);
+            }
+        }
+        Ok(url)
+    }
+
+    /// Indicates whether the provided [`Url`]s point to the same target,
+    /// respecting the normalization settings.
+    fn same_url(&self, a: &Url, b: &Url) -> bool {
+        if self.config.normalize_urls {
+            a.normalized() == b.normalized()
+        } else {
+            a == b
+        }
+    }
+
+    /// Checks whether the provided time-to-live (or [`None`] for never
+    /// expiring) is within the bounds (see
+    /// [`UrlShortenerService::with_ttl_bounds()`]).
+    fn check_ttl(&self, ttl: Option<Duration>) -> Result<(), ShortenerError> {
+        let (min, max) = (self.config.min_ttl, self.config.max_ttl);
+        let within = match ttl {
+            Some(ttl) => {
+                min.is_none_or(|min| min <= ttl)
+                    && max.is_none_or(|max| ttl <= max)
+            }
+            None => true,
+        };
+        if !within {
+            return Err(ShortenerError::TtlOutOfBounds);
+        }
+        Ok(())
+    }
+
+    /// Checks whether the provided [`Url`] is not pointed to by any
+    /// [`ShortLink`] other than the `except` ones, if
+    /// [`UrlShortenerService::with_unique_urls()`] is enabled.
+    fn check_unique_url(
+        &self,
+        url: &Url,
+        except: &[Slug],
+    ) -> Result<(), ShortenerError> {
+        if !self.config.unique_urls {
+            return Ok(());
+        }
+
+        let duplicate = self.links.values().any(|state| {
+            !except.contains(&state.link.slug)
+                && self.same_unique_url(&self.url_of(state), url)
+        });
+        if duplicate {
+            return Err(ShortenerError::UrlAlreadyInUse);
+        }
+        Ok(())
+    }
+
+    /// Indicates whether the provided [`Url`]s are the same in terms of
+    /// [`UrlShortenerService::with_unique_urls()`].
+    fn same_unique_url(&self, a: &Url, b: &Url) -> bool {
+        if self.config.unique_url_host_case_insensitive {
+            self.same_url(&a.with_lowercased_host(), &b.with_lowercased_host())
+        } else {
+            self.same_url(a, b)
+        }
+    }
+
+    /// Appends the provided [`Event`] to the event log, applies it to the read
+    /// model and forwards it to the [`EventSink`]s.
+    ///
+    /// The [`Event`] must be validated beforehand.
+    fn append(&mut self, event: Event) {
+        self.apply(&event);
+        self.record(event);
+
+        self.appended_since_compaction += 1;
+        if let Some(every) = self.config.auto_compact_every {
+            if self.appended_since_compaction >= every {
+                self.compact();
+            }
+        }
+    }
+
+    /// Appends the provided [`Event`] to the event log and forwards it to the
+    /// [`EventSink`]s, without applying it to the read model.
+    ///
+    /// The [`Event`] must be already reflected in the read model.
+    fn record(&mut self, event: Event) {
+        match &mut self.suspended {
+            Some(pending) => pending.push(event.clone()),
+            None => {
+                for sink in &mut self.sinks {
+                    sink.append(&event);
+                }
+            }
+        }
+        self.events.push(event);
+    }
+
+    /// Applies the provided [`Event`] to the read model.
+    fn apply(&mut self, event: &Event) {
+        if let Event::ShortLinkCreated { .. }
+        | Event::ShortLinkChanged { .. }
+        | Event::ShortLinkRenamed { .. }
+        | Event::ShortLinkMerged { .. }
+        | Event::ShortLinkAliasRemoved { .. }
+        | Event::ShortLinkDeleted { .. } = event
+        {
+            if let Some(cache) = &self.resolve_cache {
+                let mut cache = cache.borrow_mut();
+                cache.invalidate(event.slug());
+                for (alias, target) in &self.aliases {
+                    if target == event.slug() {
+                        cache.invalidate(alias);
+                    }
+                }
+            }
+        }
+
+        if let Event::ShortLinkRenamed { slug, new_slug, .. }
+        | Event::ShortLinkMerged {
+            slug,
+            into: new_slug,
+            ..
+        } = event
+        {
+            for target in self.aliases.values_mut() {
+                if target == slug {
+                    *target = new_slug.clone();
+                }
+            }
+        }
+
+        if let Event::ShortLinkRenamed { slug, new_slug, .. } = event {
+            if let Some(mut state) = self.links.remove(slug) {
+                state.link.slug = new_slug.clone();
+                self.links.insert(new_slug.clone(), state);
+            }
+            return;
+        }
+
+        if let Event::ShortLinkDeleted { slug, .. } = event {
+            self.links.remove(slug);
+            self.aliases.retain(|_, target| target != slug);
+            return;
+        }
+
+        if let Event::ShortLinkMerged { slug, into, .. } = event {
+            let Some(merged) = self.links.remove(slug) else {
+                return;
+            };
+            self.aliases.insert(slug.clone(), into.clone());
+            if let Some(state) = self.links.get_mut(into) {
+                state.redirects =
+                    state.redirects.saturating_add(merged.redirects);
+                state.unlogged_redirects = state
+                    .unlogged_redirects
+                    .saturating_add(merged.unlogged_redirects);
+                state.seeded = state.seeded.saturating_add(merged.seeded);
+                state.hits = state.hits.saturating_add(merged.hits);
+                state.redirects_while_disabled = state
+                    .redirects_while_disabled
+                    .saturating_add(merged.redirects_while_disabled);
+                for (source, count) in merged.sources {
+                    let total = state.sources.entry(source).or_default();
+                    *total = total.saturating_add(count);
+                }
+                for variant in merged.variants {
+                    if let Some(v) =
+                        state.variants.iter_mut().find(|v| v.url == variant.url)
+                    {
+                        v.redirects =
+                            v.redirects.saturating_add(variant.redirects);
+                    }
+                }
+                state.first_redirect = state
+                    .first_redirect
+                    .into_iter()
+                    .chain(merged.first_redirect)
+                    .min();
+                state.last_redirect =
+                    state.last_redirect.max(merged.last_redirect);
+            }
+            return;
+        }
+
+        if let Event::ShortLinkCreated {
+            slug,
+            url,
+            custom_slug,
+            id,
+            at,
+        } = event
+        {
+            let link = ShortLink {
+                slug: slug.clone(),
+                url: self.obfuscate(url),
+            };
+            let mut state = LinkState::new(link, *at);
+            state.custom_slug = *custom_slug;
+            state.id = *id;
+            self.last_link_id = self.last_link_id.max(*id);
+            if !custom_slug {
+                self.generated_slugs = self.generated_slugs.saturating_add(1);
+            }
+            self.links.insert(slug.clone(), state);
+            return;
+        }
+
+        if let Event::ShortLinkChanged { slug, new_url, .. } = event {
+            let url = self.obfuscate(new_url);
+            if let Some(state) = self.links.get_mut(slug) {
+                state.link.url = url;
+                state.variants.clear();
+                state.url_changes = state.url_changes.saturating_add(1);
+            }
+            return;
+        }
+
+        if let Event::ShortLinkAliasAdded { slug, alias, .. } = event {
+            self.aliases.insert(alias.clone(), slug.clone());
+            return;
+        }
+
+        if let Event::ShortLinkAliasRemoved { alias, .. } = event {
+            self.aliases.remove(alias);
+            return;
+        }
+
+        if let Event::ShortLinkSplitSet { slug, variants, .. } = event {
+            let variants = variants
+                .iter()
+                .map(|(url, weight)| Variant {
+                    url: self.obfuscate(url),
+                    weight: *weight,
+                    redirects: 0,
+                })
+                .collect();
+            if let Some(state) = self.links.get_mut(slug) {
+                state.variants = variants;
+            }
+            return;
+        }
+
+        let Some(state) = self.links.get_mut(event.slug()) else {
+            return;
+        };
+        match event {
+            Event::ShortLinkCreated { .. }
+            | Event::ShortLinkChanged { .. }
+            | Event::ShortLinkRenamed { .. }
+            | Event::ShortLinkSplitSet { .. }
+            | Event::ShortLinkMerged { .. }
+            | Event::ShortLinkDeleted { .. }
+            | Event::ShortLinkAliasAdded { .. }
+            | Event::ShortLinkAliasRemoved { .. } => {}
+            Event::ShortLinkRedirected {
+                source,
+                variant,
+                count,
+                at,
+                ..
+            } => {
+                state.record_redirect(source.as_ref(), *variant, *count, *at);
+            }
+            Event::ShortLinkDisabled { .. } => state.enabled = false,
+            Event::ShortLinkEnabled { .. } => state.enabled = true,
+            Event::ShortLinkExpirySet { expires_at, .. } => {
+                state.expires_at = *expires_at;
+            }
+            Event::ShortLinkNoteSet { note, .. } => state.note = note.clone(),
+            Event::ShortLinkTitleSet { title, .. } => {
+                state.title = title.clone();
+            }
+            Event::ShortLinkActivationScheduled { active_from, .. } => {
+                state.active_from = Some(*active_from);
+            }
+            Event::ShortLinkCountriesSet { countries, .. } => {
+                state.allowed_countries = countries.clone();
+            }
+            Event::ShortLinkReferersSet { referers, .. } => {
+                state.allowed_referers = referers.clone();
+            }
+            Event::ShortLinkResponseSet {
+                status,
+                content_type,
+                ..
+            } => {
+                state.redirect_status = *status;
+                state.content_type = content_type.clone();
+            }
+            Event::ShortLinkCampaignAssigned { campaign, .. } => {
+                state.campaign = Some(campaign.clone());
+            }
+            Event::ShortLinkSamplingSet { rate, .. } => {
+                state.sample_rate = *rate;
+            }
+            Event::ShortLinkMetadataSet { key, value, .. } => {
+                if let Some(value) = value {
+                    _ = state.metadata.insert(key.clone(), value.clone());
+                } else {
+                    _ = state.metadata.remove(key);
+                }
+            }
+            Event::RedirectsSeeded { count, organic, .. } => {
+                state.redirects = state.redirects.saturating_add(*count);
+                if !organic {
+                    state.seeded = state.seeded.saturating_add(*count);
+                }
+            }
+            Event::ShortLinkMadeOneTime { .. } => state.one_time = true,
+            Event::ShortLinkMadeTemplate { .. } => state.template = true,
+            Event::ShortLinkConsumed { .. } => state.consumed = true,
+        }
+    }
+
+    /// Creates a new [`ShortLink`], returning it along with the count of
+    /// attempts it took to generate its [`Slug`] (`1` for a provided one).
+    fn create(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<(ShortLink, u32), ShortenerError> {
+        let url = self.prepare_url(url)?;
+
+        let custom_slug = slug.is_some();
+        let (slug, attempts) = match slug {
+            Some(s) => (self.check_custom_slug(s)?, 1),
+            None => self.generate_unused_slug()?,
+        };
+        self.check_unique_url(&url, &[])?;
+
+        self.append(Event::ShortLinkCreated {
+            slug: slug.clone(),
+            url: url.clone(),
+            custom_slug,
+            id: self.last_link_id + 1,
+            at: self.clock.now(),
+        });
+        Ok((ShortLink { slug, url }, attempts))
+    }
+
+    /// Indicates, for every one of the provided batch items, whether it loses
+    /// to another item with the same [`Slug`] according to the
+    /// [`UrlShortenerService::with_batch_duplicate_policy()`].
+    fn batch_duplicate_losers(
+        &self,
+        items: &[(Url, Option<Slug>)],
+    ) -> Vec<bool> {
+        let slugs: Vec<_> = items
+            .iter()
+            .map(|(_, slug)| slug.clone().map(|s| self.canonical_slug(s)))
+            .collect();
+        let mut winners = HashMap::new();
+        for (i, slug) in slugs.iter().enumerate() {
+            let Some(slug) = slug else {
+                continue;
+            };
+            match self.config.batch_duplicate_policy {
+                BatchDuplicatePolicy::FirstWins => {
+                    _ = winners.entry(slug).or_insert(Some(i));
+                }
+                BatchDuplicatePolicy::LastWins => {
+                    _ = winners.insert(slug, Some(i));
+                }
+                BatchDuplicatePolicy::Error => {
+                    let winner = winners.entry(slug).or_insert(Some(i));
+                    if *winner != Some(i) {
+                        *winner = None;
+                    }
+                }
+            }
+        }
+        slugs
+            .iter()
+            .enumerate()
+            .map(|(i, slug)| {
+                slug.as_ref().is_some_and(|s| winners[s] != Some(i))
+            })
+            .collect()
+    }
+
+    /// Checks whether the provided [`Slug`] can be used for a new
+    /// [`ShortLink`], returning its canonical form (see
+    /// [`UrlShortenerService::canonical_slug()`]).
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugFormatReserved`] if the [`Slug`] has the format
+    ///   of the generated ones (see
+    ///   [`UrlShortenerService::with_distinct_custom_slug_format()`]).
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the [`Slug`] is already in
+    ///   use (or reserved, see [`UrlShortenerService::reserve_slug()`]).
+    fn check_custom_slug(&self, slug: Slug) -> Result<Slug, ShortenerError> {
+        if self.config.distinct_custom_slug_format
+            && self.has_generated_format(&slug)
+        {
+            return Err(ShortenerError::SlugFormatReserved);
+        }
+        let slug = self.canonical_slug(slug);
+        if self.state(&slug).is_ok() || self.reserved_slugs.contains(&slug) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        Ok(slug)
+    }
+
+    /// Returns a copy of this service's state to stage changes on, replayed
+    /// from the event log and configured the same way (except the automatic
+    /// compaction), whose time stands still at the current moment.
+    fn staging(&self) -> Self {
+        let mut staging =
+            Self::new().with_clock(clock::MockClock::new(self.clock.now()));
+        staging.config = self.config.clone();
+        staging.config.auto_compact_every = None;
+        staging.reserved_slugs = self.reserved_slugs.clone();
+        for event in &self.events {
+            staging.apply(event);
+            staging.events.push(event.clone());
+        }
+        staging
+    }
+
+    /// Performs the provided [`Operation`].
+    fn perform(&mut self, op: Operation) -> Result<(), ShortenerError> {
+        match op {
+            Operation::Create { url, slug } => self.create(url, slug).map(drop),
+            Operation::Change { slug, new_url } => {
+                self.handle_change_short_link(slug, new_url).map(drop)
+            }
+            Operation::Delete { slug } => self.handle_delete(slug),
+            Operation::Rename { slug, new_slug } => {
+                let slug = self.state(&slug)?.link.slug.clone();
+                if self.state(&new_slug).is_ok()
+                    || self.reserved_slugs.contains(&new_slug)
+                {
+                    return Err(ShortenerError::SlugAlreadyInUse);
+                }
+                let new_slug = self.canonical_slug(new_slug);
+                let at = self.clock.now();
+                self.append(Event::ShortLinkRenamed { slug, new_slug, at });
+                Ok(())
+            }
+            Operation::SetMetadata { slug, key, value } => {
+                self.handle_set_metadata(slug, key, value)
+            }
+            Operation::SetNote { slug, note } => {
+                self.handle_set_note(slug, note)
+            }
+            Operation::Disable { slug } => self.handle_disable(slug),
+            Operation::Enable { slug } => self.handle_enable(slug),
+        }
+    }
+
+    /// Processes a redirection by [`Slug`] optionally tagged with a `source`
+    /// and made from a `referrer`.
+    fn redirect(
+        &mut self,
+        slug: Slug,
+        source: Option<String>,
+        referrer: Option<String>,
+    ) -> Result<ShortLink, ShortenerError> {
+        let now = self.clock.now();
+        let state = self.state(&slug)?;
+        if state.consumed {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if !state.enabled
+            && self.config.disabled_behavior == DisabledBehavior::Reject
+        {
+            return Err(ShortenerError::LinkDisabled);
+        }
+        if !state.is_live(now) {
+            return Err(ShortenerError::NotYetActive);
+        }
+        if state.is_expired(now, self.config.idle_expiry) {
+            return Err(ShortenerError::LinkExpired);
+        }
+        let (mut link, one_time) = (self.link_of(state), state.one_time);
+        let template = state.template;
+        let weights: Vec<_> = state.variants.iter().map(|v| v.weight).collect();
+        let slug = link.slug.clone();
+
+        let variant = self.pick_variant(&weights);
+        if let Some(i) = variant {
+            link.url = self.deobfuscate(&self.links[&slug].variants[i].url);
+        }
+        if template {
+            link.url = expand_template(&link.url, &slug, now);
+        }
+        if let Some(pending) = &mut self.pending_redirects {
+            pending.push(PendingRedirect {
+                slug: slug.clone(),
+                source,
+                referrer,
+                variant,
+                at: now,
+            });
+        } else {
+            self.record_redirect(slug.clone(), source, referrer, variant, now);
+        }
+        if one_time {
+            self.append(Event::ShortLinkConsumed { slug, at: now });
+        }
+        if let Some(url) = &self.maintenance_url {
+            link.url = url.clone();
+        }
+        Ok(link)
+    }
+
+    /// Records a redirect of the existing [`ShortLink`] with the provided
+    /// [`Slug`], made at the provided moment, either into the event log or
+    /// the in-memory counters only (see
+    /// [`UrlShortenerService::with_log_redirects()`]).
+    fn record_redirect(
+        &mut self,
+        slug: Slug,
+        source: Option<String>,
+        referrer: Option<String>,
+        variant: Option<usize>,
+        at: SystemTime,
+    ) {
+        let count = self.config.redirect_increment;
+        let rate = self.sample_rate_of(self.links.get(&slug));
+        let sampled = rate >= 1.0 || self.rng.gen_bool(rate);
+        if self.config.log_redirects && sampled {
+            self.append(Event::ShortLinkRedirected {
+                slug,
+                source,
+                referrer,
+                variant,
+                count,
+                at,
+            });
+        } else if let Some(state) = self.links.get_mut(&slug) {
+            state.record_redirect(source.as_ref(), variant, count, at);
+            state.unlogged_redirects =
+                state.unlogged_redirects.saturating_add(count);
+        }
+    }
+
+    /// Returns the fraction of the redirects of the provided [`LinkState`]
+    /// recorded individually into the event log (see
+    /// [`UrlShortenerService::handle_set_redirect_sampling()`]), or the
+    /// default one of the service for a [`ShortLink`] not existing anymore.
+    fn sample_rate_of(&self, state: Option<&LinkState>) -> f64 {
+        state
+            .and_then(|state| state.sample_rate)
+            .unwrap_or(self.config.redirect_sample_rate)
+    }
+
+    /// Estimates the count of redirects of the provided [`LinkState`] from
+    /// the provided count of the ones recorded individually into the event
+    /// log, given its sample rate (see
+    /// [`UrlShortenerService::sample_rate_of()`]).
+    fn unsample(&self, state: Option<&LinkState>, recorded: u64) -> u64 {
+        (recorded as f64 / self.sample_rate_of(state)).round() as u64
+    }
+
+    /// Picks the index of a split variant randomly according to the provided
+    /// weights, if any.
+    fn pick_variant(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|w| u64::from(*w)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = self.rng.gen_range(0..total);
+        weights.iter().position(|w| {
+            let w = u64::from(*w);
+            if roll < w {
+                return true;
+            }
+            roll -= w;
+            false
+        })
+    }
+
+    /// Indicates whether replaying the event log reproduces the current state
+    /// (except the redirects not logged individually, and the details lost on
+    /// [`UrlShortenerService::compact()`]).
+    fn is_consistent(&self) -> bool {
+        let Ok(replayed) = Self::from_events(self.events.iter().cloned())
+        else {
+            return false;
+        };
+        replayed.aliases == self.aliases
+            && replayed.links.len() == self.links.len()
+            && self.links.iter().all(|(slug, state)| {
+                replayed.links.get(slug).is_some_and(|r| {
+                    r.link.url == self.url_of(state)
+                        && r.redirects.saturating_add(state.unlogged_redirects)
+                            == state.redirects
+                        && r.seeded == state.seeded
+                        && r.created_at == state.created_at
+                        && r.custom_slug == state.custom_slug
+                        && r.id == state.id
+                        && r.enabled == state.enabled
+                        && r.expires_at == state.expires_at
+                        && r.note == state.note
+                        && r.title == state.title
+                        && r.active_from == state.active_from
+                        && r.allowed_countries == state.allowed_countries
+                        && r.allowed_referers == state.allowed_referers
+                        && r.metadata == state.metadata
+                        && r.redirect_status == state.redirect_status
+                        && r.content_type == state.content_type
+                        && r.campaign == state.campaign
+                        && r.one_time == state.one_time
+                        && r.template == state.template
+                        && r.consumed == state.consumed
+                        && r.sample_rate == state.sample_rate
+                })
+            })
+    }
+
+    /// Returns all the [`Event`]s of the current [`ShortLink`] with the
+    /// provided [`Slug`] in chronological order, following its renames back
+    /// to its creation.
+    fn link_events(&self, slug: &Slug) -> Vec<&Event> {
+        let mut current = slug;
+        let mut events = Vec::new();
+        for event in self.events.iter().rev() {
+            match event {
+                Event::ShortLinkRenamed { slug, new_slug, .. }
+                    if new_slug == current =>
+                {
+                    current = slug;
+                }
+                _ if event.slug() != current => continue,
+                _ => {}
+            }
+            events.push(event);
+            if matches!(event, Event::ShortLinkCreated { .. }) {
+                break;
+            }
+        }
+        events.reverse();
+        events
+    }
+
+    /// Returns the [`LinkAnalytics`] of the provided [`LinkState`], as
+    /// recorded in the event log.
+    fn analytics_of(&self, state: &LinkState) -> LinkAnalytics {
+        let redirects = self
+            .link_events(&state.link.slug)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ShortLinkRedirected {
+                    source,
+                    referrer,
+                    count,
+                    at,
+                    ..
+                } => {
+                    let click = ClickRecord {
+                        at: *at,
+                        referrer: referrer.clone(),
+                        source: source.clone(),
+                    };
+                    Some((click, *count))
+                }
+                _ => None,
+            })
+            .collect();
+        LinkAnalytics {
+            created_at: state.created_at,
+            redirects,
+        }
+    }
+
+    /// Returns the 64-bit FNV-1a hash of the [`Event`]s in the event log, in
+    /// order.
+    fn log_fingerprint(&self) -> u64 {
+        self.events.iter().fold(FNV_OFFSET_BASIS, |hash, event| {
+            // `0xff` never occurs in UTF-8, so it separates the events
+            // unambiguously.
+            let hash = fnv1a(hash, format!("{event:?}").as_bytes());
+            fnv1a(hash, &[0xff])
+        })
+    }
+
+    /// Returns the minimal event log reproducing the current state: a
+    /// creation of every [`ShortLink`] with its current [`Url`], followed by
+    /// the [`Event`]s setting its attributes and an [`Event::RedirectsSeeded`]
+    /// with its count of redirects, omitting all the intermediate history.
+    ///
+    /// Replaying it with [`UrlShortenerService::from_events()`] (e.g. for
+    /// handing off to a new service) produces identical [`Stats`].
+    pub fn minimal_event_log(&self) -> Vec<Event> {
+        let mut states: Vec<_> = self.links.values().collect();
+        states.sort_by(|a, b| {
+            (a.created_at, &a.link.slug.0).cmp(&(b.created_at, &b.link.slug.0))
+        });
+
+        let at = self.clock.now();
+        let mut events = Vec::new();
+        for state in states {
+            events.extend(self.state_events(state, at));
+        }
+
+        let mut aliases: Vec<_> = self.aliases.iter().collect();
+        aliases.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (alias, target) in aliases {
+            let Some(state) = self.links.get(target) else {
+                continue;
+            };
+            // Merged right away, so no id is ever observed.
+            events.push(Event::ShortLinkCreated {
+                slug: alias.clone(),
+                url: self.url_of(state),
+                custom_slug: true,
+                id: 0,
+                at: state.created_at,
+            });
+            events.push(Event::ShortLinkMerged {
+                slug: alias.clone(),
+                into: target.clone(),
+                at,
+            });
+        }
+        events
+    }
+
+    /// Returns the [`Event`]s creating a [`ShortLink`] in the provided
+    /// [`LinkState`], recorded at the provided moment except the creation.
+    fn state_events(&self, state: &LinkState, at: SystemTime) -> Vec<Event> {
+        let slug = &state.link.slug;
+        let mut events = Vec::new();
+        events.push(Event::ShortLinkCreated {
+            slug: slug.clone(),
+            url: self.url_of(state),
+            custom_slug: state.custom_slug,
+            id: state.id,
+            at: state.created_at,
+        });
+        if !state.variants.is_empty() {
+            events.push(Event::ShortLinkSplitSet {
+                slug: slug.clone(),
+                variants: state
+                    .variants
+                    .iter()
+                    .map(|v| (self.deobfuscate(&v.url), v.weight))
+                    .collect(),
+                at,
+            });
+        }
+        if !state.enabled {
+            events.push(Event::ShortLinkDisabled {
+                slug: slug.clone(),
+                at,
+            });
+        }
+        if state.expires_at.is_some() {
+            events.push(Event::ShortLinkExpirySet {
+                slug: slug.clone(),
+                expires_at: state.expires_at,
+                at,
+            });
+        }
+        if state.note.is_some() {
+            events.push(Event::ShortLinkNoteSet {
+                slug: slug.clone(),
+                note: state.note.clone(),
+                at,
+            });
+        }
+        if let Some(active_from) = state.active_from {
+            events.push(Event::ShortLinkActivationScheduled {
+                slug: slug.clone(),
+                active_from,
+                at,
+            });
+        }
+        if state.title.is_some() {
+            events.push(Event::ShortLinkTitleSet {
+                slug: slug.clone(),
+                title: state.title.clone(),
+                at,
+            });
+        }
+        if !state.allowed_countries.is_empty() {
+            events.push(Event::ShortLinkCountriesSet {
+                slug: slug.clone(),
+                countries: state.allowed_countries.clone(),
+                at,
+            });
+        }
+        if !state.allowed_referers.is_empty() {
+            events.push(Event::ShortLinkReferersSet {
+                slug: slug.clone(),
+                referers: state.allowed_referers.clone(),
+                at,
+            });
+        }
+        for (key, value) in &state.metadata {
+            events.push(Event::ShortLinkMetadataSet {
+                slug: slug.clone(),
+                key: key.clone(),
+                value: Some(value.clone()),
+                at,
+            });
+        }
+        if state.redirect_status != 302 || state.content_type.is_some() {
+            events.push(Event::ShortLinkResponseSet {
+                slug: slug.clone(),
+                status: state.redirect_status,
+                content_type: state.content_type.clone(),
+                at,
+            });
+        }
+        if let Some(campaign) = &state.campaign {
+            events.push(Event::ShortLinkCampaignAssigned {
+                slug: slug.clone(),
+                campaign: campaign.clone(),
+                at,
+            });
+        }
+        if state.sample_rate.is_some() {
+            events.push(Event::ShortLinkSamplingSet {
+                slug: slug.clone(),
+                rate: state.sample_rate,
+                at,
+            });
+        }
+        if state.one_time {
+            events.push(Event::ShortLinkMadeOneTime {
+                slug: slug.clone(),
+                at,
+            });
+        }
+        if state.template {
+            events.push(Event::ShortLinkMadeTemplate {
+                slug: slug.clone(),
+                at,
+            });
+        }
+        let organic = state.redirects.saturating_sub(state.seeded);
+        for (count, organic) in [(organic, true), (state.seeded, false)] {
+            if count > 0 {
+                events.push(Event::RedirectsSeeded {
+                    slug: slug.clone(),
+                    count,
+                    organic,
+                    at,
+                });
+            }
+        }
+        if state.consumed {
+            events.push(Event::ShortLinkConsumed {
+                slug: slug.clone(),
+                at,
+            });
+        }
+        events
+    }
+
+    /// Returns the [`Slug`] of the provided [`Url`] if it's a short one of
+    /// this service (see [`UrlShortenerService::with_base_url()`]).
+    fn slug_of_short_url(&self, url: &Url) -> Option<Slug> {
+        let base = self.config.base_url.as_ref()?.normalized();
+        let url = url.normalized();
+        let path = url.0.strip_prefix(base.0.trim_end_matches('/'))?;
+        let slug = path.strip_prefix('/')?;
+        let slug = &slug[..slug.find(['?', '#']).unwrap_or(slug.len())];
+        (!slug.is_empty()).then(|| Slug(slug.into()))
+    }
+
+    /// Returns the characters the generated [`Slug`]s consist of (see
+    /// [`UrlShortenerService::with_slug_alphabet()`]).
+    fn slug_alphabet(&self) -> Vec<char> {
+        match &self.config.slug_alphabet {
+            Some(alphabet) => alphabet.clone(),
+            None => ('0'..='9').chain('A'..='Z').chain('a'..='z').collect(),
+        }
+    }
+
+    /// Indicates whether the provided [`Slug`] contains any of the words the
+    /// generated ones must not contain (see
+    /// [`UrlShortenerService::with_slug_denylist()`]).
+    fn is_denied_slug(&self, slug: &str) -> bool {
+        let slug = slug.to_lowercase();
+        self.config
+            .slug_denylist
+            .iter()
+            .any(|w| slug.contains(w.as_str()))
+    }
+
+    /// Indicates whether the provided [`Slug`] has the exact format of the
+    /// generated ones.
+    fn has_generated_format(&self, slug: &Slug) -> bool {
+        let alphabet = self.slug_alphabet();
+        slug.0.chars().count() == self.config.slug_length
+            && slug.0.chars().all(|c| alphabet.contains(&c))
+    }
+
+    /// Generates a random slug (basic version)
+    fn generate_random_slug(&mut self) -> Slug {
+        use rand::seq::SliceRandom; // Ensure import is in the function scope
+        let alphabet = self.slug_alphabet();
+        let slug: String = (0..self.config.slug_length)
+            .filter_map(|_| alphabet.choose(&mut *self.rng))
+            .collect();
+        Slug(slug)
+    }
+
+    /// Returns the `n`-th sequentially generated [`Slug`] (see
+    /// [`UrlShortenerService::with_sequential_slugs()`]).
+    fn sequential_slug(&self, mut n: u64) -> Slug {
+        let alphabet = self.slug_alphabet();
+        let Some(&zero) = alphabet.first() else {
+            return Slug(String::new());
+        };
+        let base = alphabet.len() as u64;
+        let mut digits = Vec::new();
+        while n > 0 || digits.is_empty() {
+            digits.push(alphabet[(n % base) as usize]);
+            n /= base;
+        }
+        while digits.len() < self.config.slug_length {
+            digits.push(zero);
+        }
+        Slug(digits.into_iter().rev().collect())
+    }
+
+    /// Indicates whether the provided generated [`Slug`] can be used: it's not
+    /// in use, denied or reserved already.
+    fn is_unused_slug(&self, slug: &Slug) -> bool {
+        !self.is_denied_slug(&slug.0)
+            && !self.reserved_slugs.contains(slug)
+            && self.state(slug).is_err()
+    }
+
+    /// Generates a random [`Slug`] not in use yet, returning it along with the
+    /// count of attempts it took.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugAlreadyInUse`] if all the attempts (see
+    /// [`UrlShortenerService::with_slug_generation_attempts()`]) generated
+    /// [`Slug`]s in use or denied already.
+    fn generate_unused_slug(&mut self) -> Result<(Slug, u32), ShortenerError> {
+        for attempt in 1..=self.config.slug_generation_attempts.max(1) {
+            let slug = if self.config.sequential_slugs {
+                let n = self.generated_slugs + u64::from(attempt - 1);
+                self.sequential_slug(n)
+            } else {
+                self.generate_random_slug()
+            };
+            if self.is_unused_slug(&slug) {
+                return Ok((slug, attempt));
+            }
+        }
+        Err(ShortenerError::SlugAlreadyInUse)
+    }
+}
+
+impl Default for UrlShortenerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl commands::CommandHandler for UrlShortenerService {
+    fn handle_create_short_link(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.create(url, slug).map(|(link, _)| link)
+    }
+
+    fn handle_redirect(
+        &mut self,
+        slug: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.redirect(slug, None, None)
+    }
+
+    fn handle_change_short_link(
+        &mut self,
+        slug: Slug,
+        new_url: Url,
+    ) -> Result<ShortLink, ShortenerError> {
+        let state = self.state(&slug)?;
+        if self
+            .config
+            .max_url_changes
+            .is_some_and(|max| state.url_changes >= max)
+        {
+            return Err(ShortenerError::ChangeLimitExceeded);
+        }
+        let slug = state.link.slug.clone();
+        let new_url = self.prepare_url(new_url)?;
+        self.check_unique_url(&new_url, slice::from_ref(&slug))?;
+
+        self.append(Event::ShortLinkChanged {
+            slug: slug.clone(),
+            new_url: new_url.clone(),
+            at: self.clock.now(),
+        });
+        Ok(ShortLink { slug, url: new_url })
+    }
+}
+
+impl queries::QueryHandler for UrlShortenerService {
+    fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
+        self.audit_read("get_stats", Some(&slug));
+        let state = self.state(&slug)?;
+        Ok(Stats {
+            link: self.link_of(state),
+            redirects: state.redirects,
+        })
+    }
+}
+
+/// Additional commands beyond the [`CommandHandler`].
+impl UrlShortenerService {
+    /// Claims the provided [`Slug`] for a new [`ShortLink`] pointing to the
+    /// provided [`Url`], or fails.
+    ///
+    /// It never generates a [`Slug`] nor falls back to another one, while
+    /// the provided one is stored in its canonical form, the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does (e.g. without a
+    /// trailing slash, see
+    /// [`UrlShortenerService::with_trim_trailing_slash_slugs()`]).
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the [`Slug`] is taken.
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_create_short_link()`] does.
+    pub fn try_claim(
+        &mut self,
+        url: Url,
+        slug: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.create(url, Some(slug)).map(|(link, _)| link)
+    }
+
+    /// Creates a new [`ShortLink`] for every one of the provided items one
+    /// after another, the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does, returning the
+    /// results in the same order.
+    ///
+    /// A [`Slug`] provided more than once within the batch is resolved
+    /// according to the [`UrlShortenerService::with_batch_duplicate_policy()`]
+    /// upfront, regardless of whether the winning item turns out to be valid.
+    pub fn handle_create_many(
+        &mut self,
+        items: &[(Url, Option<Slug>)],
+    ) -> Vec<Result<ShortLink, ShortenerError>> {
+        let losers = self.batch_duplicate_losers(items);
+        items
+            .iter()
+            .zip(losers)
+            .map(|((url, slug), lost)| {
+                if lost {
+                    return Err(ShortenerError::SlugAlreadyInUse);
+                }
+                self.handle_create_short_link(url.clone(), slug.clone())
+            })
+            .collect()
+    }
+
+    /// Creates a new [`ShortLink`] with all the provided attributes at once,
+    /// the same way as [`CommandHandler::handle_create_short_link()`] followed
+    /// by setting each of them does.
+    ///
+    /// Everything is validated before any [`Event`] is appended, so either
+    /// the whole [`ShortLink`] is created or nothing changes.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::InvalidRedirectStatus`] if the redirect status is
+    ///   not a redirect one.
+    /// - [`ShortenerError::TtlOutOfBounds`] if the time-to-live is outside
+    ///   the bounds (see [`UrlShortenerService::with_ttl_bounds()`]).
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_create_short_link()`] does.
+    pub fn handle_create_full(
+        &mut self,
+        req: CreateRequest,
+    ) -> Result<ShortLink, ShortenerError> {
+        if let Some(status) = req.redirect_status {
+            check_redirect_status(status)?;
+        }
+        self.check_ttl(req.ttl)?;
+        let (link, _) = self.create(req.url, req.slug)?;
+
+        let slug = &link.slug;
+        let at = self.clock.now();
+        if let Some(ttl) = req.ttl {
+            self.append(Event::ShortLinkExpirySet {
+                slug: slug.clone(),
+                expires_at: Some(at + ttl),
+                at,
+            });
+        }
+        if req.note.is_some() {
+            self.append(Event::ShortLinkNoteSet {
+                slug: slug.clone(),
+                note: req.note,
+                at,
+            });
+        }
+        let mut metadata: Vec<_> = req.metadata.into_iter().collect();
+        metadata.sort();
+        for (key, value) in metadata {
+            self.append(Event::ShortLinkMetadataSet {
+                slug: slug.clone(),
+                key,
+                value: Some(value),
+                at,
+            });
+        }
+        if let Some(status) = req.redirect_status {
+            self.append(Event::ShortLinkResponseSet {
+                slug: slug.clone(),
+                status,
+                content_type: None,
+                at,
+            });
+        }
+        Ok(link)
+    }
+
+    /// Creates a new [`ShortLink`] the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does, additionally
+    /// returning the heuristic [`UrlWarning`]s about its [`Url`], which don't
+    /// prevent the creation.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_checked(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<(ShortLink, Vec<UrlWarning>), ShortenerError> {
+        let link = self.handle_create_short_link(url, slug)?;
+        let warnings = link.url.warnings();
+        Ok((link, warnings))
+    }
+
+    /// Creates a new one-time [`ShortLink`] the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does.
+    ///
+    /// Its first redirect succeeds and is counted, while the subsequent ones
+    /// fail with a [`ShortenerError::SlugNotFound`]. Its [`Stats`] remain
+    /// available.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_one_time(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        let link = self.handle_create_short_link(url, slug)?;
+        self.append(Event::ShortLinkMadeOneTime {
+            slug: link.slug.clone(),
+            at: self.clock.now(),
+        });
+        Ok(link)
+    }
+
+    /// Creates a new template [`ShortLink`] the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does, pointing to the
+    /// provided `template` [`Url`] with placeholders expanded on every
+    /// redirect (e.g. `https://dest.com/?ref={slug}`):
+    /// - `{slug}` with the [`Slug`] of the [`ShortLink`];
+    /// - `{ts}` with the Unix timestamp of the redirect in seconds.
+    ///
+    /// Only the redirects return the expanded [`Url`], while the queries
+    /// report the `template` as is.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::InvalidTemplate`] if the `template` contains an
+    ///   unknown or unclosed placeholder.
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_create_short_link()`] does.
+    pub fn handle_create_template(
+        &mut self,
+        template: String,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        check_template(&template)?;
+        let link = self.handle_create_short_link(Url(template), slug)?;
+        self.append(Event::ShortLinkMadeTemplate {
+            slug: link.slug.clone(),
+            at: self.clock.now(),
+        });
+        Ok(link)
+    }
+
+    /// Creates a copy of the [`ShortLink`] with the provided `source`
+    /// [`Slug`] under the `new_slug`, with the same [`Url`], metadata,
+    /// expiry, status and other settings, but none of its redirects and
+    /// aliases.
+    ///
+    /// The copy expires as long after its creation as the source does after
+    /// its own, while it's disabled or not live yet (see
+    /// [`UrlShortenerService::handle_set_active_from()`]) if the source is.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the `new_slug` is in use or
+    ///   reserved already.
+    /// - [`ShortenerError::UrlAlreadyInUse`] if
+    ///   [`UrlShortenerService::with_unique_urls()`] is enabled, as the copy
+    ///   points to the same [`Url`] as the source.
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_create_short_link()`] does for a custom
+    ///   [`Slug`].
+    pub fn handle_clone_link(
+        &mut self,
+        source: Slug,
+        new_slug: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        let state = self.state(&source)?;
+        let new_slug = self.check_custom_slug(new_slug)?;
+        let url = self.url_of(state);
+        self.check_unique_url(&url, &[])?;
+
+        let at = self.clock.now();
+        let ttl = state.expires_at.map(|until| {
+            until.duration_since(state.created_at).unwrap_or_default()
+        });
+        let mut events = self.state_events(state, at);
+        events.retain(|event| {
+            !matches!(
+                event,
+                Event::RedirectsSeeded { .. } | Event::ShortLinkConsumed { .. }
+            )
+        });
+        events[0] = Event::ShortLinkCreated {
+            slug: new_slug.clone(),
+            url: url.clone(),
+            custom_slug: true,
+            id: self.last_link_id + 1,
+            at,
+        };
+        for mut event in events {
+            if let Event::ShortLinkExpirySet { expires_at, .. } = &mut event {
+                *expires_at = ttl.map(|ttl| at + ttl);
+            }
+            self.append(event.map_slugs(|_| new_slug.clone()));
+        }
+        Ok(ShortLink {
+            slug: new_slug,
+            url,
+        })
+    }
+
+    /// Creates a new [`ShortLink`] with a generated [`Slug`] the same way as
+    /// [`CommandHandler::handle_create_short_link()`] does, returning it along
+    /// with the count of attempts it took to generate a [`Slug`] not in use
+    /// yet.
+    ///
+    /// High counts indicate the keyspace is getting saturated, so the
+    /// [`UrlShortenerService::with_slug_length()`] should be increased.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_reported(
+        &mut self,
+        url: Url,
+    ) -> Result<(ShortLink, u32), ShortenerError> {
+        self.create(url, None)
+    }
+
+    /// Creates a new [`ShortLink`] with the provided [`Slug`], split between
+    /// the provided [`Url`]s: every redirect serves one of them, picked
+    /// randomly according to their relative weights.
+    ///
+    /// The first [`Url`] is the one reported as the [`ShortLink`]'s one.
+    /// Changing the [`ShortLink`] to a new [`Url`] removes the split.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::InvalidSplitWeights`] if no [`Url`]s are provided,
+    ///   or none of them has a positive weight.
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_create_short_link()`] does.
+    pub fn handle_create_split(
+        &mut self,
+        slug: Slug,
+        variants: Vec<(Url, u32)>,
+    ) -> Result<ShortLink, ShortenerError> {
+        if variants.iter().all(|(_, weight)| *weight == 0) {
+            return Err(ShortenerError::InvalidSplitWeights);
+        }
+        let variants = variants
+            .into_iter()
+            .map(|(url, weight)| Ok((self.prepare_url(url)?, weight)))
+            .collect::<Result<Vec<_>, ShortenerError>>()?;
+        for (url, _) in &variants[1..] {
+            self.check_unique_url(url, &[])?;
+        }
+
+        let link =
+            self.handle_create_short_link(variants[0].0.clone(), Some(slug))?;
+        self.append(Event::ShortLinkSplitSet {
+            slug: link.slug.clone(),
+            variants,
+            at: self.clock.now(),
+        });
+        Ok(link)
+    }
+
+    /// Moves the [`ShortLink`] with the provided [`Slug`] from the `from`
+    /// namespace to the `to` one, keeping all its attributes and statistics.
+    ///
+    /// Namespaces are represented as [`Slug`] prefixes separated with a `/`
+    /// (see [`namespaced_slug()`]), with an empty namespace being the root
+    /// one.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`] in
+    ///   the `from` namespace, including if the [`Slug`] is only an alias of
+    ///   one (see [`UrlShortenerService::handle_add_alias()`]).
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the [`Slug`] is already in
+    ///   use (or reserved, see [`UrlShortenerService::reserve_slug()`]) in the
+    ///   `to` namespace.
+    pub fn handle_move_namespace(
+        &mut self,
+        slug: &Slug,
+        from: &str,
+        to: &str,
+    ) -> Result<ShortLink, ShortenerError> {
+        let old = self.canonical_slug(namespaced_slug(from, slug));
+        if !self.links.contains_key(&old) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        let new_slug = self.canonical_slug(namespaced_slug(to, slug));
+        if self.state(&new_slug).is_ok()
+            || self.reserved_slugs.contains(&new_slug)
+        {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+
+        self.append(Event::ShortLinkRenamed {
+            slug: old,
+            new_slug: new_slug.clone(),
+            at: self.clock.now(),
+        });
+        self.state(&new_slug).map(|state| self.link_of(state))
+    }
+
+    /// Renames the [`ShortLink`] with the `old` [`Slug`] to the `new` one and
+    /// changes its [`Url`] at once, keeping all its other attributes and
+    /// statistics.
+    ///
+    /// Everything is validated before any [`Event`] is appended, so either
+    /// both changes are made or nothing changes.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the `new` [`Slug`] is taken
+    ///   (or reserved, see [`UrlShortenerService::reserve_slug()`]).
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_change_short_link()`] does.
+    pub fn handle_rebrand(
+        &mut self,
+        old: Slug,
+        new: Slug,
+        new_url: Url,
+    ) -> Result<ShortLink, ShortenerError> {
+        let state = self.state(&old)?;
+        if self
+            .config
+            .max_url_changes
+            .is_some_and(|max| state.url_changes >= max)
+        {
+            return Err(ShortenerError::ChangeLimitExceeded);
+        }
+        let old = state.link.slug.clone();
+        let new = self.canonical_slug(new);
+        if self.state(&new).is_ok() || self.reserved_slugs.contains(&new) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        let new_url = self.prepare_url(new_url)?;
+        self.check_unique_url(&new_url, slice::from_ref(&old))?;
+
+        let at = self.clock.now();
+        self.append(Event::ShortLinkRenamed {
+            slug: old,
+            new_slug: new.clone(),
+            at,
+        });
+        self.append(Event::ShortLinkChanged {
+            slug: new.clone(),
+            new_url: new_url.clone(),
+            at,
+        });
+        Ok(ShortLink {
+            slug: new,
+            url: new_url,
+        })
+    }
+
+    /// Performs the provided [`Operation`]s in order as a single unit: either
+    /// all of them succeed, or none of them is applied and no [`Event`] is
+    /// appended.
+    ///
+    /// The [`Operation`]s are staged on a copy of the current state replayed
+    /// from the event log, so all of them are performed at the same moment,
+    /// and the resulting [`Event`]s are appended only once all of them
+    /// succeed.
+    ///
+    /// Replaying the copy costs the same as replaying the whole event log, so
+    /// every transaction takes time linear in the length of the event log,
+    /// however few [`Operation`]s it performs. Compacting the event log (see
+    /// [`UrlShortenerService::compact()`]) keeps it bounded.
+    ///
+    /// ## Errors
+    ///
+    /// The [`ShortenerError`] of the first failed [`Operation`], the same way
+    /// as its command does.
+    pub fn handle_transaction(
+        &mut self,
+        ops: Vec<Operation>,
+    ) -> Result<(), ShortenerError> {
+        let mut staging = self.staging();
+        std::mem::swap(&mut self.rng, &mut staging.rng);
+        let result = ops.into_iter().try_for_each(|op| staging.perform(op));
+        std::mem::swap(&mut self.rng, &mut staging.rng);
+        result?;
+
+        for event in staging.events.split_off(self.events.len()) {
+            self.append(event);
+        }
+        Ok(())
+    }
+
+    /// Adds the provided `alias` to the [`ShortLink`] with the provided
+    /// [`Slug`], so redirecting through it behaves as redirecting through the
+    /// [`ShortLink`].
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::SlugAlreadyInUse`] if the `alias` is already in
+    ///   use (or reserved, see [`UrlShortenerService::reserve_slug()`]).
+    /// - [`ShortenerError::AliasLimitExceeded`] if the [`ShortLink`] has the
+    ///   maximum number of aliases already (see
+    ///   [`UrlShortenerService::with_max_aliases()`]).
+    pub fn handle_add_alias(
+        &mut self,
+        slug: Slug,
+        alias: Slug,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        if self.state(&alias).is_ok() || self.reserved_slugs.contains(&alias) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        let aliases = self.aliases.values().filter(|t| **t == slug).count();
+        if self.config.max_aliases.is_some_and(|max| aliases >= max) {
+            return Err(ShortenerError::AliasLimitExceeded);
+        }
+        let alias = self.canonical_slug(alias);
+        let at = self.clock.now();
+        self.append(Event::ShortLinkAliasAdded { slug, alias, at });
+        Ok(())
+    }
+
+    /// Removes the provided `alias` of a [`ShortLink`] (see
+    /// [`UrlShortenerService::handle_add_alias()`]).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such alias.
+    pub fn handle_remove_alias(
+        &mut self,
+        alias: Slug,
+    ) -> Result<(), ShortenerError> {
+        let alias = self.canonical_slug(alias);
+        let Some(slug) = self.aliases.get(&alias).cloned() else {
+            return Err(ShortenerError::SlugNotFound);
+        };
+        let at = self.clock.now();
+        self.append(Event::ShortLinkAliasRemoved { slug, alias, at });
+        Ok(())
+    }
+
+    /// Disables the [`ShortLink`] with the provided [`Slug`], so redirecting
+    /// through it fails with a [`ShortenerError::LinkDisabled`].
+    ///
+    /// Does nothing if the [`ShortLink`] is disabled already.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_disable(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        let state = self.state(&slug)?;
+        if state.enabled {
+            let slug = state.link.slug.clone();
+            let at = self.clock.now();
+            self.append(Event::ShortLinkDisabled { slug, at });
+        }
+        Ok(())
+    }
+
+    /// Disables all the active [`ShortLink`]s matching the provided
+    /// `predicate`, given each one along with its count of redirects, the same
+    /// way as [`UrlShortenerService::handle_disable()`] does, e.g. for all the
+    /// ones pointing to a compromised host during an incident.
+    ///
+    /// Returns the count of the [`ShortLink`]s disabled. Inactive matches
+    /// (like already disabled ones) are skipped.
+    pub fn handle_disable_where<F: Fn(&ShortLink, u64) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> usize {
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .filter(|state| predicate(&self.link_of(state), state.redirects))
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        let at = self.clock.now();
+        for slug in &slugs {
+            self.append(Event::ShortLinkDisabled {
+                slug: slug.clone(),
+                at,
+            });
+        }
+        slugs.len()
+    }
+
+    /// Enables back the [`ShortLink`] with the provided [`Slug`].
+    ///
+    /// Does nothing if the [`ShortLink`] is enabled already.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_enable(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        let state = self.state(&slug)?;
+        if !state.enabled {
+            let slug = state.link.slug.clone();
+            let at = self.clock.now();
+            self.append(Event::ShortLinkEnabled { slug, at });
+        }
+        Ok(())
+    }
+
+    /// Deletes the [`ShortLink`] with the provided [`Slug`] along with its
+    /// aliases, so its [`Slug`] can be used by a new [`ShortLink`].
+    ///
+    /// Its redirects stay in the event log, but don't count towards the new
+    /// [`ShortLink`] with the same [`Slug`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_delete(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkDeleted { slug, at });
+        Ok(())
+    }
+
+    /// Sets the time-to-live of the [`ShortLink`] with the provided [`Slug`],
+    /// counting from now, after which redirecting through it fails with a
+    /// [`ShortenerError::LinkExpired`].
+    ///
+    /// [`None`] makes the [`ShortLink`] never expire.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::TtlOutOfBounds`] if the `ttl` is outside the
+    ///   bounds (see [`UrlShortenerService::with_ttl_bounds()`]).
+    pub fn handle_set_ttl(
+        &mut self,
+        slug: Slug,
+        ttl: Option<Duration>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        self.check_ttl(ttl)?;
+        let at = self.clock.now();
+        self.append(Event::ShortLinkExpirySet {
+            slug,
+            expires_at: ttl.map(|ttl| at + ttl),
+            at,
+        });
+        Ok(())
+    }
+
+    /// Sets the free-form note of the [`ShortLink`] with the provided
+    /// [`Slug`].
+    ///
+    /// [`None`] removes the note.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_note(
+        &mut self,
+        slug: Slug,
+        note: Option<String>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkNoteSet { slug, note, at });
+        Ok(())
+    }
+
+    /// Sets the display title (e.g. for link previews) of the [`ShortLink`]
+    /// with the provided [`Slug`].
+    ///
+    /// An empty `title` removes the title.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_title(
+        &mut self,
+        slug: Slug,
+        title: String,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let title = (!title.is_empty()).then_some(title);
+        let at = self.clock.now();
+        self.append(Event::ShortLinkTitleSet { slug, title, at });
+        Ok(())
+    }
+
+    /// Schedules the [`ShortLink`] with the provided [`Slug`] to go live at
+    /// the provided moment, before which redirecting through it fails with a
+    /// [`ShortenerError::NotYetActive`].
+    ///
+    /// Along with its time-to-live (see
+    /// [`UrlShortenerService::handle_set_ttl()`]), the [`ShortLink`] can be
+    /// redirected through from the `active_from` moment (inclusive) until its
+    /// expiration moment (exclusive).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_active_from(
+        &mut self,
+        slug: Slug,
+        active_from: SystemTime,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkActivationScheduled {
+            slug,
+            active_from,
+            at,
+        });
+        Ok(())
+    }
+
+    /// Sets the metadata entry with the provided `key` of the [`ShortLink`]
+    /// with the provided [`Slug`].
+    ///
+    /// [`None`] removes the entry.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_metadata(
+        &mut self,
+        slug: Slug,
+        key: String,
+        value: Option<String>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkMetadataSet {
+            slug,
+            key,
+            value,
+            at,
+        });
+        Ok(())
+    }
+
+    /// Sets the metadata entry with the provided `key` and `value` (see
+    /// [`UrlShortenerService::handle_set_metadata()`]) of every active
+    /// [`ShortLink`] whose [`Url`] contains the provided `substring` (compared
+    /// case-insensitively), returning the count of the tagged [`ShortLink`]s.
+    pub fn handle_tag_by_url(
+        &mut self,
+        substring: &str,
+        key: String,
+        value: String,
+    ) -> usize {
+        let substring = substring.to_lowercase();
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .filter(|state| {
+                self.url_of(state).0.to_lowercase().contains(&substring)
+            })
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let at = self.clock.now();
+        for slug in &slugs {
+            self.append(Event::ShortLinkMetadataSet {
+                slug: slug.clone(),
+                key: key.clone(),
+                value: Some(value.clone()),
+                at,
+            });
+        }
+        slugs.len()
+    }
+
+    /// Changes the [`ShortLink`] with the provided [`Slug`] the same way as
+    /// [`CommandHandler::handle_change_short_link()`] does, but only if its
+    /// metadata entry with the provided `key` equals the `expected_value`.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::PreconditionFailed`] if the metadata entry is
+    ///   missing or has another value, in which case nothing is changed.
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_change_short_link()`] does.
+    pub fn handle_change_if_metadata(
+        &mut self,
+        slug: Slug,
+        new_url: Url,
+        key: &str,
+        expected_value: &str,
+    ) -> Result<ShortLink, ShortenerError> {
+        let state = self.state(&slug)?;
+        if state.metadata.get(key).map(String::as_str) != Some(expected_value) {
+            return Err(ShortenerError::PreconditionFailed);
+        }
+        self.handle_change_short_link(slug, new_url)
+    }
+
+    /// Sets the HTTP `status` (`302` by default) and the `content_type` to
+    /// respond to redirects through the [`ShortLink`] with the provided
+    /// [`Slug`] with (see [`UrlShortenerService::handle_redirect_enriched()`]).
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::InvalidRedirectStatus`] if the `status` is not one
+    ///   of `301`, `302`, `303`, `307` or `308`.
+    pub fn handle_set_response(
+        &mut self,
+        slug: Slug,
+        status: u16,
+        content_type: Option<String>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        check_redirect_status(status)?;
+        let at = self.clock.now();
+        self.append(Event::ShortLinkResponseSet {
+            slug,
+            status,
+            content_type,
+            at,
+        });
+        Ok(())
+    }
+
+    /// Overrides the fraction of the redirects through the [`ShortLink`] with
+    /// the provided [`Slug`] to record individually into the event log (see
+    /// [`UrlShortenerService::with_redirect_sampling()`]) with the provided
+    /// `rate`, e.g. for a high-traffic one, or resets it to the default one of
+    /// the service with [`None`].
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::InvalidSampleRate`] if the `rate` is not in
+    ///   `0.0..=1.0` range, or is zero.
+    pub fn handle_set_redirect_sampling(
+        &mut self,
+        slug: Slug,
+        rate: Option<f64>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        if !rate.is_none_or(is_sample_rate) {
+            return Err(ShortenerError::InvalidSampleRate);
+        }
+        let at = self.clock.now();
+        self.append(Event::ShortLinkSamplingSet { slug, rate, at });
+        Ok(())
+    }
+
+    /// Assigns the [`ShortLink`] with the provided [`Slug`] to the provided
+    /// campaign, moving it out of the campaign it was assigned to before, if
+    /// any.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_assign_campaign(
+        &mut self,
+        slug: Slug,
+        campaign: String,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkCampaignAssigned { slug, campaign, at });
+        Ok(())
+    }
+
+    /// Adds the provided `count` of redirects to the [`ShortLink`] with the
+    /// provided [`Slug`] (e.g. migrated from another system), without
+    /// recording them individually.
+    ///
+    /// Redirect counters never wrap: any further redirects saturate them at
+    /// [`u64::MAX`].
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::CounterOverflow`] if the `count` would overflow the
+    ///   redirect counter, in which case nothing is seeded.
+    pub fn handle_seed_redirects(
+        &mut self,
+        slug: Slug,
+        count: u64,
+    ) -> Result<(), ShortenerError> {
+        let state = self.state(&slug)?;
+        if state.redirects.checked_add(count).is_none() {
+            return Err(ShortenerError::CounterOverflow);
+        }
+        let slug = state.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::RedirectsSeeded {
+            slug,
+            count,
+            organic: false,
+            at,
+        });
+        Ok(())
+    }
+
+    /// Reconciles the counts of redirects of the [`ShortLink`]s with the
+    /// provided `external` ones (e.g. counted by an edge layer), raising every
+    /// count to the maximum of the current and the external one.
+    ///
+    /// Taking the maximum rather than adding the external counts keeps
+    /// reconciling the same counts repeatedly from double counting redirects.
+    /// The differences are seeded (see
+    /// [`UrlShortenerService::handle_seed_redirects()`]), while lower external
+    /// counts are ignored.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no [`ShortLink`] with any
+    /// of the provided [`Slug`]s, in which case nothing is reconciled.
+    pub fn reconcile_counts(
+        &mut self,
+        external: HashMap<Slug, u64>,
+    ) -> Result<(), ShortenerError> {
+        let mut counts = Vec::with_capacity(external.len());
+        for (slug, count) in external {
+            counts.push((self.state(&slug)?.link.slug.clone(), count));
+        }
+        counts.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        let at = self.clock.now();
+        for (slug, count) in counts {
+            let current = self.links[&slug].redirects;
+            if count > current {
+                self.append(Event::RedirectsSeeded {
+                    slug,
+                    count: count - current,
+                    organic: false,
+                    at,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the provided [`AnalyticsSnapshot`] (see
+    /// [`UrlShortenerService::analytics_snapshot()`]) onto the existing
+    /// [`ShortLink`]s, adding its redirects to their current ones, while
+    /// keeping their [`Url`]s and other attributes untouched.
+    ///
+    /// The individually recorded redirects are recorded again at their
+    /// original moments, while the rest of them are seeded.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no [`ShortLink`] with any
+    /// of the [`Slug`]s in the [`AnalyticsSnapshot`], in which case nothing is
+    /// restored.
+    pub fn restore_analytics(
+        &mut self,
+        snapshot: AnalyticsSnapshot,
+    ) -> Result<(), ShortenerError> {
+        let mut links = Vec::with_capacity(snapshot.links.len());
+        for (slug, traffic) in snapshot.links {
+            links.push((self.state(&slug)?.link.slug.clone(), traffic));
+        }
+        links.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        let at = self.clock.now();
+        for (slug, traffic) in links {
+            let mut organic = traffic.redirects.saturating_sub(traffic.seeded);
+            for (click, count) in traffic.clicks {
+                organic = organic.saturating_sub(count);
+                self.append(Event::ShortLinkRedirected {
+                    slug: slug.clone(),
+                    source: click.source,
+                    referrer: click.referrer,
+                    variant: None,
+                    count,
+                    at: click.at,
+                });
+            }
+            for (count, organic) in [(organic, true), (traffic.seeded, false)] {
+                if count > 0 {
+                    self.append(Event::RedirectsSeeded {
+                        slug: slug.clone(),
+                        count,
+                        organic,
+                        at,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the codes of the countries the [`ShortLink`] with the provided
+    /// [`Slug`] is allowed in (see
+    /// [`UrlShortenerService::handle_redirect_geo()`]).
+    ///
+    /// An empty list allows it everywhere.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_allowed_countries(
+        &mut self,
+        slug: Slug,
+        countries: Vec<String>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkCountriesSet {
+            slug,
+            countries,
+            at,
+        });
+        Ok(())
+    }
+
+    /// Processes a redirection by [`Slug`] the same way as
+    /// [`CommandHandler::handle_redirect()`] does, returning everything needed
+    /// to respond to it (see [`UrlShortenerService::handle_set_response()`]
+    /// and [`UrlShortenerService::handle_set_metadata()`]).
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_redirect_enriched(
+        &mut self,
+        slug: Slug,
+    ) -> Result<EnrichedRedirect, ShortenerError> {
+        let link = self.redirect(slug, None, None)?;
+        let state = self.state(&link.slug)?;
+        Ok(EnrichedRedirect {
+            url: link.url,
+            status: state.redirect_status,
+            content_type: state.content_type.clone(),
+            metadata: state.metadata.clone().into_iter().collect(),
+        })
+    }
+
+    /// Processes a redirection by [`Slug`] the same way as
+    /// [`CommandHandler::handle_redirect()`] does, made by a visitor from the
+    /// country with the provided code.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::CountryBlocked`] if the [`ShortLink`] is not
+    ///   allowed in the visitor's country (see
+    ///   [`UrlShortenerService::handle_set_allowed_countries()`]).
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_redirect()`] does.
+    pub fn handle_redirect_geo(
+        &mut self,
+        slug: Slug,
+        country: &str,
+    ) -> Result<ShortLink, ShortenerError> {
+        if !self.state(&slug)?.is_allowed_in(country) {
+            return Err(ShortenerError::CountryBlocked);
+        }
+        self.redirect(slug, None, None)
+    }
+
+    /// Sets the hosts of the referers the [`ShortLink`] with the provided
+    /// [`Slug`] is allowed to be redirected through from (see
+    /// [`UrlShortenerService::handle_redirect_with_referrer()`]).
+    ///
+    /// An empty list allows it from anywhere.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn handle_set_allowed_referers(
+        &mut self,
+        slug: Slug,
+        referers: Vec<String>,
+    ) -> Result<(), ShortenerError> {
+        let slug = self.state(&slug)?.link.slug.clone();
+        let at = self.clock.now();
+        self.append(Event::ShortLinkReferersSet { slug, referers, at });
+        Ok(())
+    }
+
+    /// Processes a redirection by [`Slug`] the same way as
+    /// [`CommandHandler::handle_redirect()`] does, made from a page with the
+    /// provided `referer` [`Url`], if any.
+    ///
+    /// Redirects without a referer (like direct visits) are always allowed.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::RefererNotAllowed`] if the [`ShortLink`] is not
+    ///   allowed from the `referer` host (see
+    ///   [`UrlShortenerService::handle_set_allowed_referers()`]).
+    /// - Any other [`ShortenerError`] the same way as
+    ///   [`CommandHandler::handle_redirect()`] does.
+    pub fn handle_redirect_with_referrer(
+        &mut self,
+        slug: Slug,
+        referer: Option<&Url>,
+    ) -> Result<ShortLink, ShortenerError> {
+        let state = self.state(&slug)?;
+        if referer.is_some_and(|r| !state.is_allowed_from(r)) {
+            return Err(ShortenerError::RefererNotAllowed);
+        }
+        self.redirect(slug, None, referer.map(|r| r.0.clone()))
+    }
+
+    /// Processes a redirection by [`Slug`] the same way as
+    /// [`CommandHandler::handle_redirect()`] does, additionally tagging it with
+    /// the provided `source` (e.g. a campaign or a referring channel).
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_redirect_with_source(
+        &mut self,
+        slug: Slug,
+        source: String,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.redirect(slug, Some(source), None)
+    }
+
+    /// Merges the redirects buffered since the last flush (see
+    /// [`UrlShortenerService::with_buffered_redirects()`]) into the counters,
+    /// returning how many were merged.
+    ///
+    /// Should be called periodically, as it bounds how stale the counters
+    /// are. Redirects of [`ShortLink`]s renamed in the meantime are dropped.
+    pub fn flush_pending_redirects(&mut self) -> u64 {
+        let Some(pending) = self.pending_redirects.as_mut() else {
+            return 0;
+        };
+        let mut merged = 0;
+        for redirect in std::mem::take(pending) {
+            if !self.links.contains_key(&redirect.slug) {
+                continue;
+            }
+            self.record_redirect(
+                redirect.slug,
+                redirect.source,
+                redirect.referrer,
+                redirect.variant,
+                redirect.at,
+            );
+            merged += 1;
+        }
+        merged
+    }
+
+    /// Records the redirects not logged individually (see
+    /// [`UrlShortenerService::with_log_redirects()`]) into the event log as
+    /// [`Event::RedirectsSeeded`], one per [`ShortLink`].
+    ///
+    /// Should be called periodically for the counters to survive a replay.
+    pub fn checkpoint_redirects(&mut self) {
+        let at = self.clock.now();
+        let mut seeded: Vec<_> = self
+            .links
+            .iter_mut()
+            .filter(|(_, state)| state.unlogged_redirects > 0)
+            .map(|(slug, state)| {
+                let count = std::mem::take(&mut state.unlogged_redirects);
+                (slug.clone(), count)
+            })
+            .collect();
+        seeded.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        for (slug, count) in seeded {
+            self.record(Event::RedirectsSeeded {
+                slug,
+                count,
+                organic: true,
+                at,
+            });
+        }
+    }
+
+    /// Replaces the event log with the minimal one reproducing the current
+    /// state (see [`UrlShortenerService::minimal_event_log()`]).
+    ///
+    /// The detailed history (individual redirects along with their source
+    /// tags and moments, previous [`Url`]s) is discarded, and sequence numbers
+    /// start over, so replicas must be resynced from scratch.
+    pub fn compact(&mut self) {
+        self.appended_since_compaction = 0;
+        self.compacted = true;
+        for state in self.links.values_mut() {
+            state.unlogged_redirects = 0;
+        }
+        self.events = self.minimal_event_log();
+    }
+
+    /// Returns the whole current event log (e.g. for archiving it externally),
+    /// replacing it with the minimal one reproducing the current state, as
+    /// [`UrlShortenerService::compact()`] does.
+    ///
+    /// Nothing is lost: replaying either the returned event log or the new one
+    /// reproduces the same [`Stats`].
+    pub fn rotate_log(&mut self) -> Vec<Event> {
+        let log = std::mem::take(&mut self.events);
+        self.compact();
+        log
+    }
+
+    /// Removes the [`Event`]s made before the provided `cutoff` from the event
+    /// log and returns them in order (e.g. for archiving them externally).
+    ///
+    /// The removed [`Event`]s include the ones appended after newer ones,
+    /// like the imported redirects (see
+    /// [`UrlShortenerService::handle_import_access_log()`]), unless they
+    /// depend on a newer [`Event`] (e.g. a redirect through a [`ShortLink`]
+    /// created after the `cutoff`), staying in the event log then.
+    ///
+    /// The removed [`Event`]s are replaced with the minimal event log
+    /// reproducing the state they lead to (see
+    /// [`UrlShortenerService::minimal_event_log()`]), made at the `cutoff`, so
+    /// replaying the event log still reproduces the current state. As in
+    /// [`UrlShortenerService::compact()`], sequence numbers change, so replicas
+    /// must be resynced from scratch.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::LogNotSplittable`] if replaying the split event log
+    /// wouldn't reproduce the current state, in which case nothing is
+    /// changed.
+    pub fn split_log_at(
+        &mut self,
+        cutoff: SystemTime,
+    ) -> Result<Vec<Event>, ShortenerError> {
+        let mut archived = Self::new();
+        let (mut old, mut live) = (Vec::new(), Vec::new());
+        for event in &self.events {
+            if event.at() < cutoff && archived.validate(event).is_ok() {
+                archived.append(event.clone());
+                old.push(event.clone());
+            } else {
+                live.push(event.clone());
+            }
+        }
+        if old.is_empty() {
+            return Ok(old);
+        }
+
+        let mut split = archived
+            .with_clock(clock::MockClock::new(cutoff))
+            .minimal_event_log();
+        split.extend(live);
+        let events = std::mem::replace(&mut self.events, split);
+        if !self.is_consistent() {
+            self.events = events;
+            return Err(ShortenerError::LogNotSplittable);
+        }
+        self.compacted = true;
+        Ok(old)
+    }
+
+    /// Folds every group of [`ShortLink`]s pointing to the same [`Url`]
+    /// (compared in the normalized form if
+    /// [`UrlShortenerService::with_normalize_urls()`] is enabled) into the one
+    /// with the lexicographically smallest [`Slug`], returning the [`Slug`]s
+    /// folded into every such canonical one, all ordered by [`Slug`].
+    ///
+    /// Redirect counts of the folded [`ShortLink`]s (along with their counts
+    /// of individual redirects, redirects while disabled and redirects per
+    /// source tag or per split variant with the same [`Url`]) are added to
+    /// the canonical one, while their [`Slug`]s become its aliases:
+    /// redirecting through them behaves as redirecting through the canonical
+    /// one. Other attributes of the folded [`ShortLink`]s are discarded.
+    pub fn consolidate_duplicates(&mut self) -> Vec<(Slug, Vec<Slug>)> {
+        let mut slugs: Vec<_> = self.links.keys().cloned().collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut groups: BTreeMap<String, Vec<Slug>> = BTreeMap::new();
+        for slug in slugs {
+            let url = self.url_of(&self.links[&slug]);
+            let url = if self.config.normalize_urls {
+                url.normalized()
+            } else {
+                url
+            };
+            groups.entry(url.0).or_default().push(slug);
+        }
+
+        let at = self.clock.now();
+        let mut consolidated = Vec::new();
+        for mut group in groups.into_values().filter(|g| g.len() > 1) {
+            let canonical = group.remove(0);
+            for slug in &group {
+                self.append(Event::ShortLinkMerged {
+                    slug: slug.clone(),
+                    into: canonical.clone(),
+                    at,
+                });
+            }
+            consolidated.push((canonical, group));
+        }
+        consolidated.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        consolidated
+    }
+
+    /// Changes every [`ShortLink`] pointing to the `old` [`Url`] (compared in
+    /// the normalized form if [`UrlShortenerService::with_normalize_urls()`]
+    /// is enabled) to point to the `new` one, returning the count of the
+    /// changed [`ShortLink`]s.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::InvalidUrl`] if the `new` [`Url`] is not allowed.
+    /// - [`ShortenerError::UrlAlreadyInUse`] if the `new` [`Url`] is pointed
+    ///   to by another [`ShortLink`] already (see
+    ///   [`UrlShortenerService::with_unique_urls()`]).
+    /// - [`ShortenerError::ChangeLimitExceeded`] if any of the
+    ///   [`ShortLink`]s has been changed the maximum number of times already
+    ///   (see [`UrlShortenerService::with_max_url_changes()`]).
+    ///
+    /// Nothing is changed on any error.
+    pub fn handle_retarget_url(
+        &mut self,
+        old: &Url,
+        new: Url,
+    ) -> Result<usize, ShortenerError> {
+        let new = self.prepare_url(new)?;
+
+        let states: Vec<_> = self
+            .links
+            .values()
+            .filter(|state| self.same_url(&self.url_of(state), old))
+            .collect();
+        if let Some(max) = self.config.max_url_changes {
+            if states.iter().any(|state| state.url_changes >= max) {
+                return Err(ShortenerError::ChangeLimitExceeded);
+            }
+        }
+        let mut slugs: Vec<_> =
+            states.iter().map(|state| state.link.slug.clone()).collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        self.check_unique_url(&new, &slugs)?;
+
+        let at = self.clock.now();
+        for slug in &slugs {
+            self.append(Event::ShortLinkChanged {
+                slug: slug.clone(),
+                new_url: new.clone(),
+                at,
+            });
+        }
+        Ok(slugs.len())
+    }
+
+    /// Imports redirects from the provided access log `lines` (like the ones of
+    /// Apache or Nginx), taking the [`Slug`] from the whitespace-delimited
+    /// field with the `slug_field` index.
+    ///
+    /// Leading `/` and a query string are stripped from the field. Redirects
+    /// are timestamped with the `[dd/Mon/yyyy:HH:MM:SS +hhmm]` timestamp of the
+    /// line, if it has a parseable one, or the current moment otherwise.
+    /// Being historical, the redirects are imported regardless of the
+    /// [`ShortLink`]s being disabled or expired. Blank lines are skipped.
+    pub fn handle_import_access_log(
+        &mut self,
+        lines: &str,
+        slug_field: usize,
+    ) -> ImportReport {
+        let mut report = ImportReport::default();
+        for line in lines.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let Some(field) = fields.get(slug_field) else {
+                report.parse_errors += 1;
+                continue;
+            };
+            let path = field.trim_start_matches('/');
+            let slug =
+                Slug(path.split(['?', '#']).next().unwrap_or(path).into());
+            let Ok(state) = self.state(&slug) else {
+                report.unknown_slugs += 1;
+                continue;
+            };
+            let slug = state.link.slug.clone();
+
+            let at = parse_access_log_time(&fields)
+                .unwrap_or_else(|| self.clock.now());
+            self.record_redirect(slug, None, None, None, at);
+            report.imported += 1;
+        }
+        report
+    }
+
+    /// Applies the provided [`SequencedEvent`]s (as returned by
+    /// [`UrlShortenerService::changes_since()`] of a primary) in order,
+    /// appending them to the event log.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SequenceGap`] if the [`SequencedEvent`]s don't
+    /// continue the current event log without gaps, in which case nothing is
+    /// applied.
+    ///
+    /// Any other [`ShortenerError`] if an [`Event`] cannot be applied to the
+    /// current state, in which case all the preceding ones remain applied.
+    pub fn apply_changes(
+        &mut self,
+        events: &[SequencedEvent],
+    ) -> Result<(), ShortenerError> {
+        if events
+            .iter()
+            .zip(self.seq() + 1..)
+            .any(|(e, seq)| e.seq != seq)
+        {
+            return Err(ShortenerError::SequenceGap);
+        }
+
+        for SequencedEvent { event, .. } in events {
+            self.validate(event)?;
+            self.append(event.clone());
+        }
+        Ok(())
+    }
+
+    /// Applies the provided [`Event`]s (e.g. received from an external event
+    /// stream) in order on top of the current state, appending them to the
+    /// event log.
+    ///
+    /// Unlike [`UrlShortenerService::apply_changes()`], no sequence numbers
+    /// are checked.
+    ///
+    /// ## Errors
+    ///
+    /// Any [`ShortenerError`] if an [`Event`] cannot be applied to the current
+    /// state, in which case all the preceding ones remain applied, while the
+    /// following ones are not.
+    pub fn apply_events(
+        &mut self,
+        events: &[Event],
+    ) -> Result<(), ShortenerError> {
+        for event in events {
+            self.validate(event)?;
+            self.append(event.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Additional queries beyond the [`QueryHandler`].
+impl UrlShortenerService {
+    /// Returns the [`Url`] the [`ShortLink`] with the provided [`Slug`]
+    /// points to, without counting it as a redirect.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn resolve(&self, slug: &Slug) -> Result<Url, ShortenerError> {
+        self.audit_read("resolve", Some(slug));
+        let slug = &self.canonical_slug(slug.clone());
+        let cache = self.resolve_cache.as_ref();
+        if let Some(url) = cache.and_then(|c| c.borrow_mut().get(slug)) {
+            return Ok(self.deobfuscate(&url));
+        }
+
+        let state = self.state(slug)?;
+        if let Some(cache) = cache {
+            cache
+                .borrow_mut()
+                .insert(slug.clone(), state.link.url.clone());
+        }
+        Ok(self.url_of(state))
+    }
+
+    /// Checks whether the provided URL would be accepted by the current
+    /// configuration of this service, without creating anything, returning
+    /// the [`Url`] that would be stored (completed with the
+    /// [`UrlShortenerService::with_default_scheme()`] and normalized, if
+    /// enabled).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::InvalidUrl`] if the URL would be rejected.
+    pub fn validate_url(&self, url: &str) -> Result<Url, ShortenerError> {
+        self.prepare_url(Url(url.to_owned()))
+    }
+
+    /// Indicates whether the [`ShortLink`]s with the provided [`Slug`]s point
+    /// to the same [`Url`], compared in the normalized form if
+    /// [`UrlShortenerService::with_normalize_urls()`] is enabled.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if any of the [`ShortLink`]s is
+    /// missing.
+    pub fn same_target(
+        &self,
+        a: &Slug,
+        b: &Slug,
+    ) -> Result<bool, ShortenerError> {
+        self.audit_read("same_target", Some(a));
+        self.audit_read("same_target", Some(b));
+        let (a, b) = (self.state(a)?, self.state(b)?);
+        Ok(self.same_url(&self.url_of(a), &self.url_of(b)))
+    }
+
+    /// Returns the redirect counts of every split variant of the [`ShortLink`]
+    /// with the provided [`Slug`] (see
+    /// [`UrlShortenerService::handle_create_split()`]), in the order the
+    /// variants were provided.
+    ///
+    /// A [`ShortLink`] not being split is reported as a single variant.
+    /// Per-variant counts are not preserved by
+    /// [`UrlShortenerService::compact()`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn variant_stats(
+        &self,
+        slug: Slug,
+    ) -> Result<Vec<(Url, u64)>, ShortenerError> {
+        self.audit_read("variant_stats", Some(&slug));
+        let state = self.state(&slug)?;
+        if state.variants.is_empty() {
+            return Ok(vec![(self.url_of(state), state.redirects)]);
+        }
+        Ok(state
+            .variants
+            .iter()
+            .map(|v| (self.deobfuscate(&v.url), v.redirects))
+            .collect())
+    }
+
+    /// Returns the fraction of the generated [`Slug`]s keyspace still free,
+    /// given its alphabet size, the [`UrlShortenerService::with_slug_length()`]
+    /// and the count of [`Slug`]s in use.
+    ///
+    /// Every [`Slug`] taken counts, including the aliases and the ones of
+    /// disabled or expired [`ShortLink`]s, as the generation skips them all.
+    ///
+    /// It's `1.0` for an empty service, approaching `0.0` as the keyspace
+    /// saturates.
+    pub fn keyspace_headroom(&self) -> f64 {
+        let keyspace = (self.slug_alphabet().len() as f64)
+            .powi(self.config.slug_length as i32);
+        let used = (self.links.len() + self.aliases.len()) as f64;
+        (1.0 - used / keyspace).max(0.0)
+    }
+
+    /// Returns the smallest generated [`Slug`]s length, not shorter than the
+    /// [`UrlShortenerService::with_slug_length()`], at which a next generated
+    /// [`Slug`] collides with one in use with a probability under the provided
+    /// `max_collision_prob`, given the alphabet size and the count of
+    /// [`Slug`]s in use.
+    ///
+    /// An empty service (or a single-character alphabet, which no length
+    /// helps) always gets the configured length.
+    pub fn recommended_slug_length(&self, max_collision_prob: f64) -> usize {
+        let base = self.slug_alphabet().len() as f64;
+        let used = (self.links.len() + self.aliases.len()) as f64;
+        let mut length = self.config.slug_length;
+        if used == 0.0 || base < 2.0 {
+            return length;
+        }
+        loop {
+            let keyspace = base.powi(length as i32);
+            if used / keyspace < max_collision_prob || keyspace.is_infinite() {
+                return length;
+            }
+            length += 1;
+        }
+    }
+
+    /// Returns all the [`ShortLink`]s in the exact order they were created in,
+    /// as recorded in the event log, under their current [`Slug`]s.
+    ///
+    /// [`ShortLink`]s folded into others (see
+    /// [`UrlShortenerService::consolidate_duplicates()`]) or deleted (see
+    /// [`UrlShortenerService::handle_delete()`]) are skipped.
+    pub fn links_in_creation_order(&self) -> Vec<ShortLink> {
+        let mut order = Vec::new();
+        let mut positions = HashMap::new();
+        for event in &self.events {
+            match event {
+                Event::ShortLinkCreated { slug, .. } => {
+                    _ = positions.insert(slug.clone(), order.len());
+                    order.push(Some(slug.clone()));
+                }
+                Event::ShortLinkRenamed { slug, new_slug, .. } => {
+                    if let Some(pos) = positions.remove(slug) {
+                        order[pos] = Some(new_slug.clone());
+                        _ = positions.insert(new_slug.clone(), pos);
+                    }
+                }
+                Event::ShortLinkMerged { slug, .. }
+                | Event::ShortLinkDeleted { slug, .. } => {
+                    if let Some(pos) = positions.remove(slug) {
+                        order[pos] = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+        order
+            .iter()
+            .flatten()
+            .filter_map(|slug| self.links.get(slug))
+            .map(|state| self.link_of(state))
+            .collect()
+    }
+
+    /// Checks whether every one of the provided items could be created with
+    /// [`CommandHandler::handle_create_short_link()`] one after another,
+    /// without creating anything, returning the results in the same order.
+    ///
+    /// A [`Slug`] repeated within the batch is resolved the same way as
+    /// [`UrlShortenerService::handle_create_many()`] does (see
+    /// [`UrlShortenerService::with_batch_duplicate_policy()`]), while every
+    /// item is checked against the preceding valid ones too, so a [`Url`]
+    /// repeated within the batch (if
+    /// [`UrlShortenerService::with_unique_urls()`] is enabled) is reported as
+    /// being in use.
+    pub fn validate_batch(
+        &self,
+        items: &[(Url, Option<Slug>)],
+    ) -> Vec<Result<(), ShortenerError>> {
+        let losers = self.batch_duplicate_losers(items);
+        let mut urls: Vec<Url> = Vec::new();
+        items
+            .iter()
+            .zip(losers)
+            .map(|((url, slug), lost)| {
+                if lost {
+                    return Err(ShortenerError::SlugAlreadyInUse);
+                }
+                let url = self.prepare_url(url.clone())?;
+                if let Some(slug) = slug {
+                    _ = self.check_custom_slug(slug.clone())?;
+                }
+                self.check_unique_url(&url, &[])?;
+                if self.config.unique_urls
+                    && urls.iter().any(|u| self.same_unique_url(u, &url))
+                {
+                    return Err(ShortenerError::UrlAlreadyInUse);
+                }
+
+                urls.push(url);
+                Ok(())
+            })
+            .collect()
+    }
+
+    /// Takes a [`Snapshot`] of the current [`Url`]s, redirect counts and
+    /// metadata of all the [`ShortLink`]s and of the event log, to compare
+    /// against later.
+    ///
+    /// It doesn't checkpoint the redirects not logged individually (see
+    /// [`UrlShortenerService::with_log_redirects()`]).
+    pub fn snapshot(&self) -> Snapshot {
+        self.audit_read("snapshot", None);
+        let analytics = self.config.full_analytics_snapshot.then(|| {
+            self.links
+                .values()
+                .map(|state| {
+                    (state.link.slug.clone(), self.analytics_of(state))
+                })
+                .collect()
+        });
+        Snapshot {
+            links: self
+                .links
+                .iter()
+                .map(|(slug, state)| {
+                    (slug.clone(), (self.url_of(state), state.redirects))
+                })
+                .collect(),
+            analytics,
+            ids: self
+                .links
+                .iter()
+                .map(|(slug, state)| (slug.clone(), state.id))
+                .collect(),
+            origins: self
+                .links
+                .iter()
+                .map(|(slug, state)| (slug.clone(), state.origin()))
+                .collect(),
+            metadata: self
+                .links
+                .iter()
+                .filter(|(_, state)| !state.metadata.is_empty())
+                .map(|(slug, state)| (slug.clone(), state.metadata.clone()))
+                .collect(),
+            seq: self.seq(),
+            log_fingerprint: self.log_fingerprint(),
+        }
+    }
+
+    /// Indicates whether the current [`Url`]s, redirect counts and metadata
+    /// (and the full analytics, if included, see
+    /// [`UrlShortenerService::with_full_analytics_snapshot()`]) of all the
+    /// [`ShortLink`]s, as well as the event log, exactly match the provided
+    /// [`Snapshot`].
+    pub fn matches_snapshot(&self, snapshot: &Snapshot) -> bool {
+        self.describe_snapshot_diff(snapshot).is_none()
+    }
+
+    /// Describes how the current state differs from the provided
+    /// [`Snapshot`] (see [`UrlShortenerService::matches_snapshot()`]), one
+    /// line per difference ordered by [`Slug`] followed by the one of the
+    /// event log, or returns [`None`] if it matches.
+    pub fn describe_snapshot_diff(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Option<String> {
+        let mut slugs: Vec<_> =
+            self.links.keys().chain(snapshot.links.keys()).collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs.dedup();
+
+        let mut diff = Vec::new();
+        for slug in slugs {
+            let (state, expected) =
+                match (self.links.get(slug), snapshot.links.get(slug)) {
+                    (Some(state), Some(expected)) => (state, expected),
+                    (Some(_), None) => {
+                        diff.push(format!("{}: unexpected link", slug.0));
+                        continue;
+                    }
+                    (None, _) => {
+                        diff.push(format!("{}: missing link", slug.0));
+                        continue;
+                    }
+                };
+            let (url, redirects) = expected;
+            let actual = self.url_of(state);
+            if actual != *url {
+                diff.push(format!(
+                    "{}: URL is {}, expected {}",
+                    slug.0, actual.0, url.0,
+                ));
+            }
+            if state.redirects != *redirects {
+                diff.push(format!(
+                    "{}: {} redirect(s), expected {redirects}",
+                    slug.0, state.redirects,
+                ));
+            }
+            let analytics =
+                snapshot.analytics.as_ref().and_then(|a| a.get(slug));
+            if analytics.is_some_and(|a| *a != self.analytics_of(state)) {
+                diff.push(format!("{}: analytics differ", slug.0));
+            }
+            let metadata = snapshot.metadata.get(slug);
+            if state.metadata != metadata.cloned().unwrap_or_default() {
+                diff.push(format!("{}: metadata differ", slug.0));
+            }
+        }
+        if self.seq() != snapshot.seq {
+            diff.push(format!(
+                "event log has {} event(s), expected {}",
+                self.seq(),
+                snapshot.seq,
+            ));
+        } else if self.log_fingerprint() != snapshot.log_fingerprint {
+            diff.push("event log differs".into());
+        }
+        (!diff.is_empty()).then(|| diff.join("\n"))
+    }
+
+    /// Takes an [`AnalyticsSnapshot`] of the redirects of all the
+    /// [`ShortLink`]s (their counts, along with the moments, referers and
+    /// source tags of the ones recorded individually into the event log), to
+    /// be processed separately from their [`Url`]s and restored later with
+    /// [`UrlShortenerService::restore_analytics()`].
+    pub fn analytics_snapshot(&self) -> AnalyticsSnapshot {
+        self.audit_read("analytics_snapshot", None);
+        let links = self
+            .links
+            .values()
+            .map(|state| {
+                let clicks = self
+                    .link_events(&state.link.slug)
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        Event::ShortLinkRedirected {
+                            source,
+                            referrer,
+                            count,
+                            at,
+                            ..
+                        } => {
+                            let click = ClickRecord {
+                                at: *at,
+                                referrer: referrer.clone(),
+                                source: source.clone(),
+                            };
+                            Some((click, *count))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let traffic = LinkTraffic {
+                    redirects: state.redirects,
+                    seeded: state.seeded,
+                    clicks,
+                };
+                (state.link.slug.clone(), traffic)
+            })
+            .collect();
+        AnalyticsSnapshot { links }
+    }
+
+    /// Returns the [`Slug`]s of the active [`ShortLink`]s redirected through
+    /// since the `previous` [`Snapshot`], while still pointing to the same
+    /// [`Url`], ordered by [`Slug`].
+    ///
+    /// [`ShortLink`]s created or renamed since are skipped.
+    pub fn active_stable_since(&self, previous: &Snapshot) -> Vec<Slug> {
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .filter(|state| {
+                let previous = previous.links.get(&state.link.slug);
+                previous.is_some_and(|(url, redirects)| {
+                    state.redirects > *redirects && self.url_of(state) == *url
+                })
+            })
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the [`Url`]s pointed to by any [`ShortLink`] in the `previous`
+    /// [`Snapshot`], but not by any active one now (e.g. for cleaning up the
+    /// external caches keyed by [`Url`]), ordered.
+    ///
+    /// A [`ShortLink`] is active if it's enabled, not consumed, gone live and
+    /// not expired.
+    pub fn orphaned_urls(&self, previous: &Snapshot) -> Vec<Url> {
+        let live: Vec<_> = self
+            .active_links()
+            .map(|state| self.url_of(state))
+            .collect();
+        let mut urls: Vec<_> = previous
+            .links
+            .values()
+            .map(|(url, _)| url)
+            .filter(|url| !live.contains(url))
+            .cloned()
+            .collect();
+        urls.sort_by(|a, b| a.0.cmp(&b.0));
+        urls.dedup();
+        urls
+    }
+
+    /// Returns an estimate of the size of the event log in bytes, as if every
+    /// [`Event`] were persisted on its own line in its [`Debug`] form (like
+    /// the [`EventSink`] example does).
+    ///
+    /// It's an estimate for monitoring the log growth only, as the actual
+    /// size depends on how the [`Event`]s are persisted.
+    pub fn event_log_bytes(&self) -> usize {
+        self.events
+            .iter()
+            .map(|event| format!("{event:?}").len() + 1)
+            .sum()
+    }
+
+    /// Checks the [`Slug`] uniqueness invariant across the [`ShortLink`]s,
+    /// their aliases (see [`UrlShortenerService::consolidate_duplicates()`])
+    /// and canonical forms (see
+    /// [`UrlShortenerService::with_trim_trailing_slash_slugs()`]) of their
+    /// [`Slug`]s in every namespace.
+    ///
+    /// ## Errors
+    ///
+    /// The first found [`SlugIntegrityError`], checking aliases first, all in
+    /// the [`Slug`] order.
+    pub fn verify_slug_integrity(&self) -> Result<(), SlugIntegrityError> {
+        let mut aliases: Vec<_> = self.aliases.iter().collect();
+        aliases.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (alias, target) in aliases {
+            if alias == target {
+                return Err(SlugIntegrityError::SelfAlias(alias.clone()));
+            }
+            if self.links.contains_key(alias) {
+                return Err(SlugIntegrityError::LiveAlias(alias.clone()));
+            }
+            if !self.links.contains_key(target) {
+                return Err(SlugIntegrityError::DanglingAlias(alias.clone()));
+            }
+        }
+
+        let mut slugs: Vec<_> = self.links.keys().collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut canonical = HashMap::new();
+        for slug in slugs {
+            let form = self.canonical_slug(slug.clone());
+            if let Some(other) = canonical.insert(form, slug) {
+                return Err(SlugIntegrityError::DuplicateSlug(other.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the moment this service has been started at.
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    /// Returns how long this service has been running for.
+    pub fn uptime(&self) -> Duration {
+        self.clock
+            .now()
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+    }
+
+    /// Returns the [`Slug`] the next [`ShortLink`] created without a provided
+    /// one would be generated, without generating it.
+    ///
+    /// For the [`UrlShortenerService::with_sequential_slugs()`] it's exactly
+    /// the [`Slug`] to be used, unless another [`ShortLink`] is created in
+    /// between. Otherwise, it's a fresh random sample not in use, denied or
+    /// reserved (see [`UrlShortenerService::reserve_slug()`]), but not
+    /// necessarily the one to be used.
+    pub fn peek_next_slug(&self) -> Slug {
+        let attempts = u64::from(self.config.slug_generation_attempts.max(1));
+        if !self.config.sequential_slugs {
+            use rand::seq::SliceRandom;
+            let alphabet = self.slug_alphabet();
+            let mut rng = rand::thread_rng();
+            let mut sample = || {
+                Slug(
+                    (0..self.config.slug_length)
+                        .filter_map(|_| alphabet.choose(&mut rng))
+                        .collect(),
+                )
+            };
+            let unused = (0..attempts)
+                .map(|_| sample())
+                .find(|slug| self.is_unused_slug(slug));
+            return unused.unwrap_or_else(sample);
+        }
+        (0..attempts)
+            .map(|i| self.sequential_slug(self.generated_slugs + i))
+            .find(|slug| self.is_unused_slug(slug))
+            .unwrap_or_else(|| self.sequential_slug(self.generated_slugs))
+    }
+
+    /// Returns the display title of the [`ShortLink`] with the provided
+    /// [`Slug`], if any (see [`UrlShortenerService::handle_set_title()`]).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn get_title(
+        &self,
+        slug: Slug,
+    ) -> Result<Option<String>, ShortenerError> {
+        self.audit_read("get_title", Some(&slug));
+        Ok(self.state(&slug)?.title.clone())
+    }
+
+    /// Returns how long after its creation the first redirect through the
+    /// [`ShortLink`] with the provided [`Slug`] was made, or [`None`] if it
+    /// has never been redirected through.
+    ///
+    /// The first redirect is not preserved by
+    /// [`UrlShortenerService::compact()`], nor counted if seeded.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn time_to_first_click(
+        &self,
+        slug: Slug,
+    ) -> Result<Option<Duration>, ShortenerError> {
+        self.audit_read("time_to_first_click", Some(&slug));
+        let state = self.state(&slug)?;
+        Ok(state
+            .first_redirect
+            .map(|at| at.duration_since(state.created_at).unwrap_or_default()))
+    }
+
+    /// Starts a [`LinkQuery`] of all the [`ShortLink`]s, to be filtered,
+    /// sorted and paginated.
+    pub fn query(&self) -> LinkQuery<'_> {
+        LinkQuery {
+            service: self,
+            filters: Vec::new(),
+            sort_key: SortKey::default(),
+            descending: false,
+            offset: 0,
+            limit: None,
+        }
+    }
+
+    /// Returns the [`SlugRole`] of the provided [`Slug`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn slug_role(&self, slug: &Slug) -> Result<SlugRole, ShortenerError> {
+        self.audit_read("slug_role", Some(slug));
+        let target = &self.state(slug)?.link.slug;
+        let slug = self.canonical_slug(slug.clone());
+        if *target != slug {
+            return Ok(SlugRole::Alias(target.clone()));
+        }
+        let mut aliases: Vec<_> = self
+            .aliases
+            .iter()
+            .filter(|(_, t)| *t == target)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        if aliases.is_empty() {
+            return Ok(SlugRole::Standalone);
+        }
+        aliases.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(SlugRole::CanonicalTargetOf(aliases))
+    }
+
+    /// Follows the [`ShortLink`] with the provided [`Slug`] through up to
+    /// `max_depth` [`ShortLink`]s it (transitively) points to (see
+    /// [`UrlShortenerService::with_base_url()`]), returning the [`Slug`]s of
+    /// all the visited ones, starting with the provided one.
+    ///
+    /// The chain ends at the first [`ShortLink`] pointing elsewhere (or to a
+    /// non-existent [`ShortLink`] of this service).
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::RedirectLoop`] if the chain comes back to an already
+    ///   visited [`ShortLink`].
+    pub fn resolve_chain(
+        &self,
+        slug: Slug,
+        max_depth: usize,
+    ) -> Result<Vec<Slug>, ShortenerError> {
+        self.audit_read("resolve_chain", Some(&slug));
+        let mut state = self.state(&slug)?;
+        let mut chain = vec![state.link.slug.clone()];
+        for _ in 0..max_depth {
+            let Some(next) = self.slug_of_short_url(&self.url_of(state)) else {
+                break;
+            };
+            let Ok(next) = self.state(&next) else {
+                break;
+            };
+            if chain.contains(&next.link.slug) {
+                return Err(ShortenerError::RedirectLoop);
+            }
+            chain.push(next.link.slug.clone());
+            state = next;
+        }
+        Ok(chain)
+    }
+
+    /// Returns the [`Health`] summary of this service.
+    ///
+    /// Checking the consistency replays the whole event log, so it costs
+    /// `O(n)` in the event log length.
+    pub fn health(&self) -> Health {
+        let now = self.clock.now();
+        Health {
+            total_links: self.links.len(),
+            active_links: self.active_links().count(),
+            event_log_len: self.events.len(),
+            consistency_ok: self.is_consistent(),
+            expired_pending_cleanup: self
+                .links
+                .values()
+                .filter(|state| state.is_expired(now, self.config.idle_expiry))
+                .count(),
+        }
+    }
+
+    /// Returns the [`Slug`]s of the expired [`ShortLink`]s (including the idle
+    /// ones, see [`UrlShortenerService::with_idle_expiry()`]), ordered.
+    pub fn expired_links(&self) -> Vec<Slug> {
+        let now = self.clock.now();
+        let mut slugs: Vec<_> = self
+            .links
+            .values()
+            .filter(|state| state.is_expired(now, self.config.idle_expiry))
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the fraction of the [`ShortLink`]s being expired (see
+    /// [`UrlShortenerService::expired_links()`]), or `0.0` if there are none.
+    pub fn expired_fraction(&self) -> f64 {
+        if self.links.is_empty() {
+            return 0.0;
+        }
+        self.expired_links().len() as f64 / self.links.len() as f64
+    }
+
+    /// Returns every [`Url`] the [`ShortLink`] with the provided [`Slug`] has
+    /// pointed to, in chronological order, as recorded in the event log: the
+    /// one it was created with first and the current one last.
+    ///
+    /// After [`UrlShortenerService::compact()`], only the current one is left.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn url_history(&self, slug: Slug) -> Result<Vec<Url>, ShortenerError> {
+        self.audit_read("url_history", Some(&slug));
+        let slug = &self.state(&slug)?.link.slug;
+        Ok(self
+            .link_events(slug)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ShortLinkCreated { url, .. } => Some(url.clone()),
+                Event::ShortLinkChanged { new_url, .. } => {
+                    Some(new_url.clone())
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns up to `n` most recent [`ClickRecord`]s of the [`ShortLink`] with
+    /// the provided [`Slug`], the newest first.
+    ///
+    /// Only the redirects recorded individually into the event log are
+    /// returned, so they're retained until [`UrlShortenerService::compact()`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn recent_clicks(
+        &self,
+        slug: Slug,
+        n: usize,
+    ) -> Result<Vec<ClickRecord>, ShortenerError> {
+        self.audit_read("recent_clicks", Some(&slug));
+        let slug = &self.state(&slug)?.link.slug;
+        Ok(self
+            .link_events(slug)
+            .into_iter()
+            .rev()
+            .filter_map(|event| match event {
+                Event::ShortLinkRedirected {
+                    source,
+                    referrer,
+                    at,
+                    ..
+                } => Some(ClickRecord {
+                    at: *at,
+                    referrer: referrer.clone(),
+                    source: source.clone(),
+                }),
+                _ => None,
+            })
+            .take(n)
+            .collect())
+    }
+
+    /// Returns the total count of redirects made from the provided `referrer`
+    /// across all the [`ShortLink`]s ever created (including the ones merged
+    /// away by [`UrlShortenerService::consolidate_duplicates()`]), e.g. for
+    /// measuring the contribution of a partner, with an empty one standing
+    /// for `(direct)` visits.
+    ///
+    /// Only the redirects recorded individually into the event log are
+    /// counted, scaled if sampled (see
+    /// [`UrlShortenerService::with_redirect_sampling()`]), saturating at
+    /// [`u64::MAX`].
+    pub fn referrer_total(&self, referrer: &str) -> u64 {
+        let referrer = if referrer.is_empty() {
+            "(direct)"
+        } else {
+            referrer
+        };
+        let mut recorded: HashMap<&Slug, u64> = HashMap::new();
+        for event in &self.events {
+            if let Event::ShortLinkRedirected {
+                slug,
+                referrer: from,
+                count,
+                ..
+            } = event
+            {
+                let from = from.as_deref().filter(|r| !r.is_empty());
+                if from.unwrap_or("(direct)") == referrer {
+                    let total = recorded.entry(slug).or_default();
+                    *total = total.saturating_add(*count);
+                }
+            }
+        }
+        recorded
+            .into_iter()
+            .map(|(slug, count)| self.unsample(self.state(slug).ok(), count))
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Returns the counts of redirects of the [`ShortLink`] with the provided
+    /// [`Slug`] per consecutive buckets of the provided `interval`, aligned to
+    /// the Unix epoch, along with the moments the buckets start at, in
+    /// chronological order, omitting the empty buckets.
+    ///
+    /// Only the redirects recorded individually into the event log are
+    /// counted, scaled if sampled (see
+    /// [`UrlShortenerService::with_redirect_sampling()`]).
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::InvalidInterval`] if the `interval` is zero.
+    pub fn redirect_buckets(
+        &self,
+        slug: Slug,
+        interval: Duration,
+    ) -> Result<Vec<(SystemTime, u64)>, ShortenerError> {
+        self.audit_read("redirect_buckets", Some(&slug));
+        let state = self.state(&slug)?;
+        let slug = &state.link.slug;
+        if interval.is_zero() {
+            return Err(ShortenerError::InvalidInterval);
+        }
+
+        let step = interval.as_nanos();
+        let mut buckets = BTreeMap::new();
+        for event in self.link_events(slug) {
+            if let Event::ShortLinkRedirected { at, count, .. } = event {
+                let since = at
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default();
+                let bucket: &mut u64 =
+                    buckets.entry(since.as_nanos() / step).or_default();
+                *bucket = bucket.saturating_add(*count);
+            }
+        }
+        Ok(buckets
+            .into_iter()
+            .map(|(bucket, count)| {
+                let start = bucket * step;
+                let start = Duration::new(
+                    (start / 1_000_000_000) as u64,
+                    (start % 1_000_000_000) as u32,
+                );
+                (
+                    SystemTime::UNIX_EPOCH + start,
+                    self.unsample(Some(state), count),
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the lifecycle timeline of the [`ShortLink`] with the provided
+    /// [`Slug`] as human-readable entries along with their moments, in
+    /// chronological order, as recorded in the event log.
+    ///
+    /// Consecutive redirects are summarized into a single entry at the moment
+    /// of the first one of them.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn link_timeline(
+        &self,
+        slug: Slug,
+    ) -> Result<Vec<(SystemTime, String)>, ShortenerError> {
+        self.audit_read("link_timeline", Some(&slug));
+        let slug = &self.state(&slug)?.link.slug;
+
+        let mut timeline = Vec::new();
+        let mut redirects: Option<(SystemTime, u64)> = None;
+        for event in self.link_events(slug) {
+            if let Event::ShortLinkRedirected { at, count, .. } = event {
+                let total = &mut redirects.get_or_insert((*at, 0)).1;
+                *total = total.saturating_add(*count);
+                continue;
+            }
+            if let Some((at, count)) = redirects.take() {
+                timeline.push((at, format!("redirected {count} time(s)")));
+            }
+
+            let entry = match event {
+                Event::ShortLinkCreated { url, .. } => {
+                    format!("created pointing to {}", url.0)
+                }
+                Event::ShortLinkRedirected { .. } => continue,
+                Event::ShortLinkChanged { new_url, .. } => {
+                    format!("changed to point to {}", new_url.0)
+                }
+                Event::ShortLinkDisabled { .. } => "disabled".into(),
+                Event::ShortLinkEnabled { .. } => "enabled".into(),
+                Event::ShortLinkExpirySet {
+                    expires_at: Some(_),
+                    ..
+                } => "set to expire".into(),
+                Event::ShortLinkExpirySet {
+                    expires_at: None, ..
+                } => "set to never expire".into(),
+                Event::ShortLinkNoteSet {
+                    note: Some(note), ..
+                } => {
+                    format!("note set to {note:?}")
+                }
+                Event::ShortLinkNoteSet { note: None, .. } => {
+                    "note removed".into()
+                }
+                Event::ShortLinkTitleSet {
+                    title: Some(title), ..
+                } => {
+                    format!("title set to {title:?}")
+                }
+                Event::ShortLinkTitleSet { title: None, .. } => {
+                    "title removed".into()
+                }
+                Event::ShortLinkActivationScheduled { .. } => {
+                    "scheduled to go live".into()
+                }
+                Event::ShortLinkAliasAdded { alias, .. } => {
+                    format!("alias {} added", alias.0)
+                }
+                Event::ShortLinkAliasRemoved { alias, .. } => {
+                    format!("alias {} removed", alias.0)
+                }
+                Event::RedirectsSeeded { count, .. } => {
+                    format!("seeded with {count} redirect(s)")
+                }
+                Event::ShortLinkCountriesSet { countries, .. }
+                    if countries.is_empty() =>
+                {
+                    "allowed in all countries".into()
+                }
+                Event::ShortLinkCountriesSet { countries, .. } => {
+                    format!("allowed in {}", countries.join(", "))
+                }
+                Event::ShortLinkReferersSet { referers, .. }
+                    if referers.is_empty() =>
+                {
+                    "allowed from all referers".into()
+                }
+                Event::ShortLinkReferersSet { referers, .. } => {
+                    format!("allowed from {}", referers.join(", "))
+                }
+                Event::ShortLinkMetadataSet {
+                    key,
+                    value: Some(value),
+                    ..
+                } => {
+                    format!("metadata {key:?} set to {value:?}")
+                }
+                Event::ShortLinkMetadataSet {
+                    key, value: None, ..
+                } => {
+                    format!("metadata {key:?} removed")
+                }
+                Event::ShortLinkResponseSet { status, .. } => {
+                    format!("set to redirect with {status}")
+                }
+                Event::ShortLinkCampaignAssigned { campaign, .. } => {
+                    format!("assigned to campaign {campaign:?}")
+                }
+                Event::ShortLinkMadeOneTime { .. } => "made one-time".into(),
+                Event::ShortLinkMadeTemplate { .. } => "made a template".into(),
+                Event::ShortLinkConsumed { .. } => "consumed".into(),
+                Event::ShortLinkSamplingSet {
+                    rate: Some(rate), ..
+                } => {
+                    format!("sampled at {rate}")
+                }
+                Event::ShortLinkSamplingSet { rate: None, .. } => {
+                    "sampled by default".into()
+                }
+                Event::ShortLinkRenamed { new_slug, .. } => {
+                    format!("renamed to {}", new_slug.0)
+                }
+                Event::ShortLinkSplitSet { variants, .. } => {
+                    format!("split between {} URL(s)", variants.len())
+                }
+                Event::ShortLinkMerged { into, .. } => {
+                    format!("merged into {}", into.0)
+                }
+                Event::ShortLinkDeleted { .. } => "deleted".into(),
+            };
+            timeline.push((event.at(), entry));
+        }
+        if let Some((at, count)) = redirects {
+            timeline.push((at, format!("redirected {count} time(s)")));
+        }
+        Ok(timeline)
+    }
+
+    /// Returns the [`LinkMetadata`] of the [`ShortLink`] with the provided
+    /// [`Slug`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn metadata(
+        &self,
+        slug: &Slug,
+    ) -> Result<LinkMetadata, ShortenerError> {
+        self.audit_read("metadata", Some(slug));
+        let state = self.state(slug)?;
+        let now = self.clock.now();
+        Ok(LinkMetadata {
+            created_at: state.created_at,
+            redirects: state.redirects,
+            last_redirect: state.last_redirect,
+            enabled: state.enabled,
+            ttl_remaining: state
+                .expires_at
+                .map(|at| at.duration_since(now).unwrap_or_default()),
+            note: state.note.clone(),
+        })
+    }
+
+    /// Returns the count of redirects made through the [`ShortLink`] with the
+    /// provided [`Slug`] while it was disabled, served due to
+    /// [`DisabledBehavior::ServeAnyway`].
+    ///
+    /// The count is not preserved by [`UrlShortenerService::compact()`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn redirects_while_disabled(
+        &self,
+        slug: Slug,
+    ) -> Result<u64, ShortenerError> {
+        self.audit_read("redirects_while_disabled", Some(&slug));
+        Ok(self.state(&slug)?.redirects_while_disabled)
+    }
+
+    /// Returns the count of redirects made through the [`ShortLink`] with the
+    /// provided [`Slug`] within its active window: from
+    /// the moment it goes live (see
+    /// [`UrlShortenerService::handle_set_active_from()`]) until its expiration
+    /// moment, if any of them is set.
+    ///
+    /// Only the redirects recorded individually into the event log are
+    /// counted, scaled if sampled (see
+    /// [`UrlShortenerService::with_redirect_sampling()`]).
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn in_window_redirects(
+        &self,
+        slug: Slug,
+    ) -> Result<u64, ShortenerError> {
+        self.audit_read("in_window_redirects", Some(&slug));
+        let state = self.state(&slug)?;
+        let count = self
+            .link_events(&state.link.slug)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ShortLinkRedirected { at, count, .. }
+                    if state.active_from.is_none_or(|from| from <= *at)
+                        && state.expires_at.is_none_or(|until| *at < until) =>
+                {
+                    Some(*count)
+                }
+                _ => None,
+            })
+            .fold(0, u64::saturating_add);
+        Ok(self.unsample(Some(state), count))
+    }
+
+    /// Returns the momentum of the [`ShortLink`] with the provided [`Slug`]:
+    /// the ratio of its count of redirects within the last `window` to the one
+    /// within the `window` preceding it, e.g. `2.0` when they doubled.
+    ///
+    /// With no redirects within the preceding `window`, it's
+    /// [`f64::INFINITY`] if there are any within the last one, or `0.0`
+    /// otherwise.
+    ///
+    /// Only the redirects recorded individually into the event log are
+    /// counted.
+    ///
+    /// ## Errors
+    ///
+    /// - [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    /// - [`ShortenerError::InvalidInterval`] if the `window` is zero.
+    pub fn redirect_momentum(
+        &self,
+        slug: Slug,
+        window: Duration,
+    ) -> Result<f64, ShortenerError> {
+        self.audit_read("redirect_momentum", Some(&slug));
+        let slug = &self.state(&slug)?.link.slug;
+        if window.is_zero() {
+            return Err(ShortenerError::InvalidInterval);
+        }
+
+        let now = self.clock.now();
+        let within = |ago: Duration, at: SystemTime| {
+            let until = now.checked_sub(ago);
+            let from = until.and_then(|until| until.checked_sub(window));
+            until.is_some_and(|until| at <= until)
+                && from.is_none_or(|from| from < at)
+        };
+        let (mut recent, mut previous) = (0_u64, 0_u64);
+        for event in self.link_events(slug) {
+            if let Event::ShortLinkRedirected { at, count, .. } = event {
+                if within(Duration::ZERO, *at) {
+                    recent = recent.saturating_add(*count);
+                } else if within(window, *at) {
+                    previous = previous.saturating_add(*count);
+                }
+            }
+        }
+        Ok(match (recent, previous) {
+            (0, 0) => 0.0,
+            (_, 0) => f64::INFINITY,
+            _ => recent as f64 / previous as f64,
+        })
+    }
+
+    /// Returns the count of individual redirects made through the
+    /// [`ShortLink`] with the provided [`Slug`], which differs from its count
+    /// of redirects if [`UrlShortenerService::with_redirect_increment()`] is
+    /// used.
+    ///
+    /// Seeded redirects are not individual ones, so they are not counted.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn redirect_hits(&self, slug: Slug) -> Result<u64, ShortenerError> {
+        self.audit_read("redirect_hits", Some(&slug));
+        Ok(self.state(&slug)?.hits)
+    }
+
+    /// Returns the count of redirects of the [`ShortLink`] with the provided
+    /// [`Slug`] broken down into the ones made through it and the ones seeded
+    /// from elsewhere (by [`UrlShortenerService::handle_seed_redirects()`] or
+    /// [`UrlShortenerService::reconcile_counts()`]).
+    ///
+    /// The breakdown is not preserved by
+    /// [`UrlShortenerService::from_snapshot()`], treating all the redirects
+    /// as organic ones.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn get_stats_detailed_counts(
+        &self,
+        slug: Slug,
+    ) -> Result<CountBreakdown, ShortenerError> {
+        self.audit_read("get_stats_detailed_counts", Some(&slug));
+        let state = self.state(&slug)?;
+        Ok(CountBreakdown {
+            organic: state.redirects.saturating_sub(state.seeded),
+            seeded: state.seeded,
+            total: state.redirects,
+        })
+    }
+
+    /// Returns the numeric id of the [`ShortLink`] with the provided [`Slug`],
+    /// assigned monotonically on its creation, starting from `1`.
+    ///
+    /// The id is recorded in the event log, so it survives the replays,
+    /// [`UrlShortenerService::compact()`] and
+    /// [`UrlShortenerService::from_snapshot()`], and can be embedded into
+    /// [`Url`]s compactly with [`encode_base62()`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`].
+    pub fn link_id(&self, slug: &Slug) -> Result<u64, ShortenerError> {
+        self.audit_read("link_id", Some(slug));
+        Ok(self.state(slug)?.id)
+    }
+
+    /// Returns the [`LinkMetadata`] of the [`ShortLink`]s with the provided
+    /// [`Slug`]s, positionally.
+    ///
+    /// Missing [`ShortLink`]s are reported with a
+    /// [`ShortenerError::SlugNotFound`] at their positions.
+    pub fn metadata_batch(
+        &self,
+        slugs: &[Slug],
+    ) -> Vec<Result<LinkMetadata, ShortenerError>> {
+        slugs.iter().map(|slug| self.metadata(slug)).collect()
+    }
+
+    /// Returns all the [`Event`]s appended after the provided sequence
+    /// number, in order.
+    ///
+    /// Used to sync a replica knowing the state up to `seq` via
+    /// [`UrlShortenerService::apply_changes()`].
+    pub fn changes_since(&self, seq: u64) -> Vec<SequencedEvent> {
+        self.events
+            .iter()
+            .zip(1..)
+            .skip_while(|(_, s)| *s <= seq)
+            .map(|(event, seq)| SequencedEvent {
+                seq,
+                event: event.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the sequence number of the [`Event::ShortLinkCreated`] of the
+    /// [`ShortLink`] with the provided [`Slug`], following its renames.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::SlugNotFound`] if there is no such [`ShortLink`], or
+    /// its creation is not in the event log anymore.
+    pub fn creation_seq(&self, slug: &Slug) -> Result<u64, ShortenerError> {
+        self.audit_read("creation_seq", Some(slug));
+        let slug = &self.state(slug)?.link.slug;
+        let created = self
+            .link_events(slug)
+            .into_iter()
+            .find(|e| matches!(e, Event::ShortLinkCreated { .. }))
+            .ok_or(ShortenerError::SlugNotFound)?;
+        self.events
+            .iter()
+            .position(|e| std::ptr::eq(e, created))
+            .map(|pos| pos as u64 + 1)
+            .ok_or(ShortenerError::SlugNotFound)
+    }
+
+    /// Returns the count of redirects at each of the provided `percentiles`
+    /// (in `0.0..=1.0` range) across all the active [`ShortLink`]s,
+    /// positionally.
+    ///
+    /// Percentiles are computed with linear interpolation between the closest
+    /// ranks: for `n` sorted counts, the percentile `p` lies at the fractional
+    /// index `p * (n - 1)`, and the result is rounded to the nearest integer.
+    /// With no active [`ShortLink`]s, every percentile is `0`.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::InvalidInterval`] if any of the `percentiles` is not
+    /// in `0.0..=1.0` range.
+    pub fn redirect_percentiles(
+        &self,
+        percentiles: &[f64],
+    ) -> Result<Vec<u64>, ShortenerError> {
+        if !percentiles.iter().all(|p| (0.0..=1.0).contains(p)) {
+            return Err(ShortenerError::InvalidInterval);
+        }
+
+        let mut counts: Vec<u64> =
+            self.active_links().map(|state| state.redirects).collect();
+        if counts.is_empty() {
+            return Ok(vec![0; percentiles.len()]);
+        }
+        counts.sort_unstable();
+
+        Ok(percentiles
+            .iter()
+            .map(|p| {
+                let rank = p * (counts.len() - 1) as f64;
+                let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+                let (lo_val, hi_val) = (counts[lo] as f64, counts[hi] as f64);
+                (lo_val + (hi_val - lo_val) * (rank - lo as f64)).round() as u64
+            })
+            .collect())
+    }
+
+    /// Returns the [`Slug`]s of the [`ShortLink`]s having the metadata entry
+    /// with the provided `key` (see
+    /// [`UrlShortenerService::handle_set_metadata()`]) and, if provided, the
+    /// `value`, ordered by [`Slug`].
+    pub fn links_with_metadata(
+        &self,
+        key: &str,
+        value: Option<&str>,
+    ) -> Vec<Slug> {
+        let mut slugs: Vec<_> = self
+            .links
+            .values()
+            .filter(|state| {
+                state
+                    .metadata
+                    .get(key)
+                    .is_some_and(|v| value.is_none_or(|value| v == value))
+            })
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the count of [`ShortLink`]s created on every day (as the number
+    /// of days since the Unix epoch in the time zone with the provided UTC
+    /// offset), ordered by day, omitting the days without any.
+    pub fn daily_new_links(&self, utc_offset_minutes: i32) -> Vec<(i64, u64)> {
+        let mut days = BTreeMap::new();
+        for state in self.links.values() {
+            *days
+                .entry(day_of(state.created_at, utc_offset_minutes))
+                .or_default() += 1;
+        }
+        days.into_iter().collect()
+    }
+
+    /// Returns the mean count of redirects per active [`ShortLink`], or `0.0`
+    /// if there are none.
+    pub fn mean_redirects_per_link(&self) -> f64 {
+        let (count, total) = self
+            .active_links()
+            .fold((0, 0), |(n, t), s| (n + 1, t + u128::from(s.redirects)));
+        if count == 0 {
+            return 0.0;
+        }
+        total as f64 / count as f64
+    }
+
+    /// Returns the [`Slug`]s of the [`ShortLink`]s (including the split
+    /// variants) whose [`Url`] matches the provided violation `predicate`,
+    /// ordered, e.g. for auditing the existing [`ShortLink`]s before enabling
+    /// a restrictive policy.
+    pub fn links_violating<F: Fn(&Url) -> bool>(
+        &self,
+        predicate: F,
+    ) -> Vec<Slug> {
+        let mut slugs: Vec<_> = self
+            .links
+            .values()
+            .filter(|state| {
+                predicate(&self.url_of(state))
+                    || state
+                        .variants
+                        .iter()
+                        .any(|v| predicate(&self.deobfuscate(&v.url)))
+            })
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns a fingerprint of the current state: the 64-bit FNV-1a hash of
+    /// the [`Slug`], the [`Url`] and the count of redirects of every active
+    /// [`ShortLink`], ordered by [`Slug`].
+    ///
+    /// It's stable across runs and platforms, and independent of the order
+    /// the [`ShortLink`]s were created in, so services in the same logical
+    /// state (e.g. replicas) have the same fingerprint, while any drift
+    /// changes it (with an overwhelming probability).
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut links: Vec<_> = self.active_links().collect();
+        links.sort_by(|a, b| a.link.slug.0.cmp(&b.link.slug.0));
+        links.into_iter().fold(FNV_OFFSET_BASIS, |hash, state| {
+            // `0xff` never occurs in UTF-8, so it separates the fields
+            // unambiguously.
+            let hash = fnv1a(hash, state.link.slug.0.as_bytes());
+            let hash = fnv1a(hash, &[0xff]);
+            let hash = fnv1a(hash, self.url_of(state).0.as_bytes());
+            let hash = fnv1a(hash, &[0xff]);
+            fnv1a(hash, &state.redirects.to_le_bytes())
+        })
+    }
+
+    /// Returns the [`Stats`] of the active [`ShortLink`]s whose count of
+    /// redirects is within the provided `min..=max` range, ordered by the count
+    /// and then by [`Slug`], e.g. for segmenting them by popularity.
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::InvalidInterval`] if the `min` is greater than the
+    /// `max`.
+    pub fn links_in_redirect_range(
+        &self,
+        min: u64,
+        max: u64,
+    ) -> Result<Vec<Stats>, ShortenerError> {
+        if min > max {
+            return Err(ShortenerError::InvalidInterval);
+        }
+
+        let mut stats: Vec<_> = self
+            .active_links()
+            .filter(|state| (min..=max).contains(&state.redirects))
+            .map(|state| Stats {
+                link: self.link_of(state),
+                redirects: state.redirects,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            a.redirects
+                .cmp(&b.redirects)
+                .then_with(|| a.link.slug.0.cmp(&b.link.slug.0))
+        });
+        Ok(stats)
+    }
+
+    /// Calls the provided `f` with the [`Slug`], the [`ShortLink`] and the
+    /// count of redirects of every active [`ShortLink`], ordered by [`Slug`],
+    /// e.g. for streaming a huge store into a file.
+    ///
+    /// Only the references to the [`Slug`]s are collected for ordering them,
+    /// while the [`ShortLink`]s are built one at a time.
+    pub fn for_each_link<F: FnMut(&Slug, &ShortLink, u64)>(&self, mut f: F) {
+        let mut states: Vec<_> = self.active_links().collect();
+        states.sort_by(|a, b| a.link.slug.0.cmp(&b.link.slug.0));
+        for state in states {
+            f(&state.link.slug, &self.link_of(state), state.redirects);
+        }
+    }
+
+    /// Returns the counts of redirects of all the active [`ShortLink`]s by
+    /// their [`Slug`]s, all as of the same moment, e.g. for feeding a gauge.
+    pub fn counter_snapshot(&self) -> HashMap<Slug, u64> {
+        self.active_links()
+            .map(|state| (state.link.slug.clone(), state.redirects))
+            .collect()
+    }
+
+    /// Returns up to the `limit` active [`ShortLink`]s shuffled by the
+    /// provided `seed`, e.g. for a fair round-robin display of them.
+    ///
+    /// The order only depends on the `seed` and the [`Slug`]s, so the same
+    /// `seed` always yields the same order for the same [`ShortLink`]s.
+    pub fn shuffled_links(&self, seed: u64, limit: usize) -> Vec<ShortLink> {
+        let seed = fnv1a(FNV_OFFSET_BASIS, &seed.to_le_bytes());
+        let mut links: Vec<_> = self
+            .active_links()
+            .map(|s| (fnv1a(seed, s.link.slug.0.as_bytes()), &s.link.slug, s))
+            .collect();
+        links.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1 .0.cmp(&b.1 .0)));
+        links
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, state)| self.link_of(state))
+            .collect()
+    }
+
+    /// Returns the pairs of the [`Slug`]s of the active [`ShortLink`]s within
+    /// the provided `max_edit_distance` (Levenshtein one, in characters) of
+    /// each other, e.g. `paypa1` and `paypal` for detecting typo-squatting,
+    /// with the smaller [`Slug`] first and all ordered.
+    ///
+    /// Every pair of the [`ShortLink`]s is compared, so it takes quadratic
+    /// time in their count, while the pairs whose lengths differ by more than
+    /// the `max_edit_distance` are skipped without computing the distance.
+    pub fn find_similar_slug_pairs(
+        &self,
+        max_edit_distance: usize,
+    ) -> Vec<(Slug, Slug)> {
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .map(|state| {
+                let chars: Vec<_> = state.link.slug.0.chars().collect();
+                (&state.link.slug, chars)
+            })
+            .collect();
+        slugs.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        let mut pairs = Vec::new();
+        for (i, (a, a_chars)) in slugs.iter().enumerate() {
+            for (b, b_chars) in &slugs[i + 1..] {
+                if a_chars.len().abs_diff(b_chars.len()) <= max_edit_distance
+                    && edit_distance(a_chars, b_chars) <= max_edit_distance
+                {
+                    pairs.push(((*a).clone(), (*b).clone()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns the [`Slug`]s of the active [`ShortLink`]s whose [`Url`] has a
+    /// scheme other than `https`, ordered, e.g. for auditing them before
+    /// enforcing HTTPS.
+    pub fn insecure_links(&self) -> Vec<Slug> {
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .filter(|state| {
+                !self.url_of(state).scheme().eq_ignore_ascii_case("https")
+            })
+            .map(|state| state.link.slug.clone())
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the Gini coefficient of the counts of redirects of the active
+    /// [`ShortLink`]s, from `0.0` (all of them are redirected through evenly)
+    /// to almost `1.0` (all the redirects are made through a single one), or
+    /// `0.0` if there are less than two of them or no redirects at all.
+    ///
+    /// For the `n` counts `x` sorted ascending (indexed from `1`), it equals
+    /// `2 * Σ(i * x_i) / (n * Σx_i) - (n + 1) / n`.
+    pub fn click_gini(&self) -> f64 {
+        let mut counts: Vec<_> =
+            self.active_links().map(|s| s.redirects).collect();
+        counts.sort_unstable();
+        let n = counts.len() as f64;
+        let total: f64 = counts.iter().map(|&x| x as f64).sum();
+        if counts.len() < 2 || total == 0.0 {
+            return 0.0;
+        }
+        let weighted: f64 = counts
+            .iter()
+            .zip(1..)
+            .map(|(&x, i)| f64::from(i) * x as f64)
+            .sum();
+        2.0 * weighted / (n * total) - (n + 1.0) / n
+    }
+
+    /// Returns the Pearson correlation coefficient between the lengths (in
+    /// characters) of the [`Slug`]s of the active [`ShortLink`]s and their
+    /// counts of redirects, from `-1.0` (shorter [`Slug`]s are redirected
+    /// through more) to `1.0` (longer ones are), or [`None`] if there are less
+    /// than two active [`ShortLink`]s or either of the values doesn't vary.
+    pub fn slug_length_click_correlation(&self) -> Option<f64> {
+        let points: Vec<_> = self
+            .active_links()
+            .map(|s| (s.link.slug.0.chars().count() as f64, s.redirects as f64))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for (x, y) in &points {
+            cov += (x - mean_x) * (y - mean_y);
+            var_x += (x - mean_x).powi(2);
+            var_y += (y - mean_y).powi(2);
+        }
+        if var_x == 0.0 || var_y == 0.0 {
+            return None;
+        }
+        Some(cov / (var_x * var_y).sqrt())
+    }
+
+    /// Returns every [`Url`] pointed to by more than one active [`ShortLink`]
+    /// along with the [`Slug`]s and counts of redirects of these
+    /// [`ShortLink`]s, all ordered by [`Url`] and [`Slug`] respectively.
+    pub fn duplicate_target_groups(&self) -> Vec<(Url, Vec<(Slug, u64)>)> {
+        let mut groups: BTreeMap<String, Vec<(Slug, u64)>> = BTreeMap::new();
+        for state in self.active_links() {
+            groups
+                .entry(self.url_of(state).0)
+                .or_default()
+                .push((state.link.slug.clone(), state.redirects));
+        }
+        groups
+            .into_iter()
+            .filter(|(_, links)| links.len() > 1)
+            .map(|(url, mut links)| {
+                links.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+                (Url(url), links)
+            })
+            .collect()
+    }
+
+    /// Exports the counts of redirects of the [`ShortLink`]s (ordered by
+    /// [`Slug`]) and the total count of them in the Prometheus text exposition
+    /// format, for scraping by a metrics system.
+    pub fn export_prometheus(&self) -> String {
+        self.audit_read("export_prometheus", None);
+        let mut states: Vec<_> = self.links.values().collect();
+        states.sort_by(|a, b| a.link.slug.0.cmp(&b.link.slug.0));
+
+        let mut out = String::from(
+            "# HELP tiny_url_redirects_total Count of redirects of a short \
+             link.\n\
+             # TYPE tiny_url_redirects_total counter\n",
+        );
+        for state in states {
+            let slug = state
+                .link
+                .slug
+                .0
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            out.push_str(&format!(
+                "tiny_url_redirects_total{{slug=\"{slug}\"}} {}\n",
+                state.redirects,
+            ));
+        }
+        out.push_str(
+            "# HELP tiny_url_links_total Count of short links.\n\
+             # TYPE tiny_url_links_total gauge\n",
+        );
+        out.push_str(&format!("tiny_url_links_total {}\n", self.links.len()));
+        out
+    }
+
+    /// Renders all the [`ShortLink`]s as a monospace-aligned table of the
+    /// `Slug`, `URL` and `Redirects` columns, ordered by [`Slug`], for quick
+    /// inspection.
+    ///
+    /// [`Url`]s longer than 40 characters are truncated, ending with `...`.
+    pub fn render_table(&self) -> String {
+        const MAX_URL_WIDTH: usize = 40;
+
+        self.audit_read("render_table", None);
+
+        let mut rows: Vec<_> = self
+            .links
+            .values()
+            .map(|state| {
+                let url = self.url_of(state).0;
+                let url = if url.chars().count() > MAX_URL_WIDTH {
+                    let head: String =
+                        url.chars().take(MAX_URL_WIDTH - 3).collect();
+                    format!("{head}...")
+                } else {
+                    url
+                };
+                (state.link.slug.0.clone(), url, state.redirects.to_string())
+            })
+            .collect();
+        rows.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+        let width =
+            |header: &str, column: fn(&(String, String, String)) -> &str| {
+                rows.iter()
+                    .map(|row| column(row).chars().count())
+                    .fold(header.len(), usize::max)
+            };
+        let slug_w = width("Slug", |(slug, ..)| slug);
+        let url_w = width("URL", |(_, url, _)| url);
+        let count_w = width("Redirects", |(.., count)| count);
+
+        let mut table = format!(
+            "{:<slug_w$} | {:<url_w$} | {:>count_w$}\n",
+            "Slug", "URL", "Redirects",
+        );
+        table.push_str(&format!(
+            "{}-+-{}-+-{}\n",
+            "-".repeat(slug_w),
+            "-".repeat(url_w),
+            "-".repeat(count_w),
+        ));
+        for (slug, url, count) in &rows {
+            table.push_str(&format!(
+                "{slug:<slug_w$} | {url:<url_w$} | {count:>count_w$}\n",
+            ));
+        }
+        table
+    }
+
+    /// Returns the [`CampaignStats`] of the provided campaign (see
+    /// [`UrlShortenerService::handle_assign_campaign()`]).
+    ///
+    /// An unknown campaign has no [`ShortLink`]s.
+    pub fn campaign_stats(&self, campaign: &str) -> CampaignStats {
+        self.links
+            .values()
+            .filter(|state| state.campaign.as_deref() == Some(campaign))
+            .fold(CampaignStats::default(), |stats, state| CampaignStats {
+                links: stats.links + 1,
+                total_redirects: stats
+                    .total_redirects
+                    .saturating_add(state.redirects),
+            })
+    }
+
+    /// Returns the distinct lowercased hosts of the [`Url`]s of all the active
+    /// [`ShortLink`]s, sorted.
+    pub fn distinct_hosts(&self) -> Vec<String> {
+        let mut hosts: Vec<_> = self
+            .active_links()
+            .filter_map(|state| Some(self.url_of(state).host()?.to_lowercase()))
+            .collect();
+        hosts.sort();
+        hosts.dedup();
+        hosts
+    }
+
+    /// Returns the count of active [`ShortLink`]s per length (in characters)
+    /// of their [`Slug`]s.
+    pub fn slug_length_histogram(&self) -> BTreeMap<usize, u64> {
+        let mut histogram = BTreeMap::new();
+        for state in self.active_links() {
+            let length = state.link.slug.0.chars().count();
+            *histogram.entry(length).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Returns the groups of existing [`Slug`]s differing only by case, each
+    /// group and the groups themselves ordered by [`Slug`].
+    ///
+    /// An empty result means no [`Slug`]s would clash if compared
+    /// case-insensitively.
+    pub fn case_collisions(&self) -> Vec<Vec<Slug>> {
+        let mut groups: BTreeMap<String, Vec<Slug>> = BTreeMap::new();
+        for slug in self.links.keys() {
+            groups
+                .entry(slug.0.to_lowercase())
+                .or_default()
+                .push(slug.clone());
+        }
+        let mut collisions: Vec<_> = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_by(|a, b| a.0.cmp(&b.0));
+                group
+            })
+            .collect();
+        collisions.sort_by(|a, b| a[0].0.cmp(&b[0].0));
+        collisions
+    }
+
+    /// Returns the currently reserved [`Slug`]s (see
+    /// [`UrlShortenerService::reserve_slug()`]), ordered.
+    pub fn reserved_slugs(&self) -> Vec<Slug> {
+        let mut slugs: Vec<_> = self.reserved_slugs.iter().cloned().collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the [`Slug`]s of the active [`ShortLink`]s shadowing any of the
+    /// provided `reserved_paths` of the application (like `static` or
+    /// `assets`), ordered by [`Slug`].
+    ///
+    /// A [`Slug`] shadows a path if it equals the path or lies under it (like
+    /// `static/logo.png` under `static`). Leading and trailing slashes of the
+    /// paths are ignored.
+    pub fn reserved_path_conflicts(
+        &self,
+        reserved_paths: &[&str],
+    ) -> Vec<Slug> {
+        let reserved: Vec<_> =
+            reserved_paths.iter().map(|p| p.trim_matches('/')).collect();
+        let mut slugs: Vec<_> = self
+            .active_links()
+            .map(|state| &state.link.slug)
+            .filter(|slug| {
+                reserved.iter().any(|path| {
+                    slug.0.strip_prefix(path).is_some_and(|rest| {
+                        rest.is_empty() || rest.starts_with('/')
+                    })
+                })
+            })
+            .cloned()
+            .collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        slugs
+    }
+
+    /// Returns the fraction of the [`ShortLink`]s created with a provided
+    /// [`Slug`] rather than a generated one, or `0.0` if there are none.
+    pub fn custom_slug_ratio(&self) -> f64 {
+        if self.links.is_empty() {
+            return 0.0;
+        }
+        let custom = self.links.values().filter(|s| s.custom_slug).count();
+        custom as f64 / self.links.len() as f64
+    }
+
+    /// Returns the creation moments and [`Slug`]s of the [`ShortLink`]s with
+    /// the provided [`SlugOrigin`], the earliest first with ties broken by
+    /// [`Slug`].
+    pub fn creations_by_origin(
+        &self,
+        origin: SlugOrigin,
+    ) -> Vec<(SystemTime, Slug)> {
+        let mut creations: Vec<_> = self
+            .links
+            .values()
+            .filter(|s| s.origin() == origin)
+            .map(|s| (s.created_at, s.link.slug.clone()))
+            .collect();
+        creations.sort_by(|(a, x), (b, y)| (a, &x.0).cmp(&(b, &y.0)));
+        creations
+    }
+
+    /// Returns the total counts of redirects of the active [`ShortLink`]s per
+    /// the [`SlugOrigin`] of their [`Slug`]s, omitting the origins without any
+    /// active [`ShortLink`]s.
+    pub fn redirects_by_origin(&self) -> HashMap<SlugOrigin, u64> {
+        let mut totals = HashMap::new();
+        for state in self.active_links() {
+            let total: &mut u64 = totals.entry(state.origin()).or_default();
+            *total = total.saturating_add(state.redirects);
+        }
+        totals
+    }
+
+    /// Returns a page of up to `limit` [`ShortLink`]s created strictly before
+    /// the `before` cursor (or the newest ones, if [`None`]), the newest first
+    /// with ties broken by [`Slug`], along with the cursor of the next page, if
+    /// there are more [`ShortLink`]s.
+    ///
+    /// As the cursor is a creation moment, [`ShortLink`]s created at the same
+    /// moment are never split across pages: such a group is left for the next
+    /// page, or, if it doesn't fit into an empty page, is returned whole
+    /// exceeding the `limit`. A zero `limit` returns an empty page without a
+    /// cursor.
+    pub fn recent_links_page(
+        &self,
+        before: Option<SystemTime>,
+        limit: usize,
+    ) -> (Vec<ShortLink>, Option<SystemTime>) {
+        if limit == 0 {
+            return (Vec::new(), None);
+        }
+
+        let mut states: Vec<_> = self
+            .links
+            .values()
+            .filter(|state| before.is_none_or(|at| state.created_at < at))
+            .collect();
+        states.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.link.slug.0.cmp(&b.link.slug.0))
+        });
+
+        let mut len = limit.min(states.len());
+        if len < states.len() {
+            let boundary = states[len].created_at;
+            let group_start = states[..len]
+                .iter()
+                .position(|state| state.created_at == boundary)
+                .unwrap_or(len);
+            len = if group_start > 0 {
+                group_start
+            } else {
+                states
+                    .iter()
+                    .position(|state| state.created_at != boundary)
+                    .unwrap_or(states.len())
+            };
+        }
+
+        let next = (len < states.len()).then(|| states[len - 1].created_at);
+        let page = states[..len].iter().map(|s| self.link_of(s)).collect();
+        (page, next)
+    }
+
+    /// Returns the [`Slug`]s of the `n` least recently used active
+    /// [`ShortLink`]s, the least recently used first, being the ones a
+    /// least-recently-used eviction would evict.
+    ///
+    /// Activity of a [`ShortLink`] is its last redirect, or its creation if
+    /// it has never been redirected. Ties are resolved in favor of the
+    /// smallest [`Slug`].
+    pub fn eviction_candidates(&self, n: usize) -> Vec<Slug> {
+        let mut states: Vec<_> = self.active_links().collect();
+        states.sort_by(|a, b| {
+            let a_key = (a.last_activity(), &a.link.slug.0);
+            a_key.cmp(&(b.last_activity(), &b.link.slug.0))
+        });
+        states
+            .into_iter()
+            .take(n)
+            .map(|state| state.link.slug.clone())
+            .collect()
+    }
+
+    /// Returns the [`Slug`] of the [`ShortLink`] with the highest ratio of
+    /// redirects to distinct source tags, along with that ratio, or [`None`]
+    /// if there were no redirects at all.
+    ///
+    /// A [`ShortLink`] redirected without any source tags is treated as having
+    /// a single source. Ties are resolved in favor of the smallest [`Slug`].
+    pub fn most_concentrated_link(&self) -> Option<(Slug, f64)> {
+        self.links
+            .iter()
+            .filter(|(_, state)| state.redirects > 0)
+            .map(|(slug, state)| {
+                let sources = state.sources.len().max(1);
+                (slug, state.redirects as f64 / sources as f64)
+            })
+            .max_by(|(slug_a, a), (slug_b, b)| {
+                a.total_cmp(b).then_with(|| slug_b.0.cmp(&slug_a.0))
+            })
+            .map(|(slug, ratio)| (slug.clone(), ratio))
+    }
+}
+
+/// Digits of the [`encode_base62()`], in the order of their values.
+const BASE62_DIGITS: &[u8; 62] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Encodes the provided `id` (like a [`UrlShortenerService::link_id()`]) in
+/// base62, with the most significant digit first and no padding.
+pub fn encode_base62(mut id: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(BASE62_DIGITS[(id % 62) as usize]);
+        id /= 62;
+        if id == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Decodes the provided base62-encoded id (see [`encode_base62()`]).
+///
+/// Returns [`None`] if it's empty, contains a non-base62 digit, or overflows
+/// a [`u64`].
+pub fn decode_base62(encoded: &str) -> Option<u64> {
+    if encoded.is_empty() {
+        return None;
+    }
+    encoded.bytes().try_fold(0_u64, |id, b| {
+        let digit = BASE62_DIGITS.iter().position(|d| *d == b)?;
+        id.checked_mul(62)?.checked_add(digit as u64)
+    })
+}
+
+/// Returns the [`Slug`] of the provided one in the provided `namespace`, being
+/// prefixed with it and a `/`.
+///
+/// An empty `namespace` is the root one, having [`Slug`]s unprefixed.
+pub fn namespaced_slug(namespace: &str, slug: &Slug) -> Slug {
+    if namespace.is_empty() {
+        slug.clone()
+    } else {
+        Slug(format!("{namespace}/{}", slug.0))
+    }
+}
+
+/// Returns the pseudonym of the provided [`Slug`]: the hex-encoded 64-bit
+/// FNV-1a hash of it, stable across runs and platforms.
+fn pseudonym_of(slug: &Slug) -> Slug {
+    let hash = fnv1a(FNV_OFFSET_BASIS, slug.0.as_bytes());
+    Slug(format!("{hash:016x}"))
+}
+
+/// Initial value of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continues the 64-bit FNV-1a `hash` over the provided `bytes`.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Placeholders allowed in the [`Url`] of a template [`ShortLink`] (see
+/// [`UrlShortenerService::handle_create_template()`]).
+const TEMPLATE_PLACEHOLDERS: [&str; 2] = ["{slug}", "{ts}"];
+
+/// Checks whether the provided link `template` contains only the known
+/// placeholders.
+///
+/// ## Errors
+///
+/// [`ShortenerError::InvalidTemplate`] if it contains an unknown or unclosed
+/// placeholder.
+fn check_template(template: &str) -> Result<(), ShortenerError> {
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        rest = &rest[start..];
+        let known = TEMPLATE_PLACEHOLDERS.iter().find(|p| rest.starts_with(*p));
+        let Some(placeholder) = known else {
+            return Err(ShortenerError::InvalidTemplate);
+        };
+        rest = &rest[placeholder.len()..];
+    }
+    Ok(())
+}
+
+/// Expands the placeholders of the provided `template` [`Url`] (see
+/// [`UrlShortenerService::handle_create_template()`]) for a redirect through
+/// the [`ShortLink`] with the provided [`Slug`] at the provided moment.
+fn expand_template(template: &Url, slug: &Slug, at: SystemTime) -> Url {
+    let ts = at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Url(template
+        .0
+        .replace("{slug}", &slug.0)
+        .replace("{ts}", &ts.to_string()))
+}
+
+/// Returns the Levenshtein distance between the provided strings of
+/// characters: the minimum count of single-character insertions, deletions
+/// and substitutions turning one into the other.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Checks whether the provided HTTP `status` is a redirect one.
+///
+/// ## Errors
+///
+/// [`ShortenerError::InvalidRedirectStatus`] if it's not.
+fn check_redirect_status(status: u16) -> Result<(), ShortenerError> {
+    match status {
+        301 | 302 | 303 | 307 | 308 => Ok(()),
+        _ => Err(ShortenerError::InvalidRedirectStatus),
+    }
+}
+
+/// Indicates whether the provided redirect sample `rate` is in `0.0..=1.0`
+/// range and non-zero.
+fn is_sample_rate(rate: f64) -> bool {
+    rate > 0.0 && rate <= 1.0
+}
+
+/// Parses the `[dd/Mon/yyyy:HH:MM:SS +hhmm]` timestamp out of the provided
+/// whitespace-delimited access log line `fields`, if there is any.
+fn parse_access_log_time(fields: &[&str]) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+        "Nov", "Dec",
+    ];
+
+    let pos = fields.iter().position(|f| f.starts_with('['))?;
+    let datetime = fields[pos].strip_prefix('[')?;
+    let offset = fields.get(pos + 1)?.strip_suffix(']')?;
+
+    let (date, time) = datetime.split_once(':')?;
+    let mut date = date.split('/');
+    let day: u32 = date.next()?.parse().ok()?;
+    let month = date.next()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u32 + 1;
+    let year: i64 = date.next()?.parse().ok()?;
+
+    let mut time = time.split(':').map(|t| t.parse::<i64>().ok());
+    let (h, m, s) = (time.next()??, time.next()??, time.next()??);
+
+    let sign = match offset.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let offset: i64 = offset.get(1..)?.parse().ok()?;
+    let offset = sign * ((offset / 100) * 3600 + (offset % 100) * 60);
+
+    let secs =
+        days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + s
+            - offset;
+    let secs = u64::try_from(secs).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Returns the number of days since the Unix epoch of the provided civil
+/// (proleptic Gregorian) date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = i64::from(month);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5
+        + i64::from(day)
+        - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Returns the day (as the number of days since the Unix epoch) the provided
+/// moment falls on in the time zone with the provided UTC offset.
+fn day_of(at: SystemTime, utc_offset_minutes: i32) -> i64 {
+    let secs = match at.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    (secs + i64::from(utc_offset_minutes) * 60).div_euclid(86_400)
+}
+
+fn main() {
+    let mut service = UrlShortenerService::new();
+
+    // Create a short link with a random slug
+    let url = Url("https://original-url.com".to_string());
+    let short_link = service
+        .handle_create_short_link(url.clone(), None)
+        .expect("Failed to create short link");
+
+    // Create a short link with a predefined slug
+    let slug = Slug("custom.xyz".to_string());
+    let custom_link = service
+        .handle_create_short_link(url.clone(), Some(slug.clone()))
+        .expect("Failed to create short link with custom slug");
+
+    // Change the URL for an existing short link
+    let new_url = Url("https://new-original-url.com".to_string());
+    let updated_link = service
+        .handle_change_short_link(slug.clone(), new_url.clone())
+        .expect("Failed to change the URL for the short link");
+
+    // Redirect using a slug
+    let redirected_link = service
+        .handle_redirect(slug.clone())
+        .expect("Failed to redirect using the slug");
+
+    // Get stats for the link
+    let stats = service.get_stats(slug.clone()).expect("Failed to get stats");
+
+    println!("Short link: {:?}", short_link);
+    println!("Custom link: {:?}", custom_link);
+    println!("Updated link: {:?}", updated_link);
+    println!("Redirected link: {:?}", redirected_link);
+    println!("Stats: {:?}", stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::VecEventSink;
+    use super::*;
+
+    /// Creates a [`Url`] out of the provided string.
+    fn url(url: &str) -> Url {
+        Url(url.into())
+    }
+
+    #[test]
+    fn error_codes_are_stable() {
+        let codes = [
+            (ShortenerError::InvalidUrl, "invalid_url"),
+            (ShortenerError::SlugAlreadyInUse, "slug_already_in_use"),
+            (ShortenerError::SlugNotFound, "slug_not_found"),
+            (ShortenerError::SequenceGap, "sequence_gap"),
+            (ShortenerError::UrlAlreadyInUse, "url_already_in_use"),
+            (ShortenerError::LinkDisabled, "link_disabled"),
+            (ShortenerError::LinkExpired, "link_expired"),
+            (ShortenerError::InvalidSplitWeights, "invalid_split_weights"),
+            (ShortenerError::CounterOverflow, "counter_overflow"),
+            (ShortenerError::CountryBlocked, "country_blocked"),
+            (ShortenerError::PreconditionFailed, "precondition_failed"),
+            (
+                ShortenerError::InvalidRedirectStatus,
+                "invalid_redirect_status",
+            ),
+            (ShortenerError::InvalidInterval, "invalid_interval"),
+            (ShortenerError::RefererNotAllowed, "referer_not_allowed"),
+            (ShortenerError::LogNotSplittable, "log_not_splittable"),
+            (ShortenerError::RedirectLoop, "redirect_loop"),
+            (ShortenerError::ChangeLimitExceeded, "change_limit_exceeded"),
+            (ShortenerError::NotYetActive, "not_yet_active"),
+            (ShortenerError::InvalidSampleRate, "invalid_sample_rate"),
+            (ShortenerError::SlugFormatReserved, "slug_format_reserved"),
+            (ShortenerError::InvalidTemplate, "invalid_template"),
+            (ShortenerError::TtlOutOfBounds, "ttl_out_of_bounds"),
+            (ShortenerError::AliasLimitExceeded, "alias_limit_exceeded"),
+        ];
+        for (error, code) in codes {
+            // Exhaustive, so a new variant doesn't compile until listed above.
+            match error {
+                ShortenerError::InvalidUrl
+                | ShortenerError::SlugAlreadyInUse
+                | ShortenerError::SlugNotFound
+                | ShortenerError::SequenceGap
+                | ShortenerError::UrlAlreadyInUse
+                | ShortenerError::LinkDisabled
+                | ShortenerError::LinkExpired
+                | ShortenerError::InvalidSplitWeights
+                | ShortenerError::CounterOverflow
+                | ShortenerError::CountryBlocked
+                | ShortenerError::PreconditionFailed
+                | ShortenerError::InvalidRedirectStatus
+                | ShortenerError::InvalidInterval
+                | ShortenerError::RefererNotAllowed
+                | ShortenerError::LogNotSplittable
+                | ShortenerError::RedirectLoop
+                | ShortenerError::ChangeLimitExceeded
+                | ShortenerError::NotYetActive
+                | ShortenerError::InvalidSampleRate
+                | ShortenerError::SlugFormatReserved
+                | ShortenerError::InvalidTemplate
+                | ShortenerError::TtlOutOfBounds
+                | ShortenerError::AliasLimitExceeded => {}
+            }
+            assert_eq!(error.code(), code);
+        }
+    }
+
+    #[test]
+    fn event_sink_receives_every_event_in_order() {
+        let sink = VecEventSink::new();
+        let mut service =
+            UrlShortenerService::new().with_event_sink(sink.clone());
+        let link = service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(link.slug.clone()).unwrap();
+        service
+            .handle_change_short_link(link.slug, url("https://b.com"))
+            .unwrap();
+
+        assert_eq!(sink.events(), service.events());
+        assert_eq!(sink.events().len(), 3);
+    }
+
+    #[test]
+    fn event_sink_receives_nothing_on_failed_commands() {
+        let sink = VecEventSink::new();
+        let mut service =
+            UrlShortenerService::new().with_event_sink(sink.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        let taken = service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("a")));
+        let missing = service.handle_redirect(slug!("missing"));
+        let invalid = service.handle_create_short_link(url("not a url"), None);
+
+        assert_eq!(taken, Err(ShortenerError::SlugAlreadyInUse));
+        assert_eq!(missing, Err(ShortenerError::SlugNotFound));
+        assert_eq!(invalid, Err(ShortenerError::InvalidUrl));
+        assert_eq!(sink.events().len(), 1);
+    }
+
+    #[test]
+    fn redirect_percentiles_of_known_distribution() {
+        let mut service = UrlShortenerService::new();
+        for count in 1..=10 {
+            let slug = Slug(format!("s{count}"));
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            for _ in 0..count {
+                service.handle_redirect(slug.clone()).unwrap();
+            }
+        }
+
+        let percentiles = service.redirect_percentiles(&[0.5, 0.9, 0.0, 1.0]);
+
+        assert_eq!(percentiles, Ok(vec![6, 9, 1, 10]));
+    }
+
+    #[test]
+    fn redirect_percentiles_skip_inactive_links() {
+        let mut service = UrlShortenerService::new();
+        for (slug, count) in [(slug!("a"), 1), (slug!("b"), 100)] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            for _ in 0..count {
+                service.handle_redirect(slug.clone()).unwrap();
+            }
+        }
+        service.handle_disable(slug!("b")).unwrap();
+
+        assert_eq!(service.redirect_percentiles(&[0.5, 1.0]), Ok(vec![1, 1]));
+        assert_eq!(service.mean_redirects_per_link(), 1.0);
+    }
+
+    #[test]
+    fn redirect_percentiles_reject_out_of_range() {
+        let service = UrlShortenerService::new();
+
+        for percentiles in [[0.5, 1.5], [-0.1, 0.5]] {
+            assert_eq!(
+                service.redirect_percentiles(&percentiles),
+                Err(ShortenerError::InvalidInterval),
+            );
+        }
+    }
+
+    #[test]
+    fn replica_converges_by_applying_changes() {
+        let mut primary = UrlShortenerService::new();
+        let mut replica = UrlShortenerService::new();
+        primary
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        replica
+            .apply_changes(&primary.changes_since(replica.seq()))
+            .unwrap();
+        primary.handle_redirect(slug!("a")).unwrap();
+        primary
+            .handle_change_short_link(slug!("a"), url("https://b.com"))
+            .unwrap();
+
+        let changes = primary.changes_since(replica.seq());
+        assert_eq!(changes.len(), 2);
+        replica.apply_changes(&changes).unwrap();
+
+        assert_eq!(replica.seq(), primary.seq());
+        assert_eq!(
+            replica.get_stats(slug!("a")),
+            primary.get_stats(slug!("a"))
+        );
+    }
+
+    #[test]
+    fn replica_rejects_gaps() {
+        let mut primary = UrlShortenerService::new();
+        let mut replica = UrlShortenerService::new();
+        primary
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        primary.handle_redirect(slug!("a")).unwrap();
+
+        let changes = primary.changes_since(0);
+
+        assert_eq!(
+            replica.apply_changes(&changes[1..]),
+            Err(ShortenerError::SequenceGap),
+        );
+        assert_eq!(replica.seq(), 0);
+    }
+
+    #[test]
+    fn most_concentrated_link_picks_highest_ratio() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.most_concentrated_link(), None);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        for source in ["x", "y", "z", "x"] {
+            service
+                .handle_redirect_with_source(slug!("a"), source.into())
+                .unwrap();
+        }
+        for source in ["x", "x", "x"] {
+            service
+                .handle_redirect_with_source(slug!("b"), source.into())
+                .unwrap();
+        }
+
+        assert_eq!(service.most_concentrated_link(), Some((slug!("b"), 3.0)));
+    }
+
+    #[test]
+    fn most_concentrated_link_counts_anonymous_clicks_as_one_source() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.most_concentrated_link(), Some((slug!("a"), 2.0)));
+    }
+
+    #[test]
+    fn public_hostname_rejects_ip_and_single_label_hosts() {
+        let mut service =
+            UrlShortenerService::new().with_require_public_hostname(true);
+        for rejected in [
+            "http://127.0.0.1/",
+            "http://localhost/",
+            "http://[::1]:80/x",
+            "http://user@localhost:8080",
+        ] {
+            assert_eq!(
+                service.handle_create_short_link(url(rejected), None),
+                Err(ShortenerError::InvalidUrl),
+                "{rejected}",
+            );
+        }
+
+        service
+            .handle_create_short_link(url("http://example.com/"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn public_hostname_is_not_required_by_default() {
+        let mut service = UrlShortenerService::new();
+
+        service
+            .handle_create_short_link(url("http://localhost/"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn metadata_batch_is_positional() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_ttl(slug!("a"), Some(Duration::from_secs(60)))
+            .unwrap();
+        service
+            .handle_set_note(slug!("a"), Some("hi".into()))
+            .unwrap();
+        clock.advance(Duration::from_secs(10));
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_disable(slug!("a")).unwrap();
+
+        let batch = service.metadata_batch(&[slug!("missing"), slug!("a")]);
+
+        assert_eq!(batch[0], Err(ShortenerError::SlugNotFound));
+        let metadata = batch[1].clone().unwrap();
+        assert_eq!(metadata.created_at, SystemTime::UNIX_EPOCH);
+        assert_eq!(metadata.redirects, 1);
+        assert_eq!(
+            metadata.last_redirect,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(10)),
+        );
+        assert!(!metadata.enabled);
+        assert_eq!(metadata.ttl_remaining, Some(Duration::from_secs(50)));
+        assert_eq!(metadata.note.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn unlogged_redirects_do_not_grow_the_log() {
+        let mut service = UrlShortenerService::new().with_log_redirects(false);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for _ in 0..5 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        assert_eq!(service.events().len(), 1);
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 5);
+    }
+
+    #[test]
+    fn unlogged_redirects_survive_checkpoints_and_compaction() {
+        let mut service = UrlShortenerService::new().with_log_redirects(false);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for _ in 0..5 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        service.checkpoint_redirects();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.get_stats(slug!("a")).unwrap().redirects, 5);
+
+        service.compact();
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.get_stats(slug!("a")).unwrap().redirects, 6);
+    }
+
+    #[test]
+    fn same_target_compares_normalized_urls() {
+        let mut service = UrlShortenerService::new().with_normalize_urls(true);
+        for (slug, target) in [
+            (slug!("a"), "https://A.com"),
+            (slug!("b"), "https://a.com:443/"),
+            (slug!("c"), "https://b.com/"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(service.same_target(&slug!("a"), &slug!("b")), Ok(true));
+        assert_eq!(service.same_target(&slug!("a"), &slug!("c")), Ok(false));
+        assert_eq!(
+            service.same_target(&slug!("a"), &slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn import_access_log_counts_redirects() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("abc")))
+            .unwrap();
+        let log = "\
+127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /abc HTTP/1.0\" 301 0
+127.0.0.1 - - [10/Oct/2000:13:55:37 -0700] \"GET /abc?x=1 HTTP/1.0\" 301 0
+127.0.0.1 - - [10/Oct/2000:13:55:37 -0700] \"GET /zzz HTTP/1.0\" 301 0
+short
+
+";
+
+        let report = service.handle_import_access_log(log, 6);
+
+        assert_eq!(
+            report,
+            ImportReport {
+                imported: 2,
+                unknown_slugs: 1,
+                parse_errors: 1,
+            },
+        );
+        let metadata = service.metadata(&slug!("abc")).unwrap();
+        assert_eq!(metadata.redirects, 2);
+        assert_eq!(
+            metadata.last_redirect,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(971_211_337)),
+        );
+    }
+
+    #[test]
+    fn slug_length_histogram_counts_links_per_length() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["abc", "abcdef", "uvwxyz", "abcdefgh"] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(Slug(slug.into())),
+                )
+                .unwrap();
+        }
+
+        let histogram = service.slug_length_histogram();
+
+        assert_eq!(histogram, BTreeMap::from([(3, 1), (6, 2), (8, 1)]));
+    }
+
+    #[test]
+    fn suspended_notifications_are_flushed_on_resume() {
+        let sink = VecEventSink::new();
+        let mut service =
+            UrlShortenerService::new().with_event_sink(sink.clone());
+        service.suspend_notifications();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert!(sink.events().is_empty());
+        assert_eq!(service.events().len(), 2);
+
+        service.resume_notifications();
+        assert_eq!(sink.events(), service.events());
+
+        service.handle_redirect(slug!("a")).unwrap();
+        assert_eq!(sink.events().len(), 3);
+    }
+
+    #[test]
+    fn retarget_url_changes_only_matching_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://old.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_create_short_link(
+                url("https://other.com"),
+                Some(slug!("d")),
+            )
+            .unwrap();
+
+        let changed = service
+            .handle_retarget_url(
+                &url("https://old.com"),
+                url("https://new.com"),
+            )
+            .unwrap();
+
+        assert_eq!(changed, 3);
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            let stats = service.get_stats(slug).unwrap();
+            assert_eq!(stats.link.url, url("https://new.com"));
+        }
+        let stats = service.get_stats(slug!("d")).unwrap();
+        assert_eq!(stats.link.url, url("https://other.com"));
+        assert_eq!(service.events().len(), 7);
+    }
+
+    #[test]
+    fn retarget_url_validates_the_new_url() {
+        let mut service =
+            UrlShortenerService::new().with_require_public_hostname(true);
+        service
+            .handle_create_short_link(url("https://old.com"), Some(slug!("a")))
+            .unwrap();
+
+        let retargeted = service.handle_retarget_url(
+            &url("https://old.com"),
+            url("https://localhost"),
+        );
+
+        assert_eq!(retargeted, Err(ShortenerError::InvalidUrl));
+        assert_eq!(service.events().len(), 1);
+    }
+
+    #[test]
+    fn resolve_cache_serves_current_urls() {
+        let mut service = UrlShortenerService::new().with_resolve_cache(2);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+        assert_eq!(
+            service.resolve(&slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn resolve_cache_is_invalidated_on_change() {
+        let mut service = UrlShortenerService::new().with_resolve_cache(2);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+
+        service
+            .handle_change_short_link(slug!("a"), url("https://b.com"))
+            .unwrap();
+
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://b.com")));
+    }
+
+    #[test]
+    fn one_time_link_is_consumed_by_first_redirect() {
+        let mut service = UrlShortenerService::new();
+        let link = service
+            .handle_create_one_time(url("https://a.com"), None)
+            .unwrap();
+
+        assert_eq!(
+            service.handle_redirect(link.slug.clone()),
+            Ok(link.clone())
+        );
+        assert_eq!(service.get_stats(link.slug.clone()).unwrap().redirects, 1);
+        assert_eq!(
+            service.handle_redirect(link.slug.clone()),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn one_time_link_stays_consumed_after_replay() {
+        let mut service = UrlShortenerService::new();
+        let link = service
+            .handle_create_one_time(url("https://a.com"), None)
+            .unwrap();
+        service.handle_redirect(link.slug.clone()).unwrap();
+        service.compact();
+
+        let mut replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert_eq!(
+            replayed.handle_redirect(link.slug),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn trailing_slash_slugs_are_equivalent_when_trimmed() {
+        let mut service =
+            UrlShortenerService::new().with_trim_trailing_slash_slugs(true);
+        let link = service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("abc/")))
+            .unwrap();
+        assert_eq!(link.slug, slug!("abc"));
+
+        service.handle_redirect(slug!("abc/")).unwrap();
+        service.handle_redirect(slug!("abc")).unwrap();
+
+        assert_eq!(service.get_stats(slug!("abc/")).unwrap().redirects, 2);
+        assert_eq!(
+            service.handle_create_short_link(
+                url("https://b.com"),
+                Some(slug!("abc"))
+            ),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn trailing_slash_slugs_are_distinct_by_default() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("abc/")))
+            .unwrap();
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("abc")))
+            .unwrap();
+
+        assert_eq!(service.resolve(&slug!("abc/")), Ok(url("https://a.com")));
+        assert_eq!(service.resolve(&slug!("abc")), Ok(url("https://b.com")));
+    }
+
+    #[test]
+    fn link_timeline_lists_lifecycle_in_order() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        clock.advance(Duration::from_secs(1));
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(1));
+        service.handle_disable(slug!("a")).unwrap();
+        service
+            .handle_change_short_link(slug!("a"), url("https://b.com"))
+            .unwrap();
+
+        let timeline = service.link_timeline(slug!("a")).unwrap();
+
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(
+            timeline,
+            [
+                (at(0), "created pointing to https://a.com".into()),
+                (at(1), "redirected 2 time(s)".into()),
+                (at(2), "disabled".into()),
+                (at(2), "changed to point to https://b.com".into()),
+            ],
+        );
+    }
+
+    #[test]
+    fn obfuscated_urls_round_trip() {
+        let mut service =
+            UrlShortenerService::new().with_url_obfuscation(b"k3y");
+        let target = url("https://a.com/ü");
+        service
+            .handle_create_short_link(target.clone(), Some(slug!("a")))
+            .unwrap();
+
+        assert_ne!(service.links[&slug!("a")].link.url, target);
+        assert_eq!(service.handle_redirect(slug!("a")).unwrap().url, target);
+        assert_eq!(service.resolve(&slug!("a")), Ok(target));
+    }
+
+    #[test]
+    fn eviction_candidates_are_least_recently_used_first() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.eviction_candidates(2), [slug!("b"), slug!("c")]);
+        assert_eq!(
+            service.eviction_candidates(5),
+            [slug!("b"), slug!("c"), slug!("a")],
+        );
+    }
+
+    #[test]
+    fn unique_urls_fold_host_case_when_enabled() {
+        let mut service = UrlShortenerService::new()
+            .with_unique_urls(true)
+            .with_unique_url_host_case_insensitive(true);
+        let link = service
+            .handle_create_short_link(url("http://Example.com/Path"), None)
+            .unwrap();
+
+        assert_eq!(link.url, url("http://Example.com/Path"));
+        assert_eq!(
+            service
+                .handle_create_short_link(url("http://example.com/Path"), None),
+            Err(ShortenerError::UrlAlreadyInUse),
+        );
+        service
+            .handle_create_short_link(url("http://example.com/path"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn unique_urls_compare_host_case_by_default() {
+        let mut service = UrlShortenerService::new().with_unique_urls(true);
+        service
+            .handle_create_short_link(url("http://Example.com"), None)
+            .unwrap();
+
+        service
+            .handle_create_short_link(url("http://example.com"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn retarget_url_keeps_urls_unique() {
+        let mut service = UrlShortenerService::new()
+            .with_unique_urls(true)
+            .with_unique_url_host_case_insensitive(true);
+        for (slug, target) in
+            [(slug!("a"), "https://a.com"), (slug!("b"), "https://b.com")]
+        {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+
+        let retargeted = service
+            .handle_retarget_url(&url("https://a.com"), url("https://B.com"));
+
+        assert_eq!(retargeted, Err(ShortenerError::UrlAlreadyInUse));
+        assert_eq!(
+            service.handle_retarget_url(
+                &url("https://a.com"),
+                url("https://A.com")
+            ),
+            Ok(1),
+        );
+    }
+
+    #[test]
+    fn recent_links_pages_have_no_duplicates_or_gaps() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        for slug in ["a", "b", "c", "d", "e"] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(Slug(slug.into())),
+                )
+                .unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+        for slug in [slug!("g"), slug!("f")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = service.recent_links_page(cursor, 2);
+            seen.extend(page.into_iter().map(|link| link.slug));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let expected: Vec<_> = ["f", "g", "e", "d", "c", "b", "a"]
+            .into_iter()
+            .map(|slug| Slug(slug.into()))
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn recent_links_page_of_zero_is_empty() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(service.recent_links_page(None, 0), (Vec::new(), None));
+    }
+
+    #[test]
+    fn move_namespace_preserves_stats() {
+        let mut service = UrlShortenerService::new();
+        let slug = slug!("a");
+        service
+            .handle_create_short_link(
+                url("https://a.com"),
+                Some(namespaced_slug("x", &slug)),
+            )
+            .unwrap();
+        service.handle_redirect(slug!("x/a")).unwrap();
+
+        let link = service.handle_move_namespace(&slug, "x", "y").unwrap();
+
+        assert_eq!(link.slug, slug!("y/a"));
+        assert_eq!(
+            service.resolve(&slug!("x/a")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.get_stats(slug!("y/a")).unwrap().redirects, 1);
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.get_stats(slug!("y/a")).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn move_namespace_rejects_missing_and_taken_slugs() {
+        let mut service = UrlShortenerService::new();
+        let slug = slug!("a");
+        for namespace in ["x", "z"] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(namespaced_slug(namespace, &slug)),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.handle_move_namespace(&slug, "x", "z"),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+        assert_eq!(
+            service.handle_move_namespace(&slug, "q", "y"),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.events().len(), 2);
+    }
+
+    #[test]
+    fn move_namespace_rejects_aliases() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("b")))
+            .unwrap();
+        service.handle_add_alias(slug!("b"), slug!("x/a")).unwrap();
+
+        assert_eq!(
+            service.handle_move_namespace(&slug!("a"), "x", "y"),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.resolve(&slug!("b")), Ok(url("https://a.com")));
+        assert_eq!(service.events().len(), 2);
+    }
+
+    #[test]
+    fn fresh_service_is_healthy() {
+        let health = UrlShortenerService::new().health();
+
+        assert!(health.consistency_ok);
+        assert_eq!(health.total_links, 0);
+        assert_eq!(health.active_links, 0);
+        assert_eq!(health.event_log_len, 0);
+        assert_eq!(health.expired_pending_cleanup, 0);
+    }
+
+    #[test]
+    fn health_reflects_operations() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_disable(slug!("b")).unwrap();
+        service
+            .handle_set_ttl(slug!("c"), Some(Duration::ZERO))
+            .unwrap();
+
+        let health = service.health();
+
+        assert!(health.consistency_ok);
+        assert_eq!(health.total_links, 3);
+        assert_eq!(health.active_links, 1);
+        assert_eq!(health.event_log_len, 5);
+        assert_eq!(health.expired_pending_cleanup, 1);
+    }
+
+    #[test]
+    fn split_links_pick_variants_deterministically() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let run = |seed| {
+            let mut service = UrlShortenerService::new()
+                .with_rng(StdRng::seed_from_u64(seed));
+            service
+                .handle_create_split(
+                    slug!("ab"),
+                    vec![
+                        (url("https://a.com"), 3),
+                        (url("https://b.com"), 1),
+                        (url("https://c.com"), 0),
+                    ],
+                )
+                .unwrap();
+            let served: Vec<_> = (0..20)
+                .map(|_| service.handle_redirect(slug!("ab")).unwrap().url)
+                .collect();
+            (served, service.variant_stats(slug!("ab")).unwrap())
+        };
+
+        let (served, stats) = run(7);
+
+        assert_eq!(run(7), (served, stats.clone()));
+        assert_eq!(stats.iter().map(|(_, count)| count).sum::<u64>(), 20);
+        assert_eq!(stats[2], (url("https://c.com"), 0));
+    }
+
+    #[test]
+    fn split_variant_stats_survive_replay() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_split(
+                slug!("ab"),
+                vec![(url("https://a.com"), 1), (url("https://b.com"), 1)],
+            )
+            .unwrap();
+        for _ in 0..5 {
+            service.handle_redirect(slug!("ab")).unwrap();
+        }
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert_eq!(
+            replayed.variant_stats(slug!("ab")),
+            service.variant_stats(slug!("ab")),
+        );
+    }
+
+    #[test]
+    fn split_weights_must_sum_above_zero() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_create_split(
+                slug!("x"),
+                vec![(url("https://a.com"), 0)]
+            ),
+            Err(ShortenerError::InvalidSplitWeights),
+        );
+        assert_eq!(
+            service.handle_create_split(slug!("x"), Vec::new()),
+            Err(ShortenerError::InvalidSplitWeights),
+        );
+    }
+
+    #[test]
+    fn case_collisions_group_slugs_differing_by_case() {
+        let mut service = UrlShortenerService::new();
+        assert!(service.case_collisions().is_empty());
+        for slug in [slug!("promo"), slug!("Promo"), slug!("other")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.case_collisions(),
+            [[slug!("Promo"), slug!("promo")]],
+        );
+    }
+
+    #[test]
+    fn events_record_normalized_urls() {
+        let mut service = UrlShortenerService::new().with_normalize_urls(true);
+        let link = service
+            .handle_create_short_link(url("HTTPS://A.com:443"), None)
+            .unwrap();
+        service
+            .handle_change_short_link(link.slug.clone(), url("HTTP://B.com:80"))
+            .unwrap();
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert!(matches!(
+            &service.events()[0],
+            Event::ShortLinkCreated { url, .. } if url.0 == "https://a.com/"
+        ));
+        assert_eq!(replayed.resolve(&link.slug), Ok(url("http://b.com/")));
+    }
+
+    #[test]
+    fn consolidate_duplicates_folds_into_smallest_slug() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("c"), slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://x.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_create_short_link(url("https://y.com"), Some(slug!("d")))
+            .unwrap();
+        service.handle_redirect(slug!("b")).unwrap();
+        service.handle_redirect(slug!("c")).unwrap();
+        service.handle_redirect(slug!("c")).unwrap();
+
+        let merged = service.consolidate_duplicates();
+
+        assert_eq!(merged, [(slug!("a"), vec![slug!("b"), slug!("c")])]);
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 3);
+        assert_eq!(service.redirect_hits(slug!("a")), Ok(3));
+        assert_eq!(
+            service.handle_redirect(slug!("c")).unwrap().slug,
+            slug!("a")
+        );
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 4);
+        assert_eq!(service.redirect_hits(slug!("a")), Ok(4));
+        assert_eq!(service.resolve(&slug!("d")), Ok(url("https://y.com")));
+    }
+
+    #[test]
+    fn consolidated_duplicates_survive_replay() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://x.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("b")).unwrap();
+        service.consolidate_duplicates();
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert!(service.is_consistent());
+        assert_eq!(replayed.get_stats(slug!("b")).unwrap().redirects, 1);
+        assert_eq!(replayed.health().total_links, 1);
+    }
+
+    #[test]
+    fn reported_creation_counts_generation_attempts() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut service = UrlShortenerService::new()
+            .with_slug_length(1)
+            .with_slug_generation_attempts(1000)
+            .with_rng(StdRng::seed_from_u64(1));
+
+        let attempts = (0..60)
+            .map(|i| {
+                let target = url(&format!("https://{i}.com"));
+                service.handle_create_short_link_reported(target).unwrap().1
+            })
+            .max();
+
+        assert!(attempts > Some(1));
+    }
+
+    #[test]
+    fn reported_creation_takes_one_attempt_in_empty_keyspace() {
+        let mut service = UrlShortenerService::new();
+
+        let (_, attempts) = service
+            .handle_create_short_link_reported(url("https://a.com"))
+            .unwrap();
+
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn empty_keyspace_has_full_headroom() {
+        assert_eq!(UrlShortenerService::new().keyspace_headroom(), 1.0);
+    }
+
+    #[test]
+    fn half_full_keyspace_has_half_headroom() {
+        let mut service = UrlShortenerService::new()
+            .with_slug_length(1)
+            .with_slug_generation_attempts(10_000);
+        for i in 0..31 {
+            service
+                .handle_create_short_link(
+                    url(&format!("https://{i}.com")),
+                    None,
+                )
+                .unwrap();
+        }
+
+        assert!((service.keyspace_headroom() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inactive_links_still_take_keyspace() {
+        let mut service = UrlShortenerService::new().with_slug_length(1);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_disable(slug!("a")).unwrap();
+
+        assert!((service.keyspace_headroom() - 61.0 / 62.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn links_in_creation_order_follow_the_log() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("m"), slug!("z"), slug!("a"), slug!("q")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_move_namespace(&slug!("z"), "", "ns")
+            .unwrap();
+        service.handle_delete(slug!("q")).unwrap();
+
+        let slugs: Vec<_> = service
+            .links_in_creation_order()
+            .into_iter()
+            .map(|link| link.slug)
+            .collect();
+
+        assert_eq!(slugs, [slug!("m"), slug!("ns/z"), slug!("a")]);
+    }
+
+    #[test]
+    fn geo_redirects_honor_the_allowlist() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect_geo(slug!("a"), "FR").unwrap();
+        service
+            .handle_set_allowed_countries(
+                slug!("a"),
+                vec!["US".into(), "de".into()],
+            )
+            .unwrap();
+
+        service.handle_redirect_geo(slug!("a"), "DE").unwrap();
+        assert_eq!(
+            service.handle_redirect_geo(slug!("a"), "FR"),
+            Err(ShortenerError::CountryBlocked),
+        );
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn geo_allowlist_survives_replay() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_allowed_countries(slug!("a"), vec!["US".into()])
+            .unwrap();
+
+        let mut replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert_eq!(
+            replayed.handle_redirect_geo(slug!("a"), "FR"),
+            Err(ShortenerError::CountryBlocked),
+        );
+    }
+
+    #[test]
+    fn redirect_counter_saturates_instead_of_wrapping() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_seed_redirects(slug!("a"), u64::MAX - 1)
+            .unwrap();
+
+        assert_eq!(
+            service.handle_seed_redirects(slug!("a"), 2),
+            Err(ShortenerError::CounterOverflow),
+        );
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, u64::MAX);
+        assert!(service.is_consistent());
+    }
+
+    /// Creates links `a`, `b` and `c` with `campaign` and `owner` metadata.
+    fn service_with_metadata() -> UrlShortenerService {
+        let mut service = UrlShortenerService::new();
+        for (slug, campaign, owner) in [
+            (slug!("b"), "spring", Some("ann")),
+            (slug!("a"), "spring", None),
+            (slug!("c"), "fall", Some("bob")),
+        ] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service
+                .handle_set_metadata(
+                    slug.clone(),
+                    "campaign".into(),
+                    Some(campaign.into()),
+                )
+                .unwrap();
+            if let Some(owner) = owner {
+                service
+                    .handle_set_metadata(
+                        slug,
+                        "owner".into(),
+                        Some(owner.into()),
+                    )
+                    .unwrap();
+            }
+        }
+        service
+    }
+
+    #[test]
+    fn links_with_metadata_match_key_and_value() {
+        let service = service_with_metadata();
+
+        assert_eq!(
+            service.links_with_metadata("campaign", Some("spring")),
+            [slug!("a"), slug!("b")],
+        );
+        assert!(service
+            .links_with_metadata("campaign", Some("winter"))
+            .is_empty());
+    }
+
+    #[test]
+    fn links_with_metadata_match_any_value() {
+        let mut service = service_with_metadata();
+        assert_eq!(
+            service.links_with_metadata("owner", None),
+            [slug!("b"), slug!("c")],
+        );
+
+        service
+            .handle_set_metadata(slug!("b"), "owner".into(), None)
+            .unwrap();
+
+        assert_eq!(service.links_with_metadata("owner", None), [slug!("c")]);
+    }
+
+    #[test]
+    fn conditional_change_applies_when_metadata_matches() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_metadata(slug!("a"), "env".into(), Some("prod".into()))
+            .unwrap();
+
+        let link = service
+            .handle_change_if_metadata(
+                slug!("a"),
+                url("https://b.com"),
+                "env",
+                "prod",
+            )
+            .unwrap();
+
+        assert_eq!(link.url, url("https://b.com"));
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://b.com")));
+    }
+
+    #[test]
+    fn conditional_change_fails_when_metadata_differs() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_metadata(slug!("a"), "env".into(), Some("prod".into()))
+            .unwrap();
+
+        assert_eq!(
+            service.handle_change_if_metadata(
+                slug!("a"),
+                url("https://b.com"),
+                "env",
+                "dev",
+            ),
+            Err(ShortenerError::PreconditionFailed),
+        );
+        assert_eq!(
+            service.handle_change_if_metadata(
+                slug!("a"),
+                url("https://b.com"),
+                "owner",
+                "prod",
+            ),
+            Err(ShortenerError::PreconditionFailed),
+        );
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+        assert_eq!(service.events().len(), 2);
+    }
+
+    #[test]
+    fn daily_new_links_bucket_creations_per_day() {
+        const DAY: u64 = 86_400;
+        let clock = clock::MockClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(DAY * 10 + 3600),
+        );
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        let mut create = |count| {
+            for _ in 0..count {
+                service
+                    .handle_create_short_link(url("https://a.com"), None)
+                    .unwrap();
+            }
+        };
+        create(2);
+        clock.advance(Duration::from_secs(DAY));
+        create(1);
+        clock.advance(Duration::from_secs(DAY * 2));
+        create(3);
+
+        assert_eq!(service.daily_new_links(0), [(10, 2), (11, 1), (13, 3)]);
+        assert_eq!(service.daily_new_links(-120), [(9, 2), (10, 1), (12, 3)]);
+    }
+
+    #[test]
+    fn validate_batch_flags_collisions_without_mutating() {
+        let mut service = UrlShortenerService::new()
+            .with_trim_trailing_slash_slugs(true)
+            .with_require_public_hostname(true);
+        service
+            .handle_create_short_link(
+                url("https://a.com"),
+                Some(slug!("taken")),
+            )
+            .unwrap();
+        let items = [
+            (url("https://b.com"), Some(slug!("x"))),
+            (url("https://c.com"), Some(slug!("x/"))),
+            (url("https://d.com"), Some(slug!("taken"))),
+            (url("https://localhost"), Some(slug!("y"))),
+            (url("https://e.com"), None),
+            (url("https://f.com"), Some(slug!("y"))),
+        ];
+
+        let results = service.validate_batch(&items);
+
+        assert_eq!(
+            results,
+            [
+                Ok(()),
+                Err(ShortenerError::SlugAlreadyInUse),
+                Err(ShortenerError::SlugAlreadyInUse),
+                Err(ShortenerError::InvalidUrl),
+                Ok(()),
+                Err(ShortenerError::SlugAlreadyInUse),
+            ],
+        );
+        assert_eq!(service.events().len(), 1);
+    }
+
+    #[test]
+    fn validate_batch_checks_unique_urls_within_batch() {
+        let service = UrlShortenerService::new().with_unique_urls(true);
+
+        let results = service.validate_batch(&[
+            (url("https://a.com"), None),
+            (url("https://a.com"), None),
+        ]);
+
+        assert_eq!(results, [Ok(()), Err(ShortenerError::UrlAlreadyInUse)]);
+    }
+
+    #[test]
+    fn minimal_event_log_reproduces_live_state() {
+        let mut service = UrlShortenerService::new().with_log_redirects(false);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_change_short_link(slug!("a"), url("https://c.com"))
+            .unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        let log = service.minimal_event_log();
+        let replayed = UrlShortenerService::from_events(log.clone()).unwrap();
+
+        assert_eq!(log.len(), 3);
+        for slug in [slug!("a"), slug!("b")] {
+            assert_eq!(
+                replayed.get_stats(slug.clone()),
+                service.get_stats(slug)
+            );
+        }
+    }
+
+    #[test]
+    fn default_scheme_is_prefixed_to_scheme_less_urls() {
+        let mut service =
+            UrlShortenerService::new().with_default_scheme(Some("https"));
+
+        let link = service
+            .handle_create_short_link(url("example.com/page"), None)
+            .unwrap();
+        let other = service
+            .handle_create_short_link(url("ftp://example.com/x"), None)
+            .unwrap();
+
+        assert_eq!(link.url, url("https://example.com/page"));
+        assert_eq!(other.url, url("ftp://example.com/x"));
+    }
+
+    #[test]
+    fn scheme_less_urls_are_rejected_without_default() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_create_short_link(url("example.com/page"), None),
+            Err(ShortenerError::InvalidUrl),
+        );
+    }
+
+    #[test]
+    fn redirects_while_disabled_count_only_disabled_periods() {
+        let mut service = UrlShortenerService::new()
+            .with_disabled_behavior(DisabledBehavior::ServeAnyway);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_disable(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_enable(slug!("a")).unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.redirects_while_disabled(slug!("a")), Ok(2));
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 4);
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.redirects_while_disabled(slug!("a")), Ok(2));
+    }
+
+    #[test]
+    fn creation_seq_is_the_created_event_seq() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("b")))
+            .unwrap();
+
+        assert_eq!(service.creation_seq(&slug!("a")), Ok(1));
+        assert_eq!(service.creation_seq(&slug!("b")), Ok(3));
+        assert_eq!(
+            service.creation_seq(&slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn enriched_redirect_carries_response_and_metadata() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_response(slug!("a"), 301, Some("text/html".into()))
+            .unwrap();
+        service
+            .handle_set_metadata(
+                slug!("a"),
+                "campaign".into(),
+                Some("x".into()),
+            )
+            .unwrap();
+
+        let redirect = service.handle_redirect_enriched(slug!("a")).unwrap();
+
+        assert_eq!(redirect.url, url("https://a.com"));
+        assert_eq!(redirect.status, 301);
+        assert_eq!(redirect.content_type.as_deref(), Some("text/html"));
+        assert_eq!(
+            redirect.metadata,
+            HashMap::from([("campaign".into(), "x".into())]),
+        );
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn enriched_redirect_defaults_to_found() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        let redirect = service.handle_redirect_enriched(slug!("a")).unwrap();
+
+        assert_eq!(redirect.status, 302);
+        assert_eq!(redirect.content_type, None);
+        assert!(redirect.metadata.is_empty());
+    }
+
+    /// Creates a [`CreateRequest`] with every attribute set.
+    fn full_request() -> CreateRequest {
+        CreateRequest {
+            url: url("https://a.com"),
+            slug: Some(slug!("full")),
+            ttl: Some(Duration::from_secs(60)),
+            note: Some("note".into()),
+            metadata: HashMap::from([("k".into(), "v".into())]),
+            redirect_status: Some(301),
+        }
+    }
+
+    #[test]
+    fn create_full_applies_every_attribute() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock);
+
+        let link = service.handle_create_full(full_request()).unwrap();
+
+        let metadata = service.metadata(&link.slug).unwrap();
+        assert_eq!(link.slug, slug!("full"));
+        assert_eq!(metadata.ttl_remaining, Some(Duration::from_secs(60)));
+        assert_eq!(metadata.note.as_deref(), Some("note"));
+        assert_eq!(
+            service.links_with_metadata("k", Some("v")),
+            [slug!("full")]
+        );
+        assert_eq!(
+            service
+                .handle_redirect_enriched(slug!("full"))
+                .unwrap()
+                .status,
+            301,
+        );
+    }
+
+    #[test]
+    fn create_full_is_all_or_nothing() {
+        let mut service = UrlShortenerService::new();
+        let request = CreateRequest {
+            redirect_status: Some(200),
+            ..full_request()
+        };
+
+        assert_eq!(
+            service.handle_create_full(request),
+            Err(ShortenerError::InvalidRedirectStatus),
+        );
+        assert!(service.events().is_empty());
+        assert_eq!(
+            service.resolve(&slug!("full")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn reserved_path_conflicts_match_equal_or_prefixed_slugs() {
+        let mut service = UrlShortenerService::new();
+        for slug in [
+            slug!("static"),
+            slug!("ok"),
+            slug!("static/x"),
+            slug!("statics"),
+        ] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.reserved_path_conflicts(&["static"]),
+            [slug!("static"), slug!("static/x")],
+        );
+        assert_eq!(
+            service.reserved_path_conflicts(&["/static"]),
+            [slug!("static"), slug!("static/x")],
+        );
+    }
+
+    #[test]
+    fn custom_slug_ratio_of_empty_service_is_zero() {
+        assert_eq!(UrlShortenerService::new().custom_slug_ratio(), 0.0);
+    }
+
+    #[test]
+    fn custom_slug_ratio_counts_custom_slugs() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(
+                url("https://a.com"),
+                Some(slug!("custom")),
+            )
+            .unwrap();
+        for _ in 0..3 {
+            service
+                .handle_create_short_link(url("https://a.com"), None)
+                .unwrap();
+        }
+
+        assert_eq!(service.custom_slug_ratio(), 0.25);
+
+        service.compact();
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.custom_slug_ratio(), 0.25);
+    }
+
+    #[test]
+    fn checked_creation_of_clean_url_has_no_warnings() {
+        let mut service = UrlShortenerService::new();
+
+        for target in ["https://example.com/x", "http://1.2.3.4/x"] {
+            let (_, warnings) =
+                service.handle_create_checked(url(target), None).unwrap();
+            assert!(warnings.is_empty(), "{target}: {warnings:?}");
+        }
+    }
+
+    #[test]
+    fn checked_creation_of_dubious_url_warns() {
+        let mut service = UrlShortenerService::new();
+
+        let (link, warnings) = service
+            .handle_create_checked(url("htp://localhost/x"), None)
+            .unwrap();
+
+        assert_eq!(warnings, [UrlWarning::SuspiciousScheme, UrlWarning::NoTld]);
+        assert_eq!(service.resolve(&link.slug), Ok(url("htp://localhost/x")));
+        assert_eq!(
+            service.handle_create_checked(url("htp//x"), None),
+            Err(ShortenerError::InvalidUrl),
+        );
+    }
+
+    #[test]
+    fn url_history_lists_every_target_in_order() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("h")))
+            .unwrap();
+        service
+            .handle_change_short_link(slug!("h"), url("https://b.com"))
+            .unwrap();
+        service.handle_redirect(slug!("h")).unwrap();
+        service
+            .handle_change_short_link(slug!("h"), url("https://c.com"))
+            .unwrap();
+
+        assert_eq!(
+            service.url_history(slug!("h")),
+            Ok(vec![
+                url("https://a.com"),
+                url("https://b.com"),
+                url("https://c.com"),
+            ]),
+        );
+        assert_eq!(
+            service.url_history(slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn builder_rejects_impossible_slug_constraints() {
+        let denied = UrlShortenerService::new()
+            .with_slug_alphabet("a")
+            .with_slug_denylist(&["A"])
+            .build();
+        let empty = UrlShortenerService::new().with_slug_alphabet("").build();
+
+        assert!(matches!(
+            denied,
+            Err(ConfigError::ImpossibleSlugConstraints)
+        ));
+        assert!(matches!(empty, Err(ConfigError::ImpossibleSlugConstraints)));
+    }
+
+    #[test]
+    fn builder_accepts_satisfiable_slug_constraints() {
+        let mut service = UrlShortenerService::new()
+            .with_slug_alphabet("ab")
+            .with_slug_length(2)
+            .with_slug_denylist(&["a"])
+            .with_slug_generation_attempts(200)
+            .build()
+            .ok()
+            .unwrap();
+
+        let link = service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+
+        assert_eq!(link.slug, slug!("bb"));
+    }
+
+    #[test]
+    fn redirect_buckets_align_to_interval_boundaries() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(3600);
+        let clock = clock::MockClock::new(start + Duration::from_secs(60));
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(600));
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(1800));
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let interval = Duration::from_secs(900);
+
+        assert_eq!(
+            service.redirect_buckets(slug!("a"), interval),
+            Ok(vec![(start, 2), (start + 2 * interval, 1)]),
+        );
+    }
+
+    #[test]
+    fn redirect_buckets_reject_zero_interval() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(
+            service.redirect_buckets(slug!("a"), Duration::ZERO),
+            Err(ShortenerError::InvalidInterval),
+        );
+    }
+
+    #[test]
+    fn maintenance_overrides_redirects_until_disabled() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        service.enable_maintenance(url("https://maintenance.example"));
+        assert_eq!(
+            service.handle_redirect(slug!("a")).unwrap().url,
+            url("https://maintenance.example"),
+        );
+        assert_eq!(
+            service.handle_redirect(slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+
+        service.disable_maintenance();
+        assert_eq!(
+            service.handle_redirect(slug!("a")).unwrap().url,
+            url("https://a.com"),
+        );
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn validate_url_applies_default_rules() {
+        let service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.validate_url("HTTPS://A.com"),
+            Ok(url("HTTPS://A.com"))
+        );
+        assert_eq!(
+            service.validate_url("a.com"),
+            Err(ShortenerError::InvalidUrl)
+        );
+    }
+
+    #[test]
+    fn validate_url_applies_configured_rules() {
+        let service = UrlShortenerService::new()
+            .with_default_scheme(Some("https"))
+            .with_normalize_urls(true)
+            .with_require_public_hostname(true);
+
+        assert_eq!(
+            service.validate_url("A.com:443"),
+            Ok(url("https://a.com/"))
+        );
+        assert_eq!(
+            service.validate_url("http://localhost"),
+            Err(ShortenerError::InvalidUrl),
+        );
+        assert_eq!(
+            service.validate_url("http://127.0.0.1"),
+            Err(ShortenerError::InvalidUrl),
+        );
+        assert!(service.events().is_empty());
+    }
+
+    #[test]
+    fn active_stable_since_skips_changed_urls() {
+        let mut service = UrlShortenerService::new().with_url_obfuscation(b"k");
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        let snapshot = service.snapshot();
+        service.handle_redirect(slug!("a")).unwrap();
+        service.handle_redirect(slug!("b")).unwrap();
+        service
+            .handle_change_short_link(slug!("b"), url("https://z.com"))
+            .unwrap();
+
+        assert_eq!(service.active_stable_since(&snapshot), [slug!("a")]);
+    }
+
+    #[test]
+    fn try_claim_claims_a_free_slug() {
+        let mut service = UrlShortenerService::new();
+
+        let link = service
+            .try_claim(url("https://a.com"), slug!("mine"))
+            .unwrap();
+
+        assert_eq!(link.slug, slug!("mine"));
+        assert_eq!(service.resolve(&slug!("mine")), Ok(url("https://a.com")));
+    }
+
+    #[test]
+    fn try_claim_fails_on_a_taken_slug() {
+        for policy in [
+            BatchDuplicatePolicy::FirstWins,
+            BatchDuplicatePolicy::LastWins,
+            BatchDuplicatePolicy::Error,
+        ] {
+            let mut service =
+                UrlShortenerService::new().with_batch_duplicate_policy(policy);
+            service
+                .try_claim(url("https://a.com"), slug!("mine"))
+                .unwrap();
+
+            assert_eq!(
+                service.try_claim(url("https://b.com"), slug!("mine")),
+                Err(ShortenerError::SlugAlreadyInUse),
+            );
+            assert_eq!(
+                service.resolve(&slug!("mine")),
+                Ok(url("https://a.com"))
+            );
+        }
+    }
+
+    #[test]
+    fn try_claim_stores_the_canonical_slug() {
+        let mut service =
+            UrlShortenerService::new().with_trim_trailing_slash_slugs(true);
+
+        let link = service
+            .try_claim(url("https://a.com"), slug!("mine/"))
+            .unwrap();
+
+        assert_eq!(link.slug, slug!("mine"));
+    }
+
+    #[test]
+    fn mean_redirects_per_link_of_empty_service_is_zero() {
+        assert_eq!(UrlShortenerService::new().mean_redirects_per_link(), 0.0);
+    }
+
+    #[test]
+    fn mean_redirects_per_link_averages_counts() {
+        let mut service = UrlShortenerService::new();
+        for count in [2, 4, 6] {
+            let link = service
+                .handle_create_short_link(url("https://a.com"), None)
+                .unwrap();
+            service.handle_seed_redirects(link.slug, count).unwrap();
+        }
+
+        assert_eq!(service.mean_redirects_per_link(), 4.0);
+    }
+
+    /// Creates links `a` and `b` in campaign `x`, and `c` in campaign `y`,
+    /// with 1, 2 and 5 redirects respectively.
+    fn service_with_campaigns() -> UrlShortenerService {
+        let mut service = UrlShortenerService::new();
+        for (slug, redirects, campaign) in [
+            (slug!("a"), 1, "x"),
+            (slug!("b"), 2, "x"),
+            (slug!("c"), 5, "y"),
+        ] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service
+                .handle_seed_redirects(slug.clone(), redirects)
+                .unwrap();
+            service
+                .handle_assign_campaign(slug, campaign.into())
+                .unwrap();
+        }
+        service
+    }
+
+    #[test]
+    fn campaign_stats_are_isolated() {
+        let service = service_with_campaigns();
+
+        assert_eq!(
+            service.campaign_stats("x"),
+            CampaignStats {
+                links: 2,
+                total_redirects: 3
+            },
+        );
+        assert_eq!(
+            service.campaign_stats("y"),
+            CampaignStats {
+                links: 1,
+                total_redirects: 5
+            },
+        );
+        assert_eq!(service.campaign_stats("z"), CampaignStats::default());
+    }
+
+    #[test]
+    fn reassigning_a_campaign_moves_its_contribution() {
+        let mut service = service_with_campaigns();
+
+        service
+            .handle_assign_campaign(slug!("b"), "y".into())
+            .unwrap();
+
+        assert_eq!(
+            service.campaign_stats("x"),
+            CampaignStats {
+                links: 1,
+                total_redirects: 1
+            },
+        );
+        assert_eq!(
+            service.campaign_stats("y"),
+            CampaignStats {
+                links: 2,
+                total_redirects: 7
+            },
+        );
+    }
+
+    #[test]
+    fn event_log_bytes_grow_and_shrink_with_the_log() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.event_log_bytes(), 0);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        let created = service.event_log_bytes();
+
+        for _ in 0..10 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        let redirected = service.event_log_bytes();
+        service.compact();
+
+        assert!(redirected > created);
+        assert!(service.event_log_bytes() < redirected);
+    }
+
+    #[test]
+    fn log_is_compacted_every_n_events() {
+        let mut service = UrlShortenerService::new().with_auto_compact_every(5);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        assert_eq!(service.events().len(), 4);
+
+        service.handle_redirect(slug!("a")).unwrap();
+        assert_eq!(service.events().len(), 2);
+        service.handle_redirect(slug!("a")).unwrap();
+        assert_eq!(service.events().len(), 3);
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 5);
+        assert_eq!(replayed.get_stats(slug!("a")).unwrap().redirects, 5);
+    }
+
+    #[test]
+    fn referer_allowlist_blocks_other_referers() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        let evil = url("https://evil.example/x");
+        service
+            .handle_redirect_with_referrer(slug!("a"), Some(&evil))
+            .unwrap();
+        service
+            .handle_set_allowed_referers(
+                slug!("a"),
+                vec!["Blog.example".into()],
+            )
+            .unwrap();
+
+        service
+            .handle_redirect_with_referrer(
+                slug!("a"),
+                Some(&url("https://blog.EXAMPLE/post")),
+            )
+            .unwrap();
+        assert_eq!(
+            service.handle_redirect_with_referrer(slug!("a"), Some(&evil)),
+            Err(ShortenerError::RefererNotAllowed),
+        );
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn distinct_hosts_are_lowercased_and_deduplicated() {
+        let mut service = UrlShortenerService::new();
+        for target in [
+            "https://b.com/x",
+            "https://a.com",
+            "http://A.com:80/y",
+            "https://user@b.com",
+        ] {
+            service.handle_create_short_link(url(target), None).unwrap();
+        }
+
+        assert_eq!(service.distinct_hosts(), ["a.com", "b.com"]);
+    }
+
+    #[test]
+    fn tag_by_url_tags_only_matching_links() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("a"), "https://www.YouTube.com/watch?v=1"),
+            (slug!("b"), "https://vimeo.com/2"),
+            (slug!("c"), "https://youtube.com/3"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+
+        let tagged = service.handle_tag_by_url(
+            "youtube.com",
+            "type".into(),
+            "video".into(),
+        );
+
+        assert_eq!(tagged, 2);
+        assert_eq!(
+            service.links_with_metadata("type", Some("video")),
+            [slug!("a"), slug!("c")],
+        );
+    }
+
+    #[test]
+    fn healthy_service_passes_slug_integrity() {
+        let mut service =
+            UrlShortenerService::new().with_trim_trailing_slash_slugs(true);
+        for slug in [slug!("a"), slug!("b"), slug!("ns/c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.consolidate_duplicates();
+
+        assert_eq!(service.verify_slug_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn corrupted_service_fails_slug_integrity() {
+        let mut service =
+            UrlShortenerService::new().with_trim_trailing_slash_slugs(true);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.consolidate_duplicates();
+        let mut corrupted = service.links[&slug!("a")].clone();
+        corrupted.link.slug = slug!("b");
+
+        service.links.insert(slug!("b"), corrupted.clone());
+        assert_eq!(
+            service.verify_slug_integrity(),
+            Err(SlugIntegrityError::LiveAlias(slug!("b"))),
+        );
+
+        service.links.remove(&slug!("b"));
+        service.links.insert(slug!("a/"), corrupted);
+        assert_eq!(
+            service.verify_slug_integrity(),
+            Err(SlugIntegrityError::DuplicateSlug(slug!("a"))),
+        );
+
+        service.links.remove(&slug!("a/"));
+        service.aliases.insert(slug!("z"), slug!("zz"));
+        assert_eq!(
+            service.verify_slug_integrity(),
+            Err(SlugIntegrityError::DanglingAlias(slug!("z"))),
+        );
+
+        service.aliases.insert(slug!("z"), slug!("z"));
+        assert_eq!(
+            service.verify_slug_integrity(),
+            Err(SlugIntegrityError::SelfAlias(slug!("z"))),
+        );
+    }
+
+    #[test]
+    fn redirect_increment_weights_each_redirect() {
+        let mut service =
+            UrlShortenerService::new().with_redirect_increment(10);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 30);
+        assert_eq!(service.redirect_hits(slug!("a")), Ok(3));
+        assert_eq!(service.in_window_redirects(slug!("a")), Ok(30));
+        assert_eq!(service.referrer_total(""), 30);
+        let timeline = service.link_timeline(slug!("a")).unwrap();
+        assert_eq!(timeline.last().unwrap().1, "redirected 30 time(s)");
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn split_log_archives_old_events_and_keeps_state() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        clock.advance(Duration::from_secs(10));
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(10));
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("b")))
+            .unwrap();
+        let cutoff = SystemTime::UNIX_EPOCH + Duration::from_secs(15);
+
+        let archived = service.split_log_at(cutoff).unwrap();
+
+        assert_eq!(archived.len(), 2);
+        assert!(archived.iter().all(|event| event.at() < cutoff));
+        assert!(service.is_consistent());
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.get_stats(slug!("a")).unwrap().redirects, 2);
+        assert_eq!(replayed.get_stats(slug!("b")).unwrap().redirects, 0);
+    }
+
+    #[test]
+    fn split_log_archives_late_imported_events() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        clock.advance(Duration::from_secs(20));
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("b")))
+            .unwrap();
+        service.handle_import_access_log(
+            "x [01/Jan/1970:00:00:12 +0000] /a\n\
+             x [01/Jan/1970:00:00:12 +0000] /b",
+            3,
+        );
+        let cutoff = SystemTime::UNIX_EPOCH + Duration::from_secs(15);
+
+        let archived = service.split_log_at(cutoff).unwrap();
+
+        assert_eq!(archived.len(), 2);
+        assert!(archived.iter().all(|event| event.at() < cutoff));
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.get_stats(slug!("a")).unwrap().redirects, 1);
+        assert_eq!(replayed.get_stats(slug!("b")).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn split_log_before_any_event_archives_nothing() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        let archived = service.split_log_at(SystemTime::UNIX_EPOCH).unwrap();
+
+        assert!(archived.is_empty());
+        assert_eq!(service.events().len(), 1);
+    }
+
+    #[test]
+    fn slug_role_classifies_relationships() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_create_short_link(url("https://z.com"), Some(slug!("z")))
+            .unwrap();
+        service.consolidate_duplicates();
+
+        assert_eq!(service.slug_role(&slug!("z")), Ok(SlugRole::Standalone));
+        assert_eq!(
+            service.slug_role(&slug!("b")),
+            Ok(SlugRole::Alias(slug!("a"))),
+        );
+        assert_eq!(
+            service.slug_role(&slug!("a")),
+            Ok(SlugRole::CanonicalTargetOf(vec![slug!("b"), slug!("c")])),
+        );
+        assert_eq!(
+            service.slug_role(&slug!("missing")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn click_gini_of_even_clicks_is_zero() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.click_gini(), 0.0);
+        for _ in 0..100 {
+            let link = service
+                .handle_create_short_link(url("https://a.com"), None)
+                .unwrap();
+            service.handle_redirect(link.slug).unwrap();
+        }
+
+        assert!(service.click_gini().abs() < 1e-9);
+    }
+
+    #[test]
+    fn click_gini_of_concentrated_clicks_is_near_one() {
+        let mut service = UrlShortenerService::new();
+        for _ in 0..100 {
+            service
+                .handle_create_short_link(url("https://a.com"), None)
+                .unwrap();
+        }
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("hot")))
+            .unwrap();
+        service.handle_seed_redirects(slug!("hot"), 50).unwrap();
+
+        assert!(service.click_gini() > 0.98);
+    }
+
+    #[test]
+    fn creations_by_origin_are_filtered_and_ordered() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        let mut generated = Vec::new();
+        for i in 0..4 {
+            clock.advance(Duration::from_secs(1));
+            if i % 2 == 0 {
+                service
+                    .handle_create_short_link(
+                        url("https://a.com"),
+                        Some(Slug(format!("z{i}"))),
+                    )
+                    .unwrap();
+            } else {
+                let link = service
+                    .handle_create_short_link(url("https://a.com"), None)
+                    .unwrap();
+                generated.push((clock.now(), link.slug));
+            }
+        }
+
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(
+            service.creations_by_origin(SlugOrigin::Custom),
+            [(at(1), slug!("z0")), (at(3), slug!("z2"))],
+        );
+        assert_eq!(
+            service.creations_by_origin(SlugOrigin::Generated),
+            generated
+        );
+    }
+
+    #[test]
+    fn short_link_round_trips_through_a_tuple() {
+        let link = ShortLink::from_parts(slug!("a"), url("https://a.com"));
+
+        let (slug, target): (Slug, Url) = link.clone().into();
+
+        assert_eq!(
+            (slug.clone(), target.clone()),
+            (slug!("a"), url("https://a.com"))
+        );
+        assert_eq!(ShortLink::from_parts(slug, target), link);
+    }
+
+    #[test]
+    fn resolve_chain_follows_short_urls() {
+        let mut service =
+            UrlShortenerService::new().with_base_url(url("https://Sho.rt/"));
+        for (slug, target) in [
+            (slug!("c"), "https://a.com"),
+            (slug!("b"), "https://sho.rt/c"),
+            (slug!("a"), "https://sho.rt/b?x=1"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.resolve_chain(slug!("a"), 10),
+            Ok(vec![slug!("a"), slug!("b"), slug!("c")]),
+        );
+        assert_eq!(
+            service.resolve_chain(slug!("a"), 1),
+            Ok(vec![slug!("a"), slug!("b")]),
+        );
+    }
+
+    #[test]
+    fn resolve_chain_detects_loops() {
+        let mut service =
+            UrlShortenerService::new().with_base_url(url("https://sho.rt/"));
+        service
+            .handle_create_short_link(url("https://sho.rt/l"), Some(slug!("l")))
+            .unwrap();
+
+        assert_eq!(
+            service.resolve_chain(slug!("l"), 10),
+            Err(ShortenerError::RedirectLoop),
+        );
+    }
+
+    #[test]
+    fn uptime_follows_the_clock() {
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_secs(5);
+        let clock = clock::MockClock::new(started_at);
+        let service = UrlShortenerService::new().with_clock(clock.clone());
+        assert_eq!(service.uptime(), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(90));
+
+        assert_eq!(service.uptime(), Duration::from_secs(90));
+        assert_eq!(service.started_at(), started_at);
+    }
+
+    /// Creates link `a` with a redirect, then link `b`.
+    fn source_events() -> Vec<Event> {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("b")))
+            .unwrap();
+        service.events().to_vec()
+    }
+
+    #[test]
+    fn apply_events_appends_a_valid_batch() {
+        let events = source_events();
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(service.apply_events(&events), Ok(()));
+
+        assert_eq!(service.events(), events);
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 1);
+        assert_eq!(service.resolve(&slug!("b")), Ok(url("https://b.com")));
+    }
+
+    #[test]
+    fn apply_events_stops_at_the_first_invalid_event() {
+        let events = source_events();
+        let mut service = UrlShortenerService::new();
+        let batch = [events[0].clone(), events[0].clone(), events[2].clone()];
+
+        assert_eq!(
+            service.apply_events(&batch),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+
+        assert_eq!(service.events().len(), 1);
+        assert_eq!(
+            service.get_stats(slug!("b")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn url_changes_are_limited_per_slug() {
+        let mut service = UrlShortenerService::new().with_max_url_changes(2);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_change_short_link(slug!("a"), url("https://b.com"))
+            .unwrap();
+        service
+            .handle_change_short_link(slug!("a"), url("https://c.com"))
+            .unwrap();
+
+        assert_eq!(
+            service.handle_change_short_link(slug!("a"), url("https://d.com")),
+            Err(ShortenerError::ChangeLimitExceeded),
+        );
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://c.com")));
+        service
+            .handle_change_short_link(slug!("b"), url("https://d.com"))
+            .unwrap();
+    }
+
+    #[test]
+    fn url_change_limit_applies_to_retargeting_and_survives_compaction() {
+        let mut service = UrlShortenerService::new().with_max_url_changes(1);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        assert_eq!(
+            service.handle_retarget_url(
+                &url("https://a.com"),
+                url("https://c.com")
+            ),
+            Ok(2),
+        );
+
+        assert_eq!(
+            service.handle_retarget_url(
+                &url("https://c.com"),
+                url("https://d.com")
+            ),
+            Err(ShortenerError::ChangeLimitExceeded),
+        );
+        assert_eq!(service.events().len(), 4);
+
+        service.compact();
+        assert_eq!(
+            service.handle_change_short_link(slug!("a"), url("https://d.com")),
+            Err(ShortenerError::ChangeLimitExceeded),
+        );
+    }
+
+    #[test]
+    fn peek_next_slug_matches_the_next_sequential_slug() {
+        let mut service = UrlShortenerService::new()
+            .with_sequential_slugs(true)
+            .with_slug_alphabet("ab")
+            .with_slug_length(3);
+        assert_eq!(service.peek_next_slug(), slug!("aaa"));
+        assert_eq!(service.peek_next_slug(), slug!("aaa"));
+
+        let first = service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("aab")))
+            .unwrap();
+        let peeked = service.peek_next_slug();
+        let second = service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+
+        assert_eq!(first.slug, slug!("aaa"));
+        assert_eq!(peeked, slug!("aba"));
+        assert_eq!(second.slug, peeked);
+    }
+
+    #[test]
+    fn titles_are_set_overwritten_and_cleared() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        assert_eq!(service.get_title(slug!("a")), Ok(None));
+
+        service.handle_set_title(slug!("a"), "One".into()).unwrap();
+        assert_eq!(service.get_title(slug!("a")), Ok(Some("One".into())));
+        service.handle_set_title(slug!("a"), "Two".into()).unwrap();
+        assert_eq!(service.get_title(slug!("a")), Ok(Some("Two".into())));
+        service.compact();
+        assert_eq!(service.get_title(slug!("a")), Ok(Some("Two".into())));
+        service.handle_set_title(slug!("a"), String::new()).unwrap();
+        assert_eq!(service.get_title(slug!("a")), Ok(None));
+    }
+
+    #[test]
+    fn titles_of_unknown_slugs_fail() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_set_title(slug!("z"), "x".into()),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(
+            service.get_title(slug!("z")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn slug_length_click_correlation_is_pearson() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.slug_length_click_correlation(), None);
+        for (slug, redirects) in [
+            (slug!("a"), 6),
+            (slug!("bb"), 4),
+            (slug!("ccc"), 2),
+            (slug!("dddd"), 4),
+        ] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service.handle_seed_redirects(slug, redirects).unwrap();
+        }
+
+        let correlation = service.slug_length_click_correlation().unwrap();
+
+        // The covariance is -4, while the variances are 5 and 8.
+        assert!((correlation + 4.0 / 40f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slug_length_click_correlation_needs_variance() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(service.slug_length_click_correlation(), None);
+    }
+
+    #[test]
+    fn idle_links_expire_unless_redirected() {
+        const DAY: Duration = Duration::from_secs(86_400);
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new()
+            .with_clock(clock.clone())
+            .with_idle_expiry(DAY * 3);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        clock.advance(DAY * 2);
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(DAY * 2);
+
+        service.handle_redirect(slug!("a")).unwrap();
+        assert_eq!(
+            service.handle_redirect(slug!("b")),
+            Err(ShortenerError::LinkExpired),
+        );
+        assert_eq!(service.expired_links(), [slug!("b")]);
+
+        clock.advance(DAY * 3);
+        assert_eq!(service.expired_links(), [slug!("a"), slug!("b")]);
+    }
+
+    #[test]
+    fn rotate_log_returns_the_full_log_and_keeps_state() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        service
+            .handle_change_short_link(slug!("a"), url("https://b.com"))
+            .unwrap();
+        let before = service.events().to_vec();
+
+        let rotated = service.rotate_log();
+
+        assert_eq!(rotated, before);
+        let old = UrlShortenerService::from_events(rotated).unwrap();
+        let new = UrlShortenerService::from_events(service.events().to_vec())
+            .unwrap();
+        assert_eq!(new.get_stats(slug!("a")), old.get_stats(slug!("a")));
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 3);
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn duplicate_target_groups_list_shared_urls_only() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target, redirects) in [
+            (slug!("b"), "https://a.com", 2),
+            (slug!("a"), "https://a.com", 5),
+            (slug!("c"), "https://c.com", 1),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug.clone()))
+                .unwrap();
+            service.handle_seed_redirects(slug, redirects).unwrap();
+        }
+
+        assert_eq!(
+            service.duplicate_target_groups(),
+            [(url("https://a.com"), vec![(slug!("a"), 5), (slug!("b"), 2)])],
+        );
+    }
+
+    #[test]
+    fn batch_duplicate_policy_picks_the_winner() {
+        let items = [
+            (url("https://a.com"), Some(slug!("x"))),
+            (url("https://y.com"), None),
+            (url("https://b.com"), Some(slug!("x"))),
+        ];
+
+        for (policy, winner, created) in [
+            (
+                BatchDuplicatePolicy::FirstWins,
+                Some("https://a.com"),
+                [true, true, false],
+            ),
+            (
+                BatchDuplicatePolicy::LastWins,
+                Some("https://b.com"),
+                [false, true, true],
+            ),
+            (BatchDuplicatePolicy::Error, None, [false, true, false]),
+        ] {
+            let mut service =
+                UrlShortenerService::new().with_batch_duplicate_policy(policy);
+
+            let results: Vec<_> = service
+                .handle_create_many(&items)
+                .iter()
+                .map(Result::is_ok)
+                .collect();
+
+            assert_eq!(results, created);
+            assert_eq!(service.resolve(&slug!("x")).ok(), winner.map(url));
+        }
+    }
+
+    #[test]
+    fn batch_duplicate_policy_applies_to_validation() {
+        let items = [
+            (url("https://a.com"), Some(slug!("x"))),
+            (url("https://b.com"), Some(slug!("x"))),
+        ];
+
+        for (policy, valid) in [
+            (BatchDuplicatePolicy::FirstWins, [true, false]),
+            (BatchDuplicatePolicy::LastWins, [false, true]),
+            (BatchDuplicatePolicy::Error, [false, false]),
+        ] {
+            let service =
+                UrlShortenerService::new().with_batch_duplicate_policy(policy);
+
+            let results: Vec<_> = service
+                .validate_batch(&items)
+                .iter()
+                .map(Result::is_ok)
+                .collect();
+
+            assert_eq!(results, valid);
+        }
+    }
+
+    #[test]
+    fn batch_duplicates_of_existing_slugs_fail() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("x")))
+            .unwrap();
+
+        let results = service
+            .handle_create_many(&[(url("https://b.com"), Some(slug!("x")))]);
+
+        assert_eq!(results, [Err(ShortenerError::SlugAlreadyInUse)]);
+    }
+
+    #[test]
+    fn render_table_aligns_columns() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("b"), "https://example.com/long".to_owned()),
+            (slug!("abcdef"), "https://a.io".to_owned()),
+            (slug!("c"), format!("https://x.com/{}", "y".repeat(60))),
+        ] {
+            service
+                .handle_create_short_link(Url(target), Some(slug))
+                .unwrap();
+        }
+        service.handle_seed_redirects(slug!("b"), 12).unwrap();
+
+        let table = service.render_table();
+
+        let lines: Vec<_> = table.lines().collect();
+        let width = lines[0].chars().count();
+        let header: Vec<_> = lines[0].split(" | ").map(str::trim).collect();
+        assert_eq!(header, ["Slug", "URL", "Redirects"]);
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+        assert!(lines[2].starts_with("abcdef | https://a.io "));
+        assert!(lines[3].starts_with("b      | https://example.com/long"));
+        assert!(lines[3].ends_with("       12"));
+        assert!(lines[4].contains("..."));
+    }
+
+    #[test]
+    fn anonymized_events_use_stable_pseudonyms() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let events = service.export_events_anonymized();
+
+        assert_eq!(events[0].slug(), events[2].slug());
+        assert_ne!(events[0].slug(), events[1].slug());
+        assert_ne!(events[0].slug(), &slug!("a"));
+        assert!(matches!(
+            &events[0],
+            Event::ShortLinkCreated { url, .. } if url.0 == "https://a.com"
+        ));
+        assert!(UrlShortenerService::from_events(events).is_ok());
+    }
+
+    #[test]
+    fn time_to_first_click_measures_from_creation() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        clock.advance(Duration::from_secs(300));
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(300));
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(
+            service.time_to_first_click(slug!("a")),
+            Ok(Some(Duration::from_secs(300))),
+        );
+        assert_eq!(service.time_to_first_click(slug!("b")), Ok(None));
+        assert_eq!(
+            service.time_to_first_click(slug!("z")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn full_analytics_snapshot_restores_time_series() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new()
+            .with_clock(clock.clone())
+            .with_full_analytics_snapshot(true);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for i in 0..5 {
+            clock.advance(Duration::from_secs(40 * i));
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        service.handle_seed_redirects(slug!("a"), 7).unwrap();
+        let minute = Duration::from_secs(60);
+
+        let restored = UrlShortenerService::from_snapshot(&service.snapshot());
+
+        assert_eq!(
+            restored.redirect_buckets(slug!("a"), minute),
+            service.redirect_buckets(slug!("a"), minute),
+        );
+        assert_eq!(
+            restored.get_stats(slug!("a")),
+            service.get_stats(slug!("a"))
+        );
+    }
+
+    #[test]
+    fn plain_snapshot_restores_counters_only() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let restored = UrlShortenerService::from_snapshot(&service.snapshot());
+
+        assert_eq!(restored.get_stats(slug!("a")).unwrap().redirects, 1);
+        assert_eq!(
+            restored.redirect_buckets(slug!("a"), Duration::from_secs(60)),
+            Ok(Vec::new()),
+        );
+    }
+
+    #[test]
+    fn snapshot_restores_slug_origins() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(
+                url("https://a.com"),
+                Some(slug!("custom")),
+            )
+            .unwrap();
+        service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+
+        let restored = UrlShortenerService::from_snapshot(&service.snapshot());
+
+        assert_eq!(restored.custom_slug_ratio(), 0.5);
+    }
+
+    #[test]
+    fn links_violating_match_the_predicate() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("c"), "http://a.com"),
+            (slug!("a"), "https://a.com"),
+            (slug!("b"), "HTTP://b.com"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+
+        let violating = service.links_violating(|url| {
+            url.0.to_ascii_lowercase().starts_with("http://")
+        });
+
+        assert_eq!(violating, [slug!("b"), slug!("c")]);
+    }
+
+    /// Creates link `a` with a redirect, and link `taken`.
+    fn service_to_rebrand() -> UrlShortenerService {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_create_short_link(
+                url("https://t.com"),
+                Some(slug!("taken")),
+            )
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+    }
+
+    #[test]
+    fn rebrand_renames_and_retargets_keeping_stats() {
+        let mut service = service_to_rebrand();
+
+        let link = service
+            .handle_rebrand(slug!("a"), slug!("b"), url("https://b.com"))
+            .unwrap();
+
+        assert_eq!(
+            link,
+            ShortLink::from_parts(slug!("b"), url("https://b.com"))
+        );
+        assert_eq!(service.get_stats(slug!("b")).unwrap().redirects, 1);
+        assert_eq!(
+            service.get_stats(slug!("a")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn failed_rebrand_leaves_the_link_untouched() {
+        let mut service = service_to_rebrand();
+
+        for (old, new, target, error) in [
+            (
+                slug!("zz"),
+                slug!("b"),
+                "https://b.com",
+                ShortenerError::SlugNotFound,
+            ),
+            (
+                slug!("a"),
+                slug!("taken"),
+                "https://b.com",
+                ShortenerError::SlugAlreadyInUse,
+            ),
+            (slug!("a"), slug!("b"), "nope", ShortenerError::InvalidUrl),
+        ] {
+            assert_eq!(
+                service.handle_rebrand(old, new, url(target)),
+                Err(error)
+            );
+        }
+
+        assert_eq!(service.events().len(), 3);
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+        assert_eq!(
+            service.resolve(&slug!("b")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn expired_fraction_of_empty_service_is_zero() {
+        assert_eq!(UrlShortenerService::new().expired_fraction(), 0.0);
+    }
+
+    #[test]
+    fn expired_fraction_follows_the_clock() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        for (slug, ttl) in [
+            (slug!("a"), 10),
+            (slug!("b"), 10),
+            (slug!("c"), 100),
+            (slug!("d"), 100),
+        ] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service
+                .handle_set_ttl(slug, Some(Duration::from_secs(ttl)))
+                .unwrap();
+        }
+        assert_eq!(service.expired_fraction(), 0.0);
+
+        clock.advance(Duration::from_secs(50));
+
+        assert!((service.expired_fraction() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn prometheus_export_follows_the_exposition_format() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_seed_redirects(slug!("a"), 3).unwrap();
+
+        let text = service.export_prometheus();
+
+        assert!(text.starts_with(
+            "# HELP tiny_url_redirects_total Count of redirects of a short \
+             link.\n# TYPE tiny_url_redirects_total counter\n"
+        ));
+        assert!(text.contains("tiny_url_redirects_total{slug=\"a\"} 3\n"));
+        assert!(text.contains(
+            "# TYPE tiny_url_links_total gauge\ntiny_url_links_total 1\n"
+        ));
+    }
+
+    #[test]
+    fn prometheus_export_escapes_labels() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(
+                url("https://a.com"),
+                Some(Slug("q\"b\\".into())),
+            )
+            .unwrap();
+
+        let text = service.export_prometheus();
+
+        assert!(
+            text.contains("tiny_url_redirects_total{slug=\"q\\\"b\\\\\"} 0\n")
+        );
+    }
+
+    #[test]
+    fn service_matches_its_own_snapshot() {
+        let mut service =
+            UrlShortenerService::new().with_full_analytics_snapshot(true);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let snapshot = service.snapshot();
+
+        assert!(service.matches_snapshot(&snapshot));
+        assert_eq!(service.describe_snapshot_diff(&snapshot), None);
+    }
+
+    #[test]
+    fn snapshot_diff_describes_mismatches() {
+        let mut service =
+            UrlShortenerService::new().with_full_analytics_snapshot(true);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("a")).unwrap();
+        let snapshot = service.snapshot();
+
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+            .handle_set_metadata(slug!("b"), "k".into(), Some("v".into()))
+            .unwrap();
+
+        assert!(!service.matches_snapshot(&snapshot));
+        assert_eq!(
+            service.describe_snapshot_diff(&snapshot).unwrap(),
+            "a: 2 redirect(s), expected 1\n\
+             a: analytics differ\n\
+             b: metadata differ\n\
+             event log has 5 event(s), expected 3",
+        );
+    }
+
+    #[test]
+    fn links_are_active_within_their_window() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_active_from(
+                slug!("a"),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(100),
+            )
+            .unwrap();
+        service
+            .handle_set_ttl(slug!("a"), Some(Duration::from_secs(200)))
+            .unwrap();
+
+        assert_eq!(
+            service.handle_redirect(slug!("a")),
+            Err(ShortenerError::NotYetActive),
+        );
+        clock.advance(Duration::from_secs(100));
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(100));
+        assert_eq!(
+            service.handle_redirect(slug!("a")),
+            Err(ShortenerError::LinkExpired),
+        );
+    }
+
+    #[test]
+    fn active_from_of_unknown_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_set_active_from(slug!("z"), SystemTime::UNIX_EPOCH),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn in_window_redirects_skip_clicks_outside_the_window() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_active_from(
+                slug!("a"),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(50),
+            )
+            .unwrap();
+        service
+            .handle_set_ttl(slug!("a"), Some(Duration::from_secs(80)))
+            .unwrap();
+        let log = "1.1.1.1 - - [01/Jan/1970:00:00:10 +0000] /a\n\
+                   1.1.1.1 - - [01/Jan/1970:00:01:30 +0000] /a\n";
+        assert_eq!(service.handle_import_access_log(log, 5).imported, 2);
+
+        clock.advance(Duration::from_secs(60));
+        service.handle_redirect(slug!("a")).unwrap();
+        clock.advance(Duration::from_secs(10));
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 4);
+        assert_eq!(service.in_window_redirects(slug!("a")), Ok(2));
+    }
+
+    #[test]
+    fn query_filters_sorts_and_limits() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target, redirects) in [
+            (slug!("a"), "https://x.com/1", 1),
+            (slug!("b"), "https://x.com/2", 5),
+            (slug!("c"), "https://y.com", 9),
+            (slug!("d"), "https://x.com/3", 5),
+            (slug!("e"), "https://x.com/4", 0),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug.clone()))
+                .unwrap();
+            service.handle_seed_redirects(slug, redirects).unwrap();
+        }
+
+        let stats: Vec<_> = service
+            .query()
+            .filter(|stats| stats.link.url.0.contains("x.com"))
+            .sort_by(SortKey::Redirects)
+            .descending()
+            .limit(3)
+            .collect()
+            .into_iter()
+            .map(|stats| (stats.link.slug, stats.redirects))
+            .collect();
+
+        assert_eq!(stats, [(slug!("b"), 5), (slug!("d"), 5), (slug!("a"), 1)]);
+    }
+
+    #[test]
+    fn query_offsets_into_slug_order() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("c"), slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        let slugs: Vec<_> = service
+            .query()
+            .offset(1)
+            .collect()
+            .into_iter()
+            .map(|stats| stats.link.slug)
+            .collect();
+
+        assert_eq!(slugs, [slug!("b"), slug!("c")]);
+    }
+
+    #[test]
+    fn audited_reads_are_recorded_apart_from_commands() {
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new()
+            .with_clock(clock)
+            .with_read_auditing(true);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        service.get_stats(slug!("a")).unwrap();
+        service.resolve(&slug!("a")).unwrap();
+
+        assert_eq!(
+            service.read_audit(),
+            [
+                ReadEvent {
+                    kind: "get_stats",
+                    slug: Some(slug!("a")),
+                    at: SystemTime::UNIX_EPOCH,
+                },
+                ReadEvent {
+                    kind: "resolve",
+                    slug: Some(slug!("a")),
+                    at: SystemTime::UNIX_EPOCH,
+                },
+            ],
+        );
+        assert_eq!(service.events().len(), 1);
+    }
+
+    #[test]
+    fn reads_of_several_slugs_are_audited_per_slug() {
+        let mut service = UrlShortenerService::new().with_read_auditing(true);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        service.same_target(&slug!("a"), &slug!("b")).unwrap();
+
+        let audited: Vec<_> = service
+            .read_audit()
+            .into_iter()
+            .map(|read| (read.kind, read.slug))
+            .collect();
+        assert_eq!(
+            audited,
+            [
+                ("same_target", Some(slug!("a"))),
+                ("same_target", Some(slug!("b"))),
+            ],
+        );
+    }
+
+    #[test]
+    fn reads_are_not_audited_by_default() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        service.get_stats(slug!("a")).unwrap();
+
+        assert!(service.read_audit().is_empty());
+    }
+
+    #[test]
+    fn read_audit_may_be_held_while_reading() {
+        let mut service = UrlShortenerService::new().with_read_auditing(true);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.get_stats(slug!("a")).unwrap();
+
+        let audit = service.read_audit();
+        service.get_stats(slug!("a")).unwrap();
+
+        assert_eq!(audit.len(), 1);
+        assert_eq!(service.read_audit().len(), 2);
+    }
+
+    #[test]
+    fn redirects_are_grouped_by_slug_origin() {
+        let mut service = UrlShortenerService::new();
+        for (slug, redirects) in
+            [(Some(slug!("custom")), 10), (None, 3), (None, 4)]
+        {
+            let link = service
+                .handle_create_short_link(url("https://a.com"), slug)
+                .unwrap();
+            service.handle_seed_redirects(link.slug, redirects).unwrap();
+        }
+
+        assert_eq!(
+            service.redirects_by_origin(),
+            HashMap::from([
+                (SlugOrigin::Custom, 10),
+                (SlugOrigin::Generated, 7)
+            ]),
+        );
+    }
+
+    #[test]
+    fn reseeded_services_generate_identical_slugs() {
+        let mut a = UrlShortenerService::new();
+        let mut b = UrlShortenerService::new();
+        let existing = a
+            .handle_create_short_link(url("https://x.com"), None)
+            .unwrap();
+
+        a.reseed_rng(42);
+        b.reseed_rng(42);
+        let generate = |service: &mut UrlShortenerService| {
+            (0..5)
+                .map(|_| {
+                    service
+                        .handle_create_short_link(url("https://a.com"), None)
+                        .unwrap()
+                        .slug
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(generate(&mut a), generate(&mut b));
+        assert_eq!(a.resolve(&existing.slug), Ok(url("https://x.com")));
+    }
+
+    #[test]
+    fn disabled_links_do_not_keep_urls_referenced() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://x.com"), Some(slug!("a")))
+            .unwrap();
+        let snapshot = service.snapshot();
+
+        service.handle_disable(slug!("a")).unwrap();
+
+        assert_eq!(service.orphaned_urls(&snapshot), [url("https://x.com")]);
+    }
+
+    #[test]
+    fn slug_macro_accepts_valid_literals() {
+        assert_eq!(slug!("admin"), Slug("admin".into()));
+        assert_eq!(slug!("ns/a-b_1"), Slug("ns/a-b_1".into()));
+    }
+
+    #[test]
+    fn invalid_slug_literals_are_detected() {
+        assert!(Slug::is_valid_literal("admin"));
+        assert!(!Slug::is_valid_literal("not a slug"));
+        assert!(!Slug::is_valid_literal("ü"));
+        assert!(!Slug::is_valid_literal(""));
+    }
+
+    #[test]
+    fn reconcile_counts_keeps_the_higher_count() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service.handle_seed_redirects(slug, 5).unwrap();
+        }
+        let external = HashMap::from([(slug!("a"), 8), (slug!("b"), 2)]);
+
+        service.reconcile_counts(external.clone()).unwrap();
+        service.reconcile_counts(external).unwrap();
+
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 8);
+        assert_eq!(service.get_stats(slug!("b")).unwrap().redirects, 5);
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn reconcile_counts_rejects_unknown_slugs() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        let reconciled = service.reconcile_counts(HashMap::from([
+            (slug!("a"), 20),
+            (slug!("z"), 1),
+        ]));
+
+        assert_eq!(reconciled, Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 0);
+        assert_eq!(service.events().len(), 1);
+    }
+
+    #[test]
+    fn recent_clicks_are_newest_first() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_redirect_with_source(slug!("a"), "mail".into())
+            .unwrap();
+        service
+            .handle_redirect_with_referrer(
+                slug!("a"),
+                Some(&url("https://r.com")),
+            )
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        let recent = service.recent_clicks(slug!("a"), 2).unwrap();
+        let all = service.recent_clicks(slug!("a"), 10).unwrap();
+
+        assert_eq!(recent.len(), 2);
+        assert_eq!(
+            (recent[0].referrer.as_deref(), recent[0].source.as_deref()),
+            (None, None)
+        );
+        assert_eq!(recent[1].referrer.as_deref(), Some("https://r.com"));
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2].source.as_deref(), Some("mail"));
+        assert_eq!(
+            service.recent_clicks(slug!("z"), 1),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn recent_clicks_survive_full_analytics_snapshots() {
+        let mut service =
+            UrlShortenerService::new().with_full_analytics_snapshot(true);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_redirect_with_source(slug!("a"), "mail".into())
+            .unwrap();
+        service
+            .handle_redirect_with_referrer(
+                slug!("a"),
+                Some(&url("https://r.com")),
+            )
+            .unwrap();
+
+        let restored = UrlShortenerService::from_snapshot(&service.snapshot());
+
+        assert_eq!(
+            restored.recent_clicks(slug!("a"), 10),
+            service.recent_clicks(slug!("a"), 10),
+        );
+    }
+
+    #[test]
+    fn empty_service_recommends_the_configured_length() {
+        let service = UrlShortenerService::new()
+            .with_slug_length(2)
+            .with_slug_alphabet("ab");
+
+        assert_eq!(service.recommended_slug_length(0.01), 2);
+    }
+
+    #[test]
+    fn loaded_service_recommends_a_longer_length() {
+        let mut service = UrlShortenerService::new()
+            .with_slug_length(2)
+            .with_slug_alphabet("abcd");
+        for i in 0..10 {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(Slug(format!("x{i}"))),
+                )
+                .unwrap();
+        }
+
+        // 10 of 4^4 slugs collide with 3.9% probability, of 4^5 with 0.98%.
+        assert_eq!(service.recommended_slug_length(0.01), 5);
+        assert_eq!(service.recommended_slug_length(1.0), 2);
+    }
+
+    #[test]
+    fn disable_where_disables_matching_live_links() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("a"), "https://bad.com/1"),
+            (slug!("b"), "https://bad.com/2"),
+            (slug!("c"), "https://good.com"),
+            (slug!("d"), "https://bad.com/3"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+        service.handle_disable(slug!("d")).unwrap();
+
+        let disabled = service.handle_disable_where(|link, _| {
+            link.url.0.starts_with("https://bad.com")
+        });
+
+        assert_eq!(disabled, 2);
+        assert_eq!(service.events().len(), 7);
+        for slug in [slug!("a"), slug!("b")] {
+            assert_eq!(
+                service.handle_redirect(slug),
+                Err(ShortenerError::LinkDisabled),
+            );
+        }
+        service.handle_redirect(slug!("c")).unwrap();
+    }
+
+    #[test]
+    fn links_in_redirect_range_select_the_band() {
+        let mut service = UrlShortenerService::new();
+        for (slug, redirects) in [
+            (slug!("a"), 1),
+            (slug!("b"), 5),
+            (slug!("c"), 3),
+            (slug!("d"), 9),
+            (slug!("e"), 5),
+            (slug!("f"), 4),
+        ] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service.handle_seed_redirects(slug, redirects).unwrap();
+        }
+        service.handle_disable(slug!("f")).unwrap();
+
+        let band: Vec<_> = service
+            .links_in_redirect_range(3, 5)
+            .unwrap()
+            .into_iter()
+            .map(|stats| (stats.link.slug, stats.redirects))
+            .collect();
+
+        assert_eq!(band, [(slug!("c"), 3), (slug!("b"), 5), (slug!("e"), 5)]);
+    }
+
+    #[test]
+    fn links_in_redirect_range_reject_inverted_bounds() {
+        assert_eq!(
+            UrlShortenerService::new().links_in_redirect_range(2, 1),
+            Err(ShortenerError::InvalidInterval),
+        );
+    }
+
+    /// Creates links `a`, `b` and `c` in the provided order, and redirects
+    /// `a` once.
+    fn service_created_in(order: [Slug; 3]) -> UrlShortenerService {
+        let mut service = UrlShortenerService::new();
+        for slug in order {
+            let target = url(&format!("https://{}.com", slug.0));
+            service
+                .handle_create_short_link(target, Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+    }
+
+    #[test]
+    fn identical_states_have_identical_fingerprints() {
+        let a = service_created_in([slug!("a"), slug!("b"), slug!("c")]);
+        let b = service_created_in([slug!("c"), slug!("a"), slug!("b")]);
+
+        assert_eq!(a.state_fingerprint(), b.state_fingerprint());
+    }
+
+    #[test]
+    fn any_change_alters_the_fingerprint() {
+        let original = service_created_in([slug!("a"), slug!("b"), slug!("c")]);
+        let mut redirected =
+            service_created_in([slug!("a"), slug!("b"), slug!("c")]);
+        let mut changed =
+            service_created_in([slug!("a"), slug!("b"), slug!("c")]);
+        let mut renamed =
+            service_created_in([slug!("a"), slug!("b"), slug!("c")]);
+
+        redirected.handle_redirect(slug!("b")).unwrap();
+        changed
+            .handle_change_short_link(slug!("c"), url("https://x.com"))
+            .unwrap();
+        renamed
+            .handle_move_namespace(&slug!("c"), "", "ns")
+            .unwrap();
+
+        for other in [redirected, changed, renamed] {
+            assert_ne!(original.state_fingerprint(), other.state_fingerprint());
+        }
+    }
+
+    #[test]
+    fn sampled_redirects_record_a_fraction_of_clicks() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut service = UrlShortenerService::new()
+            .with_rng(StdRng::seed_from_u64(7))
+            .with_redirect_sampling(0.5);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        for _ in 0..1000 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        let recorded = service.recent_clicks(slug!("a"), usize::MAX).unwrap();
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 1000);
+        assert!((400..600).contains(&recorded.len()), "{}", recorded.len());
+        assert_eq!(
+            service.in_window_redirects(slug!("a")),
+            Ok(recorded.len() as u64 * 2),
+        );
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_sample_rates() {
+        for rate in [0.0, 1.5, f64::NAN] {
+            let built = UrlShortenerService::new()
+                .with_redirect_sampling(rate)
+                .build();
+
+            assert!(matches!(built, Err(ConfigError::InvalidSampleRate)));
+        }
+    }
+
+    #[test]
+    fn per_link_sampling_overrides_the_default() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut service =
+            UrlShortenerService::new().with_rng(StdRng::seed_from_u64(7));
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        assert_eq!(
+            service.handle_set_redirect_sampling(slug!("a"), Some(1.5)),
+            Err(ShortenerError::InvalidSampleRate),
+        );
+        service
+            .handle_set_redirect_sampling(slug!("a"), Some(0.25))
+            .unwrap();
+
+        for _ in 0..400 {
+            service.handle_redirect(slug!("a")).unwrap();
+            service.handle_redirect(slug!("b")).unwrap();
+        }
+
+        let sampled =
+            service.recent_clicks(slug!("a"), usize::MAX).unwrap().len();
+        let unsampled =
+            service.recent_clicks(slug!("b"), usize::MAX).unwrap().len();
+        assert!((60..140).contains(&sampled), "{sampled}");
+        assert_eq!(unsampled, 400);
+        assert_eq!(
+            service.in_window_redirects(slug!("a")),
+            Ok(sampled as u64 * 4)
+        );
+        assert_eq!(service.referrer_total(""), sampled as u64 * 4 + 400);
+
+        let replayed =
+            UrlShortenerService::from_events(service.minimal_event_log())
+                .unwrap();
+        assert_eq!(replayed.links[&slug!("a")].sample_rate, Some(0.25));
+    }
+
+    #[test]
+    fn reserved_slugs_are_listed_sorted() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("used")))
+            .unwrap();
+        for slug in [slug!("login"), slug!("admin"), slug!("api")] {
+            service.reserve_slug(slug).unwrap();
+        }
+
+        assert_eq!(
+            service.reserve_slug(slug!("used")),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+        assert_eq!(
+            service.reserved_slugs(),
+            [slug!("admin"), slug!("api"), slug!("login")],
+        );
+
+        service.unreserve_slug(slug!("api"));
+        assert_eq!(service.reserved_slugs(), [slug!("admin"), slug!("login")]);
+        service
+            .handle_create_short_link(url("https://b.com"), Some(slug!("api")))
+            .unwrap();
+    }
+
+    #[test]
+    fn reserved_slugs_cannot_be_taken_by_any_command() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://x.com"), Some(slug!("x")))
+            .unwrap();
+        service.reserve_slug(slug!("res")).unwrap();
+
+        assert_eq!(
+            service.handle_create_short_link(
+                url("https://b.com"),
+                Some(slug!("res"))
+            ),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+        assert_eq!(
+            service.handle_rebrand(
+                slug!("x"),
+                slug!("res"),
+                url("https://y.com")
+            ),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+        assert_eq!(
+            service
+                .validate_batch(&[(url("https://z.com"), Some(slug!("res")))]),
+            [Err(ShortenerError::SlugAlreadyInUse)],
+        );
+    }
+
+    #[test]
+    fn reserved_slugs_are_skipped_by_generation() {
+        let mut service = UrlShortenerService::new()
+            .with_sequential_slugs(true)
+            .with_slug_length(1);
+        let next = service.peek_next_slug();
+
+        service.reserve_slug(next.clone()).unwrap();
+
+        let peeked = service.peek_next_slug();
+        let link = service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+        assert_ne!(peeked, next);
+        assert_eq!(link.slug, peeked);
+    }
+
+    #[test]
+    fn buffered_redirects_are_counted_after_flush() {
+        let mut service =
+            UrlShortenerService::new().with_buffered_redirects(true);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        service.handle_redirect(slug!("b")).unwrap();
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 0);
+
+        assert_eq!(service.flush_pending_redirects(), 4);
+
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 3);
+        assert_eq!(service.get_stats(slug!("b")).unwrap().redirects, 1);
+        assert_eq!(service.flush_pending_redirects(), 0);
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn unbuffered_redirects_have_nothing_to_flush() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.flush_pending_redirects(), 0);
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn custom_slugs_in_generated_format_are_rejected() {
+        let mut service = UrlShortenerService::new()
+            .with_slug_length(4)
+            .with_distinct_custom_slug_format(true);
+
+        assert_eq!(
+            service.handle_create_short_link(
+                url("https://a.com"),
+                Some(slug!("aB3x"))
+            ),
+            Err(ShortenerError::SlugFormatReserved),
+        );
+        for slug in [slug!("my-promo"), slug!("ab-x")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_create_short_link(url("https://a.com"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn custom_slugs_in_generated_format_are_allowed_by_default() {
+        let mut service = UrlShortenerService::new().with_slug_length(4);
+
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("aB3x")))
+            .unwrap();
+    }
+
+    #[test]
+    fn validate_batch_checks_custom_slug_format() {
+        let service =
+            UrlShortenerService::new().with_distinct_custom_slug_format(true);
+
+        let results = service.validate_batch(&[
+            (url("https://a.com"), Some(slug!("abcdef"))),
+            (url("https://b.com"), Some(slug!("ab-c"))),
+        ]);
+
+        assert_eq!(results, [Err(ShortenerError::SlugFormatReserved), Ok(())]);
+    }
+
+    #[test]
+    fn insecure_links_list_live_non_https_links() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("a"), "http://a.com"),
+            (slug!("b"), "https://b.com"),
+            (slug!("c"), "HTTP://c.com"),
+            (slug!("d"), "HTTPS://d.com"),
+            (slug!("e"), "http://e.com"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+        service.handle_disable(slug!("e")).unwrap();
+
+        assert_eq!(service.insecure_links(), [slug!("a"), slug!("c")]);
+    }
+
+    #[test]
+    fn orphaned_urls_lost_every_live_slug() {
+        let mut service = UrlShortenerService::new();
+        for (slug, target) in [
+            (slug!("a"), "https://x.com"),
+            (slug!("b"), "https://x.com"),
+            (slug!("c"), "https://y.com"),
+        ] {
+            service
+                .handle_create_short_link(url(target), Some(slug))
+                .unwrap();
+        }
+        let snapshot = service.snapshot();
+        assert!(service.orphaned_urls(&snapshot).is_empty());
+
+        service.handle_delete(slug!("a")).unwrap();
+        assert!(service.orphaned_urls(&snapshot).is_empty());
+        service.handle_delete(slug!("b")).unwrap();
+
+        assert_eq!(service.orphaned_urls(&snapshot), [url("https://x.com")]);
+    }
+
+    #[test]
+    fn resolve_cache_is_invalidated_on_delete() {
+        let mut service = UrlShortenerService::new().with_resolve_cache(2);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a.com")));
+
+        service.handle_delete(slug!("a")).unwrap();
+
+        assert_eq!(
+            service.resolve(&slug!("a")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn transaction_applies_every_operation() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        service
+            .handle_transaction(vec![
+                Operation::Create {
+                    url: url("https://b.com"),
+                    slug: Some(slug!("b")),
+                },
+                Operation::Create {
+                    url: url("https://g.com"),
+                    slug: None,
+                },
+                Operation::Change {
+                    slug: slug!("a"),
+                    new_url: url("https://a2.com"),
+                },
+                Operation::Rename {
+                    slug: slug!("b"),
+                    new_slug: slug!("bb"),
+                },
+                Operation::SetNote {
+                    slug: slug!("bb"),
+                    note: Some("note".into()),
+                },
+                Operation::Disable { slug: slug!("a") },
+            ])
+            .unwrap();
+
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://a2.com")));
+        assert_eq!(service.resolve(&slug!("bb")), Ok(url("https://b.com")));
+        assert_eq!(
+            service.get_stats(slug!("b")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.links.len(), 3);
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn failed_transaction_applies_nothing() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        let fingerprint = service.state_fingerprint();
+
+        let applied = service.handle_transaction(vec![
+            Operation::Create {
+                url: url("https://c.com"),
+                slug: Some(slug!("c")),
+            },
+            Operation::Change {
+                slug: slug!("missing"),
+                new_url: url("https://x.com"),
+            },
+            Operation::Disable { slug: slug!("a") },
+        ]);
+
+        assert_eq!(applied, Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.events().len(), 1);
+        assert_eq!(service.state_fingerprint(), fingerprint);
+        assert_eq!(
+            service.get_stats(slug!("c")),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn transaction_can_delete_and_recreate_a_slug() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("a")).unwrap();
+
+        service
+            .handle_transaction(vec![
+                Operation::Delete { slug: slug!("a") },
+                Operation::Create {
+                    url: url("https://c.com"),
+                    slug: Some(slug!("a")),
+                },
+            ])
+            .unwrap();
+
+        let order: Vec<_> = service
+            .links_in_creation_order()
+            .into_iter()
+            .map(|link| link.slug)
+            .collect();
+        assert_eq!(service.get_stats(slug!("a")).unwrap().redirects, 0);
+        assert_eq!(service.resolve(&slug!("a")), Ok(url("https://c.com")));
+        assert_eq!(order, [slug!("b"), slug!("a")]);
+        assert!(service.is_consistent());
+    }
+
+    #[test]
+    fn deleted_links_are_gone() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        service.handle_delete(slug!("a")).unwrap();
+
+        assert_eq!(
+            service.handle_delete(slug!("a")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(
+            service.get_stats(slug!("a")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert!(replayed.links.is_empty());
+    }
+
+    #[test]
+    fn redirect_momentum_compares_consecutive_windows() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let clock = clock::MockClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+        );
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        assert_eq!(service.redirect_momentum(slug!("a"), HOUR), Ok(0.0));
+
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        clock.advance(HOUR + Duration::from_secs(1));
+        for _ in 0..6 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        assert_eq!(service.redirect_momentum(slug!("a"), HOUR), Ok(2.0));
+    }
+
+    #[test]
+    fn redirect_momentum_after_a_silent_window_is_infinite() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        clock.advance(HOUR * 3);
+
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(
+            service.redirect_momentum(slug!("a"), HOUR),
+            Ok(f64::INFINITY),
+        );
+        assert_eq!(
+            service.redirect_momentum(slug!("a"), Duration::ZERO),
+            Err(ShortenerError::InvalidInterval),
+        );
+    }
+
+    #[test]
+    fn aliases_are_limited_per_link() {
+        let mut service = UrlShortenerService::new().with_max_aliases(2);
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service.handle_add_alias(slug!("a"), slug!("a1")).unwrap();
+        service.handle_add_alias(slug!("a1"), slug!("a2")).unwrap();
+
+        assert_eq!(
+            service.handle_add_alias(slug!("a"), slug!("a3")),
+            Err(ShortenerError::AliasLimitExceeded),
+        );
+        service.handle_add_alias(slug!("b"), slug!("b1")).unwrap();
+    }
+
+    #[test]
+    fn removing_an_alias_frees_a_slot() {
+        let mut service = UrlShortenerService::new().with_max_aliases(1);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_add_alias(slug!("a"), slug!("a1")).unwrap();
+
+        service.handle_remove_alias(slug!("a1")).unwrap();
+        service.handle_add_alias(slug!("a"), slug!("a2")).unwrap();
+
+        assert_eq!(
+            service.handle_redirect(slug!("a1")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(
+            service.handle_redirect(slug!("a2")).unwrap().slug,
+            slug!("a")
+        );
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        assert_eq!(replayed.resolve(&slug!("a2")), Ok(url("https://a.com")));
+    }
+
+    #[test]
+    fn link_ids_follow_creation_order() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("c"), slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        service
+            .handle_move_namespace(&slug!("a"), "", "ns")
+            .unwrap();
+
+        assert_eq!(service.link_id(&slug!("c")), Ok(1));
+        assert_eq!(service.link_id(&slug!("ns/a")), Ok(2));
+        assert_eq!(service.link_id(&slug!("b")), Ok(3));
+    }
+
+    #[test]
+    fn link_ids_survive_replay_and_snapshots() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+        let mut restored =
+            UrlShortenerService::from_snapshot(&service.snapshot());
+        restored
+            .handle_create_short_link(url("https://a.com"), Some(slug!("c")))
+            .unwrap();
+
+        assert_eq!(replayed.link_id(&slug!("b")), Ok(2));
+        assert_eq!(restored.link_id(&slug!("b")), Ok(2));
+        assert_eq!(restored.link_id(&slug!("c")), Ok(3));
+    }
+
+    #[test]
+    fn base62_round_trips() {
+        for id in [0, 1, 61, 62, 3843, 3844, u64::MAX] {
+            assert_eq!(decode_base62(&encode_base62(id)), Some(id));
+        }
+        assert_eq!(encode_base62(61), "z");
+        assert_eq!(encode_base62(62), "10");
+        assert_eq!(decode_base62(""), None);
+        assert_eq!(decode_base62("a-"), None);
+        assert_eq!(decode_base62("zzzzzzzzzzzzzzzzzzzz"), None);
+    }
+
+    #[test]
+    fn detailed_counts_split_organic_and_seeded() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_seed_redirects(slug!("a"), 100).unwrap();
+        for _ in 0..5 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        let expected = CountBreakdown {
+            organic: 5,
+            seeded: 100,
+            total: 105,
+        };
+
+        assert_eq!(service.get_stats_detailed_counts(slug!("a")), Ok(expected));
+
+        service.compact();
+        assert_eq!(service.get_stats_detailed_counts(slug!("a")), Ok(expected));
+    }
+
+    #[test]
+    fn checkpointed_redirects_stay_organic() {
+        let mut service = UrlShortenerService::new().with_log_redirects(false);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+        service.checkpoint_redirects();
+
+        let replayed =
+            UrlShortenerService::from_events(service.events().to_vec())
+                .unwrap();
+
+        assert_eq!(
+            replayed.get_stats_detailed_counts(slug!("a")),
+            Ok(CountBreakdown {
+                organic: 3,
+                seeded: 0,
+                total: 3,
+            }),
+        );
+    }
+
+    #[test]
+    fn template_placeholders_are_expanded_on_redirect() {
+        let clock = clock::MockClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        );
+        let mut service = UrlShortenerService::new().with_clock(clock);
+        let template = "https://dest.com/?ref={slug}&t={ts}";
+        let link = service
+            .handle_create_template(template.into(), Some(slug!("p")))
+            .unwrap();
+
+        let redirect = service.handle_redirect(slug!("p")).unwrap();
+
+        assert_eq!(link.url, url(template));
+        assert_eq!(redirect.url, url("https://dest.com/?ref=p&t=1700000000"));
+        assert_eq!(service.resolve(&slug!("p")), Ok(url(template)));
+    }
+
+    #[test]
+    fn templates_with_unknown_placeholders_are_rejected() {
+        let mut service = UrlShortenerService::new();
+
+        for template in [
+            "https://dest.com/?u={user}",
+            "https://dest.com/?u={slug",
+            "https://dest.com/?u=}",
+        ] {
+            assert_eq!(
+                service.handle_create_template(template.into(), None),
+                Err(ShortenerError::InvalidTemplate),
+            );
+        }
+        assert!(service.events().is_empty());
+    }
+
+    #[test]
+    fn for_each_link_visits_live_links_in_slug_order() {
+        let mut service = UrlShortenerService::new().with_url_obfuscation(b"k");
+        for slug in [slug!("d"), slug!("b"), slug!("a"), slug!("c")] {
+            let target = url(&format!("https://{}.com", slug.0));
+            service
+                .handle_create_short_link(target, Some(slug))
+                .unwrap();
+        }
+        service.handle_redirect(slug!("b")).unwrap();
+        service.handle_disable(slug!("c")).unwrap();
+
+        let mut visited = Vec::new();
+        service.for_each_link(|slug, link, redirects| {
+            assert_eq!(slug, &link.slug);
+            visited.push(Stats {
+                link: link.clone(),
+                redirects,
+            });
+        });
+
+        let expected: Vec<_> = service
+            .query()
+            .filter(|stats| stats.link.slug != slug!("c"))
+            .collect();
+        assert_eq!(visited, expected);
+        assert_eq!(visited[1].link.url, url("https://b.com"));
+        assert_eq!(visited[1].redirects, 1);
+    }
+
+    #[test]
+    fn ttls_outside_the_bounds_are_rejected() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let mut service = UrlShortenerService::new()
+            .with_ttl_bounds(Some(HOUR), Some(HOUR * 24));
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        for ttl in [Some(HOUR / 2), Some(HOUR * 25)] {
+            assert_eq!(
+                service.handle_set_ttl(slug!("a"), ttl),
+                Err(ShortenerError::TtlOutOfBounds),
+            );
+        }
+        assert_eq!(service.handle_set_ttl(slug!("a"), Some(HOUR)), Ok(()));
+        assert_eq!(service.handle_set_ttl(slug!("a"), Some(HOUR * 24)), Ok(()));
+    }
+
+    #[test]
+    fn ttl_bounds_apply_on_creation() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let mut service = UrlShortenerService::new()
+            .with_ttl_bounds(Some(HOUR), Some(HOUR * 24));
+        let request = |ttl| CreateRequest {
+            url: url("https://b.com"),
+            slug: Some(slug!("b")),
+            ttl,
+            note: None,
+            metadata: HashMap::new(),
+            redirect_status: None,
+        };
+
+        assert_eq!(
+            service.handle_create_full(request(Some(HOUR * 48))),
+            Err(ShortenerError::TtlOutOfBounds),
+        );
+        assert!(service.events().is_empty());
+        service.handle_create_full(request(Some(HOUR * 2))).unwrap();
+    }
+
+    #[test]
+    fn ttl_without_maximum_may_be_unset() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let mut service =
+            UrlShortenerService::new().with_ttl_bounds(Some(HOUR), None);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(service.handle_set_ttl(slug!("a"), None), Ok(()));
+    }
+
+    #[test]
+    fn ttl_with_maximum_may_be_unset() {
+        const HOUR: Duration = Duration::from_secs(3600);
+        let mut service =
+            UrlShortenerService::new().with_ttl_bounds(None, Some(HOUR));
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(service.handle_set_ttl(slug!("a"), None), Ok(()));
+        assert!(matches!(
+            service.events().last(),
+            Some(Event::ShortLinkExpirySet {
+                expires_at: None,
+                ..
+            }),
+        ));
+    }
+
+    #[test]
+    fn analytics_round_trip_without_touching_links() {
+        let mut source = UrlShortenerService::new();
+        let mut target = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            let name = &slug.0;
+            source
+                .handle_create_short_link(
+                    url(&format!("https://{name}.com")),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            target
+                .handle_create_short_link(
+                    url(&format!("https://other-{name}.com")),
+                    Some(slug),
+                )
+                .unwrap();
+        }
+        source
+            .handle_redirect_with_source(slug!("a"), "mail".into())
+            .unwrap();
+        source
+            .handle_redirect_with_referrer(
+                slug!("a"),
+                Some(&url("https://r.com")),
+            )
+            .unwrap();
+        source.handle_seed_redirects(slug!("b"), 10).unwrap();
+        source.handle_redirect(slug!("b")).unwrap();
+        let snapshot = source.analytics_snapshot();
+
+        target.restore_analytics(snapshot.clone()).unwrap();
+
+        assert_eq!(target.analytics_snapshot(), snapshot);
+        assert_eq!(target.resolve(&slug!("a")), Ok(url("https://other-a.com")));
+        assert_eq!(
+            target.get_stats_detailed_counts(slug!("b")),
+            source.get_stats_detailed_counts(slug!("b")),
+        );
+        assert_eq!(
+            target.recent_clicks(slug!("a"), 5),
+            source.recent_clicks(slug!("a"), 5),
+        );
+        assert!(target.is_consistent());
+    }
+
+    #[test]
+    fn analytics_of_unknown_slugs_are_not_restored() {
+        let mut source = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            source
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            source.handle_redirect(slug).unwrap();
+        }
+        let mut target = UrlShortenerService::new();
+        target
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+
+        assert_eq!(
+            target.restore_analytics(source.analytics_snapshot()),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(target.get_stats(slug!("a")).unwrap().redirects, 0);
+    }
+
+    #[test]
+    fn similar_slug_pairs_are_within_the_distance() {
+        let mut service = UrlShortenerService::new();
+        for slug in
+            ["paypal", "paypa1", "github", "gihtub", "zzzzzz", "paypal-x"]
+        {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(Slug(slug.into())),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.find_similar_slug_pairs(1),
+            [(slug!("paypa1"), slug!("paypal"))],
+        );
+        assert_eq!(
+            service.find_similar_slug_pairs(2),
+            [
+                (slug!("gihtub"), slug!("github")),
+                (slug!("paypa1"), slug!("paypal")),
+                (slug!("paypal"), slug!("paypal-x")),
+            ],
+        );
+    }
+
+    #[test]
+    fn edit_distance_is_levenshtein() {
+        let distance = |a: &str, b: &str| {
+            edit_distance(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("flaw", "lawn"), 2);
+    }
+
+    #[test]
+    fn config_round_trip_preserves_behavior() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut original = UrlShortenerService::new()
+            .with_slug_length(9)
+            .with_slug_alphabet("abcdef")
+            .with_slug_denylist(&["bad"])
+            .with_distinct_custom_slug_format(true)
+            .with_ttl_bounds(
+                Some(Duration::from_secs(60)),
+                Some(Duration::from_secs(600)),
+            )
+            .with_max_aliases(1)
+            .with_read_auditing(true)
+            .build()
+            .ok()
+            .unwrap();
+        original.reserve_slug(slug!("mine")).unwrap();
+
+        let rebuilt = UrlShortenerService::from_config(original.config())
+            .ok()
+            .unwrap();
+
+        assert_eq!(rebuilt.reserved_slugs(), [slug!("mine")]);
+        let mut generated = Vec::new();
+        for service in [original, rebuilt] {
+            let mut service = service.with_rng(StdRng::seed_from_u64(3));
+            let link = service
+                .handle_create_short_link(url("https://x.com"), None)
+                .unwrap();
+            assert_eq!(
+                service.handle_create_short_link(
+                    url("https://y.com"),
+                    Some(slug!("mine"))
+                ),
+                Err(ShortenerError::SlugAlreadyInUse),
+            );
+            assert_eq!(
+                service.handle_set_ttl(
+                    link.slug.clone(),
+                    Some(Duration::from_secs(1))
+                ),
+                Err(ShortenerError::TtlOutOfBounds),
+            );
+            service.resolve(&link.slug).unwrap();
+            assert_eq!(service.read_audit().len(), 1);
+            generated.push(link.slug);
+        }
+        assert_eq!(generated[0], generated[1]);
+        assert_eq!(generated[0].0.len(), 9);
+    }
+
+    #[test]
+    fn shuffled_links_are_deterministic_per_seed() {
+        let mut service = UrlShortenerService::new();
+        for i in 0..20 {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(Slug(format!("s{i}"))),
+                )
+                .unwrap();
+        }
+
+        let shuffled = service.shuffled_links(1, 20);
+
+        assert_eq!(shuffled, service.shuffled_links(1, 20));
+        assert_ne!(shuffled, service.shuffled_links(2, 20));
+        assert_eq!(service.shuffled_links(1, 5), shuffled[..5]);
+        let mut slugs: Vec<_> =
+            shuffled.into_iter().map(|link| link.slug.0).collect();
+        slugs.sort_unstable_by_key(|slug| slug[1..].parse::<u32>().unwrap());
+        assert_eq!(slugs, (0..20).map(|i| format!("s{i}")).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn referrer_total_sums_across_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+        let partner = url("https://partner.com/x");
+        for _ in 0..2 {
+            service
+                .handle_redirect_with_referrer(slug!("a"), Some(&partner))
+                .unwrap();
+        }
+        service
+            .handle_redirect_with_referrer(slug!("b"), Some(&partner))
+            .unwrap();
+        service
+            .handle_redirect_with_referrer(slug!("b"), None)
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+
+        assert_eq!(service.referrer_total("https://partner.com/x"), 3);
+        assert_eq!(service.referrer_total(""), 2);
+        assert_eq!(service.referrer_total("(direct)"), 2);
+        assert_eq!(service.referrer_total("https://other.com"), 0);
+    }
+
+    #[test]
+    fn referrer_total_counts_merged_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("b")] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service
+                .handle_redirect_with_referrer(
+                    slug,
+                    Some(&url("https://p.com")),
+                )
+                .unwrap();
+        }
+
+        service.consolidate_duplicates();
+
+        assert_eq!(service.referrer_total("https://p.com"), 2);
+    }
+
+    #[test]
+    fn cloned_link_shares_everything_but_stats() {
+        let clock = clock::MockClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+        );
+        let mut service = UrlShortenerService::new().with_clock(clock);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_metadata(slug!("a"), "team".into(), Some("x".into()))
+            .unwrap();
+        service
+            .handle_set_note(slug!("a"), Some("hi".into()))
+            .unwrap();
+        service
+            .handle_set_ttl(slug!("a"), Some(Duration::from_secs(600)))
+            .unwrap();
+        service.handle_seed_redirects(slug!("a"), 3).unwrap();
+        service.handle_disable(slug!("a")).unwrap();
+
+        let link = service.handle_clone_link(slug!("a"), slug!("c")).unwrap();
+
+        let clone = service.metadata(&slug!("c")).unwrap();
+        let source = service.metadata(&slug!("a")).unwrap();
+        assert_eq!(
+            link,
+            ShortLink::from_parts(slug!("c"), url("https://a.com"))
+        );
+        assert_eq!(
+            service.links_with_metadata("team", Some("x")),
+            [slug!("a"), slug!("c")],
+        );
+        assert_eq!((clone.redirects, source.redirects), (0, 3));
+        assert!(!clone.enabled);
+        assert_eq!(clone.note.as_deref(), Some("hi"));
+        assert_eq!(clone.ttl_remaining, source.ttl_remaining);
+        assert_ne!(service.link_id(&slug!("a")), service.link_id(&slug!("c")));
+    }
+
+    #[test]
+    fn clone_link_rejects_missing_and_taken_slugs() {
+        let mut service = UrlShortenerService::new();
+        for slug in [slug!("a"), slug!("c")] {
+            service
+                .handle_create_short_link(url("https://a.com"), Some(slug))
+                .unwrap();
+        }
+
+        assert_eq!(
+            service.handle_clone_link(slug!("zz"), slug!("d")),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(
+            service.handle_clone_link(slug!("a"), slug!("c")),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+        assert_eq!(service.events().len(), 2);
+
+        let mut service = service.with_unique_urls(true);
+        assert_eq!(
+            service.handle_clone_link(slug!("a"), slug!("d")),
+            Err(ShortenerError::UrlAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn cloned_link_expires_relative_to_its_creation() {
+        const MINUTE: Duration = Duration::from_secs(60);
+        let clock = clock::MockClock::new(SystemTime::UNIX_EPOCH);
+        let mut service = UrlShortenerService::new().with_clock(clock.clone());
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service
+            .handle_set_ttl(slug!("a"), Some(MINUTE * 10))
+            .unwrap();
+        clock.advance(MINUTE * 4);
+
+        service.handle_clone_link(slug!("a"), slug!("c")).unwrap();
+
+        let ttl = |slug| service.metadata(&slug).unwrap().ttl_remaining;
+        assert_eq!(ttl(slug!("a")), Some(MINUTE * 6));
+        assert_eq!(ttl(slug!("c")), Some(MINUTE * 10));
+    }
+
+    #[test]
+    fn counter_snapshot_matches_live_counts() {
+        let mut service = UrlShortenerService::new();
+        for (slug, redirects) in
+            [(slug!("a"), 0), (slug!("b"), 1), (slug!("c"), 2)]
+        {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug.clone()),
+                )
+                .unwrap();
+            service.handle_seed_redirects(slug, redirects).unwrap();
+        }
+        service.handle_delete(slug!("a")).unwrap();
+
+        let snapshot = service.counter_snapshot();
+
+        assert_eq!(snapshot, HashMap::from([(slug!("b"), 1), (slug!("c"), 2)]));
+        for (slug, redirects) in snapshot {
+            assert_eq!(service.get_stats(slug).unwrap().redirects, redirects);
+        }
+    }
+
+    #[test]
+    fn append_only_log_is_replayable() {
+        let mut service = UrlShortenerService::new();
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        service.handle_redirect(slug!("a")).unwrap();
+        service
+            .handle_set_note(slug!("a"), Some("note".into()))
+            .unwrap();
+
+        assert!(service.is_replayable());
+    }
+
+    #[test]
+    fn lossy_operations_make_the_log_unreplayable() {
+        let mut compacted = UrlShortenerService::new();
+        let mut rotated = UrlShortenerService::new();
+        for service in [&mut compacted, &mut rotated] {
+            service
+                .handle_create_short_link(
+                    url("https://a.com"),
+                    Some(slug!("a")),
+                )
+                .unwrap();
+            service.handle_redirect(slug!("a")).unwrap();
+        }
+
+        compacted.compact();
+        rotated.rotate_log();
+
+        assert!(!compacted.is_replayable());
+        assert!(!rotated.is_replayable());
+    }
+
+    #[test]
+    fn unlogged_redirects_make_the_log_unreplayable() {
+        let mut service = UrlShortenerService::new().with_log_redirects(false);
+        service
+            .handle_create_short_link(url("https://a.com"), Some(slug!("a")))
+            .unwrap();
+        assert!(service.is_replayable());
+
+        service.handle_redirect(slug!("a")).unwrap();
+        assert!(!service.is_replayable());
+        service.checkpoint_redirects();
+        assert!(!service.is_replayable());
+    }
 }